@@ -0,0 +1,132 @@
+// Render a cube (or an OBJ / glTF model, or a generated shape) into an offscreen texture without
+// opening a window and save it as png. Uses the software fallback adapter, so this also
+// works on CI machines without a GPU:
+//
+//     cargo run --example headless -- screenshot.png [./src/assets/cube/cube.obj]
+//     cargo run --example headless -- screenshot.png [model.gltf|model.glb]
+//     cargo run --example headless -- screenshot.png [sphere|icosphere|cylinder|capsule|plane|torus|rounded_box]
+
+use glam::{Quat, Vec3};
+use rust_wgpu_pong3d::{
+    assets::AssetServer,
+    camera::{Camera, Projection},
+    geometries::CubeGeometry,
+    mesh::procedural,
+    gpu::Gpu,
+    render_target::RenderTarget,
+    renderers::light_buffer::{DirectionalLight, LightBuffer, Lights},
+    renderers::material_shader::MaterialShader,
+    renderers::pipeline_cache::PipelineCache,
+    model::ModelGeometry,
+    scene::{Scene, Transform},
+};
+
+const WIDTH : u32 = 320;
+const HEIGHT : u32 = 240;
+
+fn shape(name : &str) -> Option<ModelGeometry> {
+    Some(match name {
+        "sphere" => procedural::uv_sphere(1.0, 32, 16),
+        "icosphere" => procedural::icosphere(1.0, 2),
+        "cylinder" => procedural::cylinder(0.7, 1.6, 32, 1),
+        "capsule" => procedural::capsule(0.5, 2.0, 32, 8),
+        "plane" => procedural::plane(2.0, 2.0, 4, 4),
+        "torus" => procedural::torus(0.8, 0.3, 48, 24),
+        "rounded_box" => procedural::rounded_box(Vec3::new(1.6, 1.2, 1.0), 0.25, 6),
+        _ => return None,
+    })
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let path = std::env::args().nth(1).unwrap_or_else(|| "screenshot.png".into());
+    let model_path = std::env::args().nth(2);
+
+    let gpu = pollster::block_on(Gpu::headless(true))?;
+    let Gpu { device, queue, .. } = &gpu;
+
+    let target = RenderTarget::offscreen(device, WIDTH, HEIGHT, wgpu::TextureFormat::Rgba8UnormSrgb);
+
+    let mut pipelines = PipelineCache::new();
+    let camera = Camera::new(
+        device,
+        &mut pipelines,
+        Projection::perspective(90.0_f32.to_radians(), 0.01, 5.0),
+        WIDTH,
+        HEIGHT,
+        Vec3::new(0.0, 0.0, -3.0),
+        Vec3::ZERO,
+        Vec3::Y,
+        "Headless camera");
+
+    let lights = Lights::new(DirectionalLight::new(Vec3::new(-0.5, -0.5, 1.0), Vec3::ONE, 1.0));
+    let lights = LightBuffer::new(device, lights, "Headless lights");
+
+    let mut assets = AssetServer::new();
+    let mut scene = Scene::new();
+    let root = scene.add_node("root", Transform::from_rotation(
+        Quat::from_rotation_y(30.0_f32.to_radians()) * Quat::from_rotation_x(20.0_f32.to_radians())), None);
+
+    let is_model = |name : &str| [".obj", ".gltf", ".glb"].iter().any(|ext| name.ends_with(ext));
+    let mut renderers = match model_path {
+        Some(name) if !is_model(&name) => {
+            let geo = shape(&name).ok_or_else(|| format!("unknown shape {name}"))?;
+            vec![MaterialShader::new(
+                device,
+                &mut pipelines,
+                target.format(),
+                &geo.positions,
+                &geo.normals,
+                &geo.colors,
+                &geo.tex_coords,
+                &geo.indices,
+                assets.white_texture(device, queue),
+                &lights)]
+        }
+        Some(model_path) => {
+            let model = assets.load_model(device, queue, &model_path)?;
+            model.instantiate(&mut scene, Some(root)).into_iter()
+                .map(|(node, index)| {
+                    let mesh = &model.meshes[index];
+                    let mut renderer = MaterialShader::from_mesh(device, queue, &mut pipelines, &mut assets, target.format(), mesh, model.material(mesh), &lights);
+                    renderer.set_node(Some(node));
+                    renderer
+                })
+                .collect()
+        }
+        None => {
+            let geo = CubeGeometry::new();
+            vec![MaterialShader::new(
+                device,
+                &mut pipelines,
+                target.format(),
+                geo.positions,
+                geo.normals,
+                geo.colors,
+                geo.tex_coords,
+                geo.indices,
+                assets.white_texture(device, queue),
+                &lights)]
+        }
+    };
+
+    // shapes are not part of the model hierarchy, attach them to the root
+    for renderer in renderers.iter_mut().filter(|r| r.node().is_none()) {
+        renderer.set_node(Some(root));
+    }
+    scene.update_world_matrices();
+    renderers.iter_mut().for_each(|r| r.update_transform(queue, &scene));
+
+    let frame = target.acquire()?;
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+        label: Some("Headless Encoder"),
+    });
+    {
+        let mut render_pass = target.begin_render_pass(&mut encoder, &frame, wgpu::Color::BLACK);
+        renderers.iter().for_each(|r| r.draw(&mut render_pass, &camera));
+    }
+    queue.submit(std::iter::once(encoder.finish()));
+
+    target.read_pixels(device, queue)?.save(&path)?;
+    println!("saved {path}");
+    Ok(())
+}
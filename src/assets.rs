@@ -0,0 +1,292 @@
+use std::{
+    collections::HashMap,
+    hash::Hash,
+    ops::Deref,
+    path::{Path, PathBuf},
+    sync::{Arc, Weak},
+};
+
+use crate::{
+    errors::WvtError,
+    model::{Material, Model, ModelGeometry},
+    texture2d::Texture2d,
+};
+
+/// Shared, reference counted handle to an asset.
+/// The asset (and its GPU resources) is freed when the last handle drops.
+pub struct Handle<T> {
+    asset : Arc<T>,
+}
+
+impl<T> Handle<T> {
+    /// wrap an asset that wasn't loaded through an `AssetServer`
+    pub fn new(asset : T) -> Self {
+        Self { asset : Arc::new(asset) }
+    }
+
+    /// number of handles to this asset
+    pub fn strong_count(&self) -> usize {
+        Arc::strong_count(&self.asset)
+    }
+}
+
+impl<T> Clone for Handle<T> {
+    fn clone(&self) -> Self {
+        Self { asset : self.asset.clone() }
+    }
+}
+
+impl<T> Deref for Handle<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.asset
+    }
+}
+
+// handles are equal when they point at the same asset
+impl<T> PartialEq for Handle<T> {
+    fn eq(&self, other : &Self) -> bool {
+        Arc::ptr_eq(&self.asset, &other.asset)
+    }
+}
+
+impl<T> Eq for Handle<T> {}
+
+impl<T : std::fmt::Debug> std::fmt::Debug for Handle<T> {
+    fn fmt(&self, f : &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("Handle").field(&self.asset).finish()
+    }
+}
+
+/// Assets of one type by key. Only weak references are kept,
+/// so the cache never keeps an asset alive on its own.
+struct AssetCache<K, T> {
+    entries : HashMap<K, Weak<T>>,
+}
+
+impl<K, T> Default for AssetCache<K, T> {
+    fn default() -> Self {
+        Self { entries : HashMap::new() }
+    }
+}
+
+impl<K : Eq + Hash, T> AssetCache<K, T> {
+    fn get(&self, key : &K) -> Option<Handle<T>> {
+        self.entries.get(key)?.upgrade().map(|asset| Handle { asset })
+    }
+
+    // dropped assets are forgotten whenever a new one comes in
+    fn insert(&mut self, key : K, asset : T) -> Handle<T> {
+        self.remove_unused();
+        let handle = Handle::new(asset);
+        self.entries.insert(key, Arc::downgrade(&handle.asset));
+        handle
+    }
+
+    fn get_or_insert_with(&mut self, key : K, f : impl FnOnce() -> T) -> Handle<T> {
+        match self.get(&key) {
+            Some(handle) => handle,
+            None => self.insert(key, f()),
+        }
+    }
+
+    fn get_or_try_insert_with<E>(&mut self, key : K, f : impl FnOnce() -> Result<T, E>) -> Result<Handle<T>, E> {
+        match self.get(&key) {
+            Some(handle) => Ok(handle),
+            None => Ok(self.insert(key, f()?)),
+        }
+    }
+
+    fn remove_unused(&mut self) {
+        self.entries.retain(|_, asset| asset.strong_count() > 0);
+    }
+
+    /// number of assets that are still alive
+    fn len(&self) -> usize {
+        self.entries.values().filter(|asset| asset.strong_count() > 0).count()
+    }
+}
+
+/// Loads textures, models, geometry and materials by path and hands out
+/// shared handles, so every file is only read and uploaded once while in use.
+#[derive(Default)]
+pub struct AssetServer {
+    textures : AssetCache<PathBuf, Texture2d>,
+    linear_textures : AssetCache<PathBuf, Texture2d>,
+    builtin_textures : AssetCache<&'static str, Texture2d>,
+    // images embedded in a model file, by image index and sRGB flag
+    embedded_textures : AssetCache<(PathBuf, usize, bool), Texture2d>,
+    models : AssetCache<PathBuf, Model>,
+    // meshes and materials of a model file, by index / name
+    geometries : AssetCache<(PathBuf, usize), ModelGeometry>,
+    materials : AssetCache<(PathBuf, String), Material>,
+}
+
+impl std::fmt::Debug for AssetServer {
+    fn fmt(&self, f : &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AssetServer")
+            .field("textures", &self.num_textures())
+            .field("models", &self.models.len())
+            .field("geometries", &self.geometries.len())
+            .field("materials", &self.materials.len())
+            .finish()
+    }
+}
+
+// the same file reached through different relative paths maps to one key
+fn asset_key(path : &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
+}
+
+impl AssetServer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn load_texture(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        path : impl AsRef<Path> ) -> Result<Handle<Texture2d>, WvtError> {
+
+        let path = path.as_ref();
+        self.textures.get_or_try_insert_with(asset_key(path), || Texture2d::from_path(device, queue, path))
+    }
+
+    /// texture holding data rather than colors (normal maps, masks), not sRGB decoded
+    pub fn load_linear_texture(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        path : impl AsRef<Path> ) -> Result<Handle<Texture2d>, WvtError> {
+
+        let path = path.as_ref();
+        self.linear_textures.get_or_try_insert_with(asset_key(path), || Texture2d::from_path_linear(device, queue, path))
+    }
+
+    /// shared 1x1 white texture, used when a material has no texture
+    pub fn white_texture(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) -> Handle<Texture2d> {
+        self.builtin_textures.get_or_insert_with("white", || Texture2d::white(device, queue))
+    }
+
+    pub fn load_model(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        path : impl AsRef<Path> ) -> Result<Handle<Model>, WvtError> {
+
+        let path = path.as_ref();
+        let key = asset_key(path);
+        if let Some(model) = self.models.get(&key) {
+            return Ok(model);
+        }
+        let model = Model::load(device, queue, self, path)?;
+        Ok(self.models.insert(key, model))
+    }
+
+    /// image `index` embedded in the model file at `path`, created by `f` when not loaded yet.
+    /// The same image can be used as color (sRGB) and as data (linear) texture.
+    pub fn embedded_texture_or_try_insert_with(
+        &mut self,
+        path : &Path,
+        index : usize,
+        srgb : bool,
+        f : impl FnOnce() -> Result<Texture2d, WvtError> ) -> Result<Handle<Texture2d>, WvtError> {
+
+        self.embedded_textures.get_or_try_insert_with((asset_key(path), index, srgb), f)
+    }
+
+    /// geometry of mesh `index` in the model file at `path`, created by `f` when not loaded yet
+    pub fn geometry_or_insert_with(
+        &mut self,
+        path : &Path,
+        index : usize,
+        f : impl FnOnce() -> ModelGeometry ) -> Handle<ModelGeometry> {
+
+        self.geometries.get_or_insert_with((asset_key(path), index), f)
+    }
+
+    /// like `geometry_or_insert_with`, `f` can fail and nothing is cached then
+    pub fn geometry_or_try_insert_with<E>(
+        &mut self,
+        path : &Path,
+        index : usize,
+        f : impl FnOnce() -> Result<ModelGeometry, E> ) -> Result<Handle<ModelGeometry>, E> {
+
+        self.geometries.get_or_try_insert_with((asset_key(path), index), f)
+    }
+
+    /// material `name` of the model file at `path`, created by `f` when not loaded yet.
+    /// `f` gets the server to load the material textures through.
+    pub fn material_or_try_insert_with(
+        &mut self,
+        path : &Path,
+        name : &str,
+        f : impl FnOnce(&mut Self) -> Result<Material, WvtError> ) -> Result<Handle<Material>, WvtError> {
+
+        let key = (asset_key(path), name.to_string());
+        if let Some(material) = self.materials.get(&key) {
+            return Ok(material);
+        }
+        let material = f(self)?;
+        Ok(self.materials.insert(key, material))
+    }
+
+    /// forget assets that have no handles left
+    pub fn remove_unused(&mut self) {
+        self.textures.remove_unused();
+        self.linear_textures.remove_unused();
+        self.builtin_textures.remove_unused();
+        self.embedded_textures.remove_unused();
+        self.models.remove_unused();
+        self.geometries.remove_unused();
+        self.materials.remove_unused();
+    }
+
+    /// number of textures that are still in use
+    pub fn num_textures(&self) -> usize {
+        self.textures.len() + self.linear_textures.len() + self.builtin_textures.len() + self.embedded_textures.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn handles_share_the_cached_asset() {
+        let mut cache = AssetCache::default();
+        let a = cache.get_or_insert_with("a", || 1);
+        let b = cache.get_or_insert_with("a", || 2);
+        assert_eq!(a, b);
+        assert_eq!(*b, 1);
+        assert_eq!(a.strong_count(), 2);
+    }
+
+    #[test]
+    fn dropped_assets_are_pruned_on_insert() {
+        let mut cache = AssetCache::default();
+        let a = cache.get_or_insert_with("a", || 1);
+        let b = cache.get_or_insert_with("b", || 2);
+        drop(a);
+        assert!(cache.get(&"a").is_none());
+        assert_eq!(cache.len(), 1);
+
+        let _c = cache.get_or_insert_with("c", || 3);
+        assert_eq!(cache.entries.len(), 2);
+        assert!(!cache.entries.contains_key("a"));
+
+        // a dropped asset is loaded again
+        drop(b);
+        assert_eq!(*cache.get_or_insert_with("b", || 4), 4);
+    }
+
+    #[test]
+    fn failed_loads_are_not_cached() {
+        let mut cache = AssetCache::<&str, i32>::default();
+        assert!(cache.get_or_try_insert_with("a", || Err(())).is_err());
+        assert!(cache.entries.is_empty());
+        assert_eq!(*cache.get_or_try_insert_with::<()>("a", || Ok(1)).unwrap(), 1);
+    }
+}
@@ -1,3 +1,4 @@
 
 pub mod geometry;
-pub mod uniform;
\ No newline at end of file
+pub mod uniform;
+pub mod instance;
@@ -1,5 +1,7 @@
+use glam::Vec3;
 use wgpu::util::DeviceExt;
-use crate::geometries::{ColorElement, NormalElement, PositionElement, TexCoordElement};
+use crate::mesh::{Aabb, BoundingSphere};
+use crate::geometries::{ColorElement, NormalElement, PositionElement, TangentElement, TexCoordElement};
 //use super::super::geometries::Vertex;
 
 #[derive(Debug)]
@@ -9,9 +11,12 @@ pub struct GeometryBuffer {
     pub texcoord_buffer : wgpu::Buffer,
     pub index_buffer : Option<wgpu::Buffer>,
     pub normal_buffer : Option<wgpu::Buffer>,
+    pub tangent_buffer : Option<wgpu::Buffer>,
     pub num_vertices : u32,
     pub num_indices : u32,
-
+    /// object space bounds of the positions, `None` without vertices
+    pub aabb : Option<Aabb>,
+    pub bounding_sphere : Option<BoundingSphere>,
 }
 
 impl GeometryBuffer {
@@ -32,7 +37,11 @@ impl GeometryBuffer {
         );
         let num_vertices = positions.len() as u32;
 
-        let normal_buffer = if normals.len() > 0 {
+        let points : Vec<Vec3> = positions.iter().map(|p| Vec3::from(p.position)).collect();
+        let aabb = Aabb::from_points(points.iter().copied());
+        let bounding_sphere = BoundingSphere::from_points(&points);
+
+        let normal_buffer = if !normals.is_empty() {
             // create index buffer
             Some( device.create_buffer_init(
         &wgpu::util::BufferInitDescriptor {
@@ -62,7 +71,7 @@ impl GeometryBuffer {
             }
         );
       
-        let index_buffer = if indices.len() > 0 {
+        let index_buffer = if !indices.is_empty() {
             // create index buffer
             Some( device.create_buffer_init(
         &wgpu::util::BufferInitDescriptor {
@@ -82,14 +91,27 @@ impl GeometryBuffer {
         Self {
             position_buffer,
             normal_buffer,
+            tangent_buffer : None,
             texcoord_buffer,
             color_buffer,
             index_buffer,
             num_vertices,
             num_indices,
+            aabb,
+            bounding_sphere,
         }
     }
 
+    /// add per vertex tangents, used for normal mapping
+    pub fn set_tangents(&mut self, device : &wgpu::Device, tangents : &[TangentElement]) {
+        self.tangent_buffer = Some( device.create_buffer_init(
+            &wgpu::util::BufferInitDescriptor {
+                label: Some("Tangent Buffer"),
+                contents: bytemuck::cast_slice(tangents),
+                usage: wgpu::BufferUsages::VERTEX,
+            }));
+    }
+
     // pub fn descriptors() -> &'static[ wgpu::VertexBufferLayout<'static> ] {
     //     &[PositionElement::desc(), ColorElement::desc()]
     // }
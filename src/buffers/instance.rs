@@ -0,0 +1,52 @@
+use crate::geometries::InstanceElement;
+
+/// Vertex buffer with per instance data, grows when more instances are written than fit
+#[derive(Debug)]
+pub struct InstanceBuffer {
+    pub buffer : wgpu::Buffer,
+    capacity : usize,
+    num_instances : u32,
+}
+
+impl InstanceBuffer {
+    pub fn new(device : &wgpu::Device, instances : &[InstanceElement]) -> Self {
+        let capacity = instances.len().max(1);
+        let buffer = Self::create_buffer(device, capacity);
+        buffer.slice(..).get_mapped_range_mut()[..std::mem::size_of_val(instances)]
+            .copy_from_slice(bytemuck::cast_slice(instances));
+        buffer.unmap();
+
+        Self {
+            buffer,
+            capacity,
+            num_instances : instances.len() as u32,
+        }
+    }
+
+    fn create_buffer(device : &wgpu::Device, capacity : usize) -> wgpu::Buffer {
+        device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Instance Buffer"),
+            size: (capacity * std::mem::size_of::<InstanceElement>()) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: true,
+        })
+    }
+
+    /// replace all instances, reallocates the buffer if it is too small
+    pub fn update(&mut self, device : &wgpu::Device, queue : &wgpu::Queue, instances : &[InstanceElement]) {
+        if instances.len() > self.capacity {
+            *self = Self::new(device, instances);
+            return;
+        }
+        queue.write_buffer(&self.buffer, 0, bytemuck::cast_slice(instances));
+        self.num_instances = instances.len() as u32;
+    }
+
+    pub fn num_instances(&self) -> u32 {
+        self.num_instances
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+}
@@ -1,7 +1,7 @@
 use wgpu::util::DeviceExt;
 
 pub trait UniformBufferData : bytemuck::Pod {
-    fn raw_view<'a>(&'a self) -> &'a[u8] {
+    fn raw_view(&self) -> &[u8] {
         bytemuck::bytes_of(self)
     }
 }
@@ -1,58 +1,145 @@
-use glam::{Mat4, Vec3};
-use crate::buffers::uniform::UniformBuffer;
+use std::sync::Arc;
+
+use glam::{Mat4, Vec2, Vec3};
+use crate::buffers::uniform::{UniformBuffer, UniformBufferData};
+use crate::frustum::Frustum;
+use crate::math::Ray;
+use crate::renderers::pipeline_cache::{LayoutKind, PipelineCache};
+
+pub mod controller;
+
+/// How the camera maps view space to clip space. All variants are left handed with depth in 0..1.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Projection {
+    /// `fov_y` in radians
+    Perspective { fov_y : f32, near : f32, far : f32 },
+    /// `half_height` world units above and below the view center, the width follows the aspect ratio
+    Orthographic { half_height : f32, near : f32, far : f32 },
+    /// no far plane, depth goes from 1 at `near` to 0 at infinity.
+    /// Needs `CompareFunction::Greater` and a depth buffer cleared to 0.
+    ReversedInfinitePerspective { fov_y : f32, near : f32 },
+}
+
+impl Projection {
+    pub fn perspective(fov_y : f32, near : f32, far : f32) -> Self {
+        Projection::Perspective { fov_y, near, far }
+    }
+
+    pub fn orthographic(half_height : f32, near : f32, far : f32) -> Self {
+        Projection::Orthographic { half_height, near, far }
+    }
+
+    pub fn reversed_infinite_perspective(fov_y : f32, near : f32) -> Self {
+        Projection::ReversedInfinitePerspective { fov_y, near }
+    }
+
+    pub fn matrix(&self, aspect_ratio : f32) -> Mat4 {
+        match *self {
+            Projection::Perspective { fov_y, near, far } =>
+                Mat4::perspective_lh(fov_y, aspect_ratio, near, far),
+            Projection::Orthographic { half_height, near, far } => {
+                let half_width = half_height * aspect_ratio;
+                Mat4::orthographic_lh(-half_width, half_width, -half_height, half_height, near, far)
+            }
+            Projection::ReversedInfinitePerspective { fov_y, near } =>
+                Mat4::perspective_infinite_reverse_lh(fov_y, aspect_ratio, near),
+        }
+    }
+
+    pub fn is_reversed_z(&self) -> bool {
+        matches!(self, Projection::ReversedInfinitePerspective { .. })
+    }
+
+    pub fn near(&self) -> f32 {
+        match *self {
+            Projection::Perspective { near, .. }
+            | Projection::Orthographic { near, .. }
+            | Projection::ReversedInfinitePerspective { near, .. } => near,
+        }
+    }
+
+    /// `None` for the infinite projection
+    pub fn far(&self) -> Option<f32> {
+        match *self {
+            Projection::Perspective { far, .. } | Projection::Orthographic { far, .. } => Some(far),
+            Projection::ReversedInfinitePerspective { .. } => None,
+        }
+    }
+}
+
+/// Camera data as seen by the shaders, `Camera` in the WGSL files
+#[repr(C)]
+#[derive(Default, Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct CameraUniform {
+    pub view : Mat4,
+    pub projection : Mat4,
+    pub view_projection : Mat4,
+    pub inverse_view : Mat4,
+    pub inverse_projection : Mat4,
+    pub inverse_view_projection : Mat4,
+    // world space eye position
+    pub eye : Vec3,
+    pub _padding : f32,
+    // viewport size in pixels
+    pub viewport : Vec2,
+    pub _padding2 : [f32; 2],
+}
+
+impl UniformBufferData for CameraUniform {}
+
+impl CameraUniform {
+    pub fn new(view : Mat4, projection : Mat4, eye : Vec3, viewport : Vec2) -> Self {
+        let view_projection = projection * view;
+        Self {
+            view,
+            projection,
+            view_projection,
+            inverse_view : view.inverse(),
+            inverse_projection : projection.inverse(),
+            inverse_view_projection : view_projection.inverse(),
+            eye,
+            viewport,
+            ..Default::default()
+        }
+    }
+}
 
 #[derive(Debug)]
 pub struct Camera {
-    pub buffer : UniformBuffer<Mat4>,
+    pub buffer : UniformBuffer<CameraUniform>,
     pub bind_group : wgpu::BindGroup,
-    pub bind_group_layout : wgpu::BindGroupLayout,
+    /// shared with the pipelines through `LayoutKind::Camera`
+    pub bind_group_layout : Arc<wgpu::BindGroupLayout>,
 
-    proj_mat : Mat4,
+    projection : Projection,
+    viewport : Vec2,
     pub eye : Vec3,
     pub center : Vec3,
-    up : Vec3,
+    pub up : Vec3,
 }
 
 impl Camera {
 
-    fn proj_view_mat(proj_mat : &Mat4, eye : &Vec3, center: &Vec3, up: &Vec3) -> Mat4 {
-        let view_mat = Mat4::look_at_lh(*eye, *center, *up);
-        //dbg!(view_mat);
-        proj_mat.mul_mat4(&view_mat)
-    }
-
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         device: &wgpu::Device,
-        proj_mat : Mat4,
+        pipelines : &mut PipelineCache,
+        projection : Projection,
+        width : u32,
+        height : u32,
         eye : Vec3,
         center : Vec3,
         up : Vec3,
         label : &str ) -> Self {
 
-
-        //dbg!(mat);
+        let viewport = Vec2::new(width.max(1) as f32, height.max(1) as f32);
+        let view = Mat4::look_at_lh(eye, center, up);
         let buffer = UniformBuffer::new(
-            &device, 
-            Self::proj_view_mat(&proj_mat, &eye, &center, &up), 
-            Some( &format!( "Vertex uniform buffer for {label}") ));
+            device, 
+            CameraUniform::new(view, projection.matrix(viewport.x / viewport.y), eye, viewport),
+            Some( &format!( "Camera uniform buffer for {label}") ));
                 
-        let bind_group_layout = 
-            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-                entries: &[
-                    wgpu::BindGroupLayoutEntry {
-                        binding: 0,
-                        visibility: wgpu::ShaderStages::VERTEX,
-                        ty: wgpu::BindingType::Buffer {
-                            ty: wgpu::BufferBindingType::Uniform,
-                            has_dynamic_offset: false,
-                            min_binding_size: None,
-                        },                    
-                        count: None,                        
-                    },
-                ],
-                label :Some( "Camera uniform group"),
-            }
-        );
+        let bind_group_layout = pipelines.bind_group_layout(device, LayoutKind::Camera);
 
         let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor{
             layout: &bind_group_layout,
@@ -69,19 +156,87 @@ impl Camera {
             buffer,
             bind_group,
             bind_group_layout,
-            proj_mat,
+            projection,
+            viewport,
             eye,
             center,
             up,
         }
     }
 
-    pub fn update_projection(&mut self, queue: &wgpu::Queue, proj_mat : Mat4) {
-        self.proj_mat = proj_mat;
-        self.buffer.data = Self::proj_view_mat(&proj_mat, &self.eye, &self.center, &self.up); 
+    // recompute all matrices and upload them
+    fn write(&mut self, queue: &wgpu::Queue) {
+        self.buffer.data = CameraUniform::new(self.view(), self.projection_matrix(), self.eye, self.viewport);
         self.buffer.update(queue);
     }
 
+    /// recompute the projection for a new viewport size
+    pub fn resize(&mut self, queue: &wgpu::Queue, width : u32, height : u32) {
+        self.viewport = Vec2::new(width.max(1) as f32, height.max(1) as f32);
+        self.write(queue);
+    }
+
+    /// switching to or from a reversed-Z projection also needs new pipelines and depth clear value
+    pub fn set_projection(&mut self, queue: &wgpu::Queue, projection : Projection) {
+        self.projection = projection;
+        self.write(queue);
+    }
+
+    pub fn projection(&self) -> &Projection {
+        &self.projection
+    }
+
+    pub fn aspect_ratio(&self) -> f32 {
+        self.viewport.x / self.viewport.y
+    }
+
+    /// viewport size in pixels
+    pub fn viewport(&self) -> Vec2 {
+        self.viewport
+    }
+
+    /// move the camera, the buffer is only written when the view actually changed.
+    /// Returns true if it was written.
+    pub fn set_view(&mut self, queue: &wgpu::Queue, eye : Vec3, center : Vec3, up : Vec3) -> bool {
+        if (eye, center, up) == (self.eye, self.center, self.up) {
+            return false;
+        }
+        self.eye = eye;
+        self.center = center;
+        self.up = up;
+        self.write(queue);
+        true
+    }
+
+    pub fn projection_matrix(&self) -> Mat4 {
+        self.projection.matrix(self.aspect_ratio())
+    }
+
+    pub fn view(&self) -> Mat4 {
+        Mat4::look_at_lh(self.eye, self.center, self.up)
+    }
+
+    pub fn view_projection(&self) -> Mat4 {
+        self.buffer.data.view_projection
+    }
+
+    /// world space ray through a point in physical window pixels, starting at the near plane
+    pub fn screen_ray(&self, x : f32, y : f32) -> Ray {
+        let ndc_x = 2.0 * x / self.viewport.x - 1.0;
+        let ndc_y = 1.0 - 2.0 * y / self.viewport.y;
+        // the far plane of the infinite projection is at w = 0, so use a point halfway instead
+        let near_depth = if self.projection.is_reversed_z() { 1.0 } else { 0.0 };
+        let inverse = self.buffer.data.inverse_view_projection;
+        let near = inverse.project_point3(Vec3::new(ndc_x, ndc_y, near_depth));
+        let middle = inverse.project_point3(Vec3::new(ndc_x, ndc_y, 0.5));
+        Ray::new(near, (middle - near).normalize_or_zero())
+    }
+
+    /// world space clip planes of the current view
+    pub fn frustum(&self) -> Frustum {
+        Frustum::from_view_projection(&self.view_projection())
+    }
+
     pub fn draw<'a>( &'a self, render_pass: &mut wgpu::RenderPass<'a>, bind_group_nr : u32 ) {
         render_pass.set_bind_group(bind_group_nr, &self.bind_group, &[]);
     }
@@ -89,5 +244,89 @@ impl Camera {
 
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use glam::Vec4;
+
+    // normalized device depth of a view space point straight ahead at `z`
+    fn depth(projection : &Projection, z : f32) -> f32 {
+        projection.matrix(1.0).project_point3(Vec3::new(0.0, 0.0, z)).z
+    }
+
+    fn assert_close(a : f32, b : f32) {
+        assert!((a - b).abs() < 1e-5, "{a} != {b}");
+    }
 
+    #[test]
+    fn perspective_depth_goes_from_0_to_1() {
+        let projection = Projection::perspective(60.0_f32.to_radians(), 0.1, 100.0);
+        assert!(!projection.is_reversed_z());
+        assert_close(depth(&projection, 0.1), 0.0);
+        assert_close(depth(&projection, 100.0), 1.0);
+        // most of the precision is spent close to the camera
+        assert!(depth(&projection, 1.0) > 0.5);
+        assert_eq!((projection.near(), projection.far()), (0.1, Some(100.0)));
+    }
 
+    #[test]
+    fn orthographic_depth_is_linear() {
+        let projection = Projection::orthographic(2.0, 1.0, 11.0);
+        assert!(!projection.is_reversed_z());
+        assert_close(depth(&projection, 1.0), 0.0);
+        assert_close(depth(&projection, 6.0), 0.5);
+        assert_close(depth(&projection, 11.0), 1.0);
+        // the width follows the aspect ratio
+        let corner = projection.matrix(2.0) * Vec4::new(4.0, 2.0, 5.0, 1.0);
+        assert_eq!(corner.w, 1.0);
+        assert_close(corner.x, 1.0);
+        assert_close(corner.y, 1.0);
+    }
+
+    #[test]
+    fn reversed_z_maps_near_to_1_and_far_to_0() {
+        let projection = Projection::reversed_infinite_perspective(60.0_f32.to_radians(), 0.1);
+        assert!(projection.is_reversed_z());
+        assert_close(depth(&projection, 0.1), 1.0);
+        assert!(depth(&projection, 1.0) < depth(&projection, 0.5));
+        assert!(depth(&projection, 1.0e6) < 1.0e-6);
+        assert!(depth(&projection, 1.0e6) > 0.0);
+        assert_eq!((projection.near(), projection.far()), (0.1, None));
+    }
+
+    fn camera(gpu : &crate::gpu::Gpu, projection : Projection) -> Camera {
+        let eye = Vec3::new(1.0, 2.0, -3.0);
+        Camera::new(&gpu.device, &mut PipelineCache::new(), projection, 320, 200, eye, Vec3::ZERO, Vec3::Y, "Test camera")
+    }
+
+    #[test]
+    fn set_view_only_uploads_changes() {
+        let Some(gpu) = crate::gpu::test_gpu() else { return };
+        let mut camera = camera(&gpu, Projection::perspective(1.0, 0.1, 100.0));
+        let (eye, center, up) = (camera.eye, camera.center, camera.up);
+        assert!(!camera.set_view(&gpu.queue, eye, center, up));
+
+        let moved = eye + Vec3::X;
+        assert!(camera.set_view(&gpu.queue, moved, center, up));
+        assert_eq!(camera.buffer.data.eye, moved);
+        assert_eq!(camera.buffer.data.view, Mat4::look_at_lh(moved, center, up));
+        assert!(!camera.set_view(&gpu.queue, moved, center, up));
+    }
+
+    #[test]
+    fn center_ray_looks_along_the_view() {
+        let Some(gpu) = crate::gpu::test_gpu() else { return };
+        for projection in [
+            Projection::perspective(1.0, 0.1, 100.0),
+            Projection::orthographic(2.0, 0.1, 100.0),
+            Projection::reversed_infinite_perspective(1.0, 0.1),
+        ] {
+            let camera = camera(&gpu, projection);
+            let ray = camera.screen_ray(160.0, 100.0);
+            let forward = (camera.center - camera.eye).normalize();
+            assert!(ray.direction.abs_diff_eq(forward, 1e-4), "{projection:?}: {}", ray.direction);
+            // starts on the near plane
+            assert!(ray.origin.abs_diff_eq(camera.eye + forward * 0.1, 1e-4), "{projection:?}: {}", ray.origin);
+        }
+    }
+}
@@ -0,0 +1,371 @@
+use glam::Vec3;
+use winit::event::{MouseButton, VirtualKeyCode};
+
+use crate::input::InputState;
+use super::Camera;
+
+// keep the view direction away from the poles, `look_at` breaks down there
+const MAX_PITCH : f32 = 89.0 * std::f32::consts::PI / 180.0;
+
+/// Moves a camera from user input. `update` runs once per simulation tick,
+/// `apply` writes the result to the camera and only touches the gpu buffer on changes.
+pub trait CameraController {
+    fn update(&mut self, input : &InputState, dt : f32);
+
+    /// current eye, center and up vector
+    fn view(&self) -> (Vec3, Vec3, Vec3);
+
+    /// returns true if the camera buffer was written
+    fn apply(&self, queue : &wgpu::Queue, camera : &mut Camera) -> bool {
+        let (eye, center, up) = self.view();
+        camera.set_view(queue, eye, center, up)
+    }
+}
+
+// unit vector for yaw around +Y and pitch above the XZ plane, yaw 0 looks along +Z
+fn direction(yaw : f32, pitch : f32) -> Vec3 {
+    Vec3::new(pitch.cos() * yaw.sin(), pitch.sin(), pitch.cos() * yaw.cos())
+}
+
+// yaw / pitch of a direction, inverse of `direction`
+fn angles(direction : Vec3) -> (f32, f32) {
+    let direction = direction.normalize_or_zero();
+    (direction.x.atan2(direction.z), direction.y.clamp(-1.0, 1.0).asin())
+}
+
+/// Orbits around a target. Drag with `rotate_button` to rotate, with `pan_button`
+/// to move the target in the view plane, scroll to zoom.
+#[derive(Clone, Debug)]
+pub struct OrbitController {
+    pub target : Vec3,
+    pub distance : f32,
+    /// radians, 0 looks along +Z
+    pub yaw : f32,
+    /// radians, positive looks down on the target
+    pub pitch : f32,
+    pub min_distance : f32,
+    pub max_distance : f32,
+    /// radians per pixel
+    pub rotate_speed : f32,
+    /// fraction of the distance per scroll line
+    pub zoom_speed : f32,
+    /// fraction of the distance per pixel
+    pub pan_speed : f32,
+    pub rotate_button : MouseButton,
+    pub pan_button : MouseButton,
+}
+
+impl OrbitController {
+    pub fn new(target : Vec3, distance : f32, yaw : f32, pitch : f32) -> Self {
+        Self {
+            target,
+            distance,
+            yaw,
+            pitch : pitch.clamp(-MAX_PITCH, MAX_PITCH),
+            min_distance : 0.1,
+            max_distance : 100.0,
+            rotate_speed : 0.005,
+            zoom_speed : 0.1,
+            pan_speed : 0.002,
+            rotate_button : MouseButton::Right,
+            pan_button : MouseButton::Middle,
+        }
+    }
+
+    /// orbit that starts at the current view of `camera`
+    pub fn from_camera(camera : &Camera) -> Self {
+        let (yaw, pitch) = angles(camera.center - camera.eye);
+        Self::new(camera.center, camera.eye.distance(camera.center), yaw, -pitch)
+    }
+
+    pub fn with_distance_range(self, min_distance : f32, max_distance : f32) -> Self {
+        Self { min_distance, max_distance, distance : self.distance.clamp(min_distance, max_distance), ..self }
+    }
+
+    pub fn eye(&self) -> Vec3 {
+        self.target - self.distance * direction(self.yaw, -self.pitch)
+    }
+}
+
+impl CameraController for OrbitController {
+    fn update(&mut self, input : &InputState, _dt : f32) {
+        let delta = input.cursor_delta();
+        if input.is_button_held(self.rotate_button) {
+            self.yaw += delta.x * self.rotate_speed;
+            self.pitch = (self.pitch + delta.y * self.rotate_speed).clamp(-MAX_PITCH, MAX_PITCH);
+        }
+        if input.is_button_held(self.pan_button) {
+            let forward = direction(self.yaw, -self.pitch);
+            let right = Vec3::Y.cross(forward).normalize_or_zero();
+            let up = forward.cross(right);
+            // drag the scene along with the cursor
+            self.target += (up * delta.y - right * delta.x) * self.pan_speed * self.distance;
+        }
+        let scroll = input.scroll_delta();
+        if scroll != 0.0 {
+            self.distance = (self.distance * (1.0 - self.zoom_speed).powf(scroll))
+                .clamp(self.min_distance, self.max_distance);
+        }
+    }
+
+    fn view(&self) -> (Vec3, Vec3, Vec3) {
+        (self.eye(), self.target, Vec3::Y)
+    }
+}
+
+/// Free flying camera: WASD to move, Q/E down/up, shift to go faster,
+/// drag with `look_button` to look around.
+#[derive(Clone, Debug)]
+pub struct FlyController {
+    pub position : Vec3,
+    pub yaw : f32,
+    pub pitch : f32,
+    /// units per second
+    pub speed : f32,
+    pub boost : f32,
+    /// radians per pixel
+    pub look_speed : f32,
+    pub look_button : MouseButton,
+}
+
+impl FlyController {
+    pub fn new(position : Vec3, yaw : f32, pitch : f32) -> Self {
+        Self {
+            position,
+            yaw,
+            pitch : pitch.clamp(-MAX_PITCH, MAX_PITCH),
+            speed : 2.0,
+            boost : 4.0,
+            look_speed : 0.003,
+            look_button : MouseButton::Right,
+        }
+    }
+
+    pub fn from_camera(camera : &Camera) -> Self {
+        let (yaw, pitch) = angles(camera.center - camera.eye);
+        Self::new(camera.eye, yaw, pitch)
+    }
+
+    pub fn forward(&self) -> Vec3 {
+        direction(self.yaw, self.pitch)
+    }
+}
+
+impl CameraController for FlyController {
+    fn update(&mut self, input : &InputState, dt : f32) {
+        if input.is_button_held(self.look_button) {
+            let delta = input.cursor_delta();
+            self.yaw += delta.x * self.look_speed;
+            self.pitch = (self.pitch - delta.y * self.look_speed).clamp(-MAX_PITCH, MAX_PITCH);
+        }
+
+        let axis = |negative, positive| {
+            let value = |key| if input.is_key_held(key) { 1.0 } else { 0.0 };
+            value(positive) - value(negative)
+        };
+        let movement = Vec3::new(
+            axis(VirtualKeyCode::A, VirtualKeyCode::D),
+            axis(VirtualKeyCode::Q, VirtualKeyCode::E),
+            axis(VirtualKeyCode::S, VirtualKeyCode::W));
+        if movement == Vec3::ZERO {
+            return;
+        }
+
+        let forward = self.forward();
+        let right = Vec3::Y.cross(forward).normalize_or_zero();
+        let boost = if input.is_key_held(VirtualKeyCode::LShift) { self.boost } else { 1.0 };
+        let velocity = (right * movement.x + Vec3::Y * movement.y + forward * movement.z).normalize_or_zero();
+        self.position += velocity * self.speed * boost * dt;
+    }
+
+    fn view(&self) -> (Vec3, Vec3, Vec3) {
+        (self.position, self.position + self.forward(), Vec3::Y)
+    }
+}
+
+/// Keeps a fixed offset to a target that is moved with `set_target`, e.g. the ball.
+/// The eye trails behind exponentially, `stiffness` is the inverse time constant in 1/s.
+#[derive(Clone, Debug)]
+pub struct FollowController {
+    pub offset : Vec3,
+    /// looked at point relative to the target
+    pub look_offset : Vec3,
+    pub stiffness : f32,
+    target : Vec3,
+    eye : Vec3,
+    center : Vec3,
+}
+
+impl FollowController {
+    pub fn new(target : Vec3, offset : Vec3) -> Self {
+        Self {
+            offset,
+            look_offset : Vec3::ZERO,
+            stiffness : 5.0,
+            target,
+            eye : target + offset,
+            center : target,
+        }
+    }
+
+    pub fn set_target(&mut self, target : Vec3) {
+        self.target = target;
+    }
+
+    /// jump to the target without trailing
+    pub fn snap(&mut self) {
+        self.eye = self.target + self.offset;
+        self.center = self.target + self.look_offset;
+    }
+}
+
+impl CameraController for FollowController {
+    fn update(&mut self, _input : &InputState, dt : f32) {
+        // frame rate independent exponential smoothing
+        let t = 1.0 - (-self.stiffness * dt).exp();
+        self.eye = self.eye.lerp(self.target + self.offset, t);
+        self.center = self.center.lerp(self.target + self.look_offset, t);
+    }
+
+    fn view(&self) -> (Vec3, Vec3, Vec3) {
+        (self.eye, self.center, Vec3::Y)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use winit::dpi::PhysicalPosition;
+    use winit::event::{DeviceId, ElementState, MouseScrollDelta, TouchPhase, WindowEvent};
+    use crate::input::{Binding, InputMap};
+
+    const DT : f32 = 1.0 / 60.0;
+
+    fn input() -> InputState {
+        InputState::new(InputMap::empty())
+    }
+
+    // winit still wants the deprecated modifiers filled in
+    #[allow(deprecated)]
+    fn move_cursor(input : &mut InputState, x : f64, y : f64) {
+        input.handle_event(&WindowEvent::CursorMoved {
+            device_id : unsafe { DeviceId::dummy() },
+            position : PhysicalPosition::new(x, y),
+            modifiers : Default::default(),
+        });
+    }
+
+    #[allow(deprecated)]
+    fn scroll(input : &mut InputState, lines : f32) {
+        input.handle_event(&WindowEvent::MouseWheel {
+            device_id : unsafe { DeviceId::dummy() },
+            delta : MouseScrollDelta::LineDelta(0.0, lines),
+            phase : TouchPhase::Moved,
+            modifiers : Default::default(),
+        });
+    }
+
+    // hold `button` and drag the cursor by `dy` pixels
+    fn drag(input : &mut InputState, button : MouseButton, dy : f64) {
+        input.end_tick();
+        input.set_binding(Binding::Mouse(button), ElementState::Pressed);
+        move_cursor(input, 0.0, 0.0);
+        move_cursor(input, 0.0, dy);
+    }
+
+    fn assert_close(a : Vec3, b : Vec3) {
+        assert!(a.abs_diff_eq(b, 1e-4), "{a} != {b}");
+    }
+
+    #[test]
+    fn orbit_pitch_is_clamped() {
+        assert_eq!(OrbitController::new(Vec3::ZERO, 5.0, 0.0, 2.0).pitch, MAX_PITCH);
+
+        let mut orbit = OrbitController::new(Vec3::ZERO, 5.0, 0.0, 0.0);
+        let mut input = input();
+        drag(&mut input, orbit.rotate_button, 10_000.0);
+        orbit.update(&input, DT);
+        assert_eq!(orbit.pitch, MAX_PITCH);
+        // looking down from above, still at the same distance
+        assert!(orbit.eye().y > 4.9);
+        assert!((orbit.eye().length() - 5.0).abs() < 1e-4);
+
+        drag(&mut input, orbit.rotate_button, -20_000.0);
+        orbit.update(&input, DT);
+        assert_eq!(orbit.pitch, -MAX_PITCH);
+    }
+
+    #[test]
+    fn orbit_distance_stays_in_range() {
+        let mut orbit = OrbitController::new(Vec3::ZERO, 5.0, 0.0, 0.0).with_distance_range(2.0, 10.0);
+        let mut input = input();
+        scroll(&mut input, 1.0);
+        orbit.update(&input, DT);
+        assert!((orbit.distance - 4.5).abs() < 1e-5);
+
+        scroll(&mut input, 100.0);
+        orbit.update(&input, DT);
+        assert_eq!(orbit.distance, 2.0);
+
+        input.end_tick();
+        scroll(&mut input, -100.0);
+        orbit.update(&input, DT);
+        assert_eq!(orbit.distance, 10.0);
+
+        // a new range clamps the current distance
+        assert_eq!(orbit.with_distance_range(1.0, 3.0).distance, 3.0);
+    }
+
+    #[test]
+    fn fly_pitch_is_clamped_and_moves_forward() {
+        let mut fly = FlyController::new(Vec3::ZERO, 0.0, 0.0);
+        let mut input = input();
+        drag(&mut input, fly.look_button, -10_000.0);
+        fly.update(&input, DT);
+        assert_eq!(fly.pitch, MAX_PITCH);
+        drag(&mut input, fly.look_button, 20_000.0);
+        fly.update(&input, DT);
+        assert_eq!(fly.pitch, -MAX_PITCH);
+
+        let mut fly = FlyController::new(Vec3::ZERO, 0.0, 0.0);
+        let mut input = self::input();
+        input.set_binding(Binding::Key(VirtualKeyCode::W), ElementState::Pressed);
+        fly.update(&input, 0.5);
+        assert_close(fly.position, Vec3::new(0.0, 0.0, 1.0));
+        assert_close(fly.view().1, Vec3::new(0.0, 0.0, 2.0));
+    }
+
+    #[test]
+    fn follow_trails_the_target() {
+        let offset = Vec3::new(0.0, 2.0, -4.0);
+        let mut follow = FollowController::new(Vec3::ZERO, offset);
+        let input = input();
+        follow.set_target(Vec3::new(10.0, 0.0, 0.0));
+
+        // one time constant covers 1 - 1/e of the way
+        follow.update(&input, 1.0 / follow.stiffness);
+        let (eye, center, _) = follow.view();
+        let covered = 1.0 - (-1.0_f32).exp();
+        assert_close(eye, offset + Vec3::X * 10.0 * covered);
+        assert_close(center, Vec3::X * 10.0 * covered);
+
+        // the same distance no matter how the time is split up
+        let mut split = FollowController::new(Vec3::ZERO, offset);
+        split.set_target(Vec3::new(10.0, 0.0, 0.0));
+        for _ in 0..10 {
+            split.update(&input, 0.1 / follow.stiffness);
+        }
+        assert_close(split.view().0, eye);
+
+        // gets there without overshooting
+        for _ in 0..600 {
+            follow.update(&input, DT);
+            assert!(follow.view().1.x <= 10.0);
+        }
+        assert_close(follow.view().0, Vec3::new(10.0, 0.0, 0.0) + offset);
+
+        follow.set_target(Vec3::ZERO);
+        follow.snap();
+        assert_eq!(follow.view().0, offset);
+    }
+}
@@ -1,5 +1,6 @@
 use thiserror::Error;
 use std::io;
+use std::path::PathBuf;
 
 #[derive(Error, Debug)]
 pub enum WvtError {
@@ -10,11 +11,62 @@ pub enum WvtError {
     #[error("wgpu error")]
     WgpuError(#[from] wgpu::Error),
 
+    #[error("No suitable graphics adapter found")]
+    NoAdapter,
+
+    #[error("Could not create surface")]
+    CreateSurfaceError(#[from] wgpu::CreateSurfaceError),
+
+    #[error("Could not request device")]
+    RequestDeviceError(#[from] wgpu::RequestDeviceError),
+
+    #[error("Could not map gpu buffer")]
+    BufferAsyncError(#[from] wgpu::BufferAsyncError),
+
+    #[error("Render target can not be read back")]
+    NotReadable,
+
+    #[error("Readback buffer was dropped before it was mapped")]
+    ReadbackDisconnected,
+
+    #[error("Readback returned {actual} bytes, expected {expected}")]
+    ReadbackSize { expected : usize, actual : usize },
+
 
     
     #[error("Image loading/handling error")]
     ImageError(#[from] image::error::ImageError),
 
+    #[error("Could not load OBJ model {}: {source}", .path.display())]
+    ObjLoad { path : PathBuf, source : tobj::LoadError },
+
+    #[error("Could not load materials of {}: {source}", .path.display())]
+    MissingMaterial { path : PathBuf, source : tobj::LoadError },
+
+    #[error("Could not load glTF model {}: {source}", .path.display())]
+    GltfLoad { path : PathBuf, source : gltf::Error },
+
+    #[error("Image {index} of {} has unsupported pixel format {format}", .path.display())]
+    UnsupportedImageFormat { path : PathBuf, index : usize, format : String },
+
+    #[error("Model {} contains no meshes", .path.display())]
+    EmptyModel { path : PathBuf },
+
+    #[error("Texture file {} not found", .path.display())]
+    MissingTexture { path : PathBuf },
+
+    #[error("Could not read texture {}: {source}", .path.display())]
+    TextureRead { path : PathBuf, source : io::Error },
+
+    #[error("Could not load texture {}: {source}", .path.display())]
+    TextureLoad { path : PathBuf, source : image::error::ImageError },
+
+    #[error("Input config error on line {line}: {message}")]
+    InputConfig { line : usize, message : String },
+
+    #[error("Could not open gamepads: {0}")]
+    Gamepad(String),
+
 
     #[error("Unknown wvt error")]
     Unknown,
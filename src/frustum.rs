@@ -0,0 +1,157 @@
+use glam::{Mat4, Vec3, Vec4};
+
+use crate::mesh::{Aabb, BoundingSphere};
+
+/// The six clip planes of a view projection, normals point inwards.
+/// Planes are `xyz` normal and `w` distance, a point is inside when `dot(n, p) + w >= 0`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Frustum {
+    pub planes : [Vec4; 6],
+}
+
+impl Frustum {
+    /// Gribb/Hartmann plane extraction for clip space depth in 0..1.
+    /// The far plane of an infinite projection never rejects anything.
+    pub fn from_view_projection(matrix : &Mat4) -> Self {
+        let [r0, r1, r2, r3] = [0, 1, 2, 3].map(|i| matrix.row(i));
+        let planes = [
+            r3 + r0, // left
+            r3 - r0, // right
+            r3 + r1, // bottom
+            r3 - r1, // top
+            r2,      // near, far for reversed-Z
+            r3 - r2, // far, near for reversed-Z
+        ].map(|plane| {
+            let length = plane.truncate().length();
+            if length > 0.0 { plane / length } else { plane }
+        });
+        Self { planes }
+    }
+
+    fn distance(plane : Vec4, point : Vec3) -> f32 {
+        plane.truncate().dot(point) + plane.w
+    }
+
+    pub fn contains_point(&self, point : Vec3) -> bool {
+        self.planes.iter().all(|plane| Self::distance(*plane, point) >= 0.0)
+    }
+
+    /// conservative, spheres near the frustum corners can be reported as visible
+    pub fn intersects_sphere(&self, sphere : &BoundingSphere) -> bool {
+        self.planes.iter().all(|plane| Self::distance(*plane, sphere.center) >= -sphere.radius)
+    }
+
+    /// conservative, tests the box corner furthest along each plane normal
+    pub fn intersects_aabb(&self, aabb : &Aabb) -> bool {
+        self.planes.iter().all(|plane| {
+            let normal = plane.truncate();
+            let corner = Vec3::select(normal.cmpge(Vec3::ZERO), aabb.max, aabb.min);
+            Self::distance(*plane, corner) >= 0.0
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::camera::Projection;
+
+    // camera at the origin looking along +Z, square viewport
+    fn frustum(projection : Projection) -> Frustum {
+        let view = Mat4::look_at_lh(Vec3::ZERO, Vec3::Z, Vec3::Y);
+        Frustum::from_view_projection(&(projection.matrix(1.0) * view))
+    }
+
+    fn assert_plane(plane : Vec4, expected : Vec4) {
+        assert!(plane.abs_diff_eq(expected, 1e-5), "{plane} != {expected}");
+    }
+
+    fn aabb(min : [f32; 3], max : [f32; 3]) -> Aabb {
+        Aabb::new(Vec3::from(min), Vec3::from(max))
+    }
+
+    fn sphere(center : [f32; 3], radius : f32) -> BoundingSphere {
+        BoundingSphere::new(Vec3::from(center), radius)
+    }
+
+    #[test]
+    fn perspective_planes() {
+        // 90 degrees, so the sides are at |x| = z and |y| = z
+        let frustum = frustum(Projection::perspective(90.0_f32.to_radians(), 1.0, 10.0));
+        let diagonal = std::f32::consts::FRAC_1_SQRT_2;
+        assert_plane(frustum.planes[0], Vec4::new(diagonal, 0.0, diagonal, 0.0));
+        assert_plane(frustum.planes[1], Vec4::new(-diagonal, 0.0, diagonal, 0.0));
+        assert_plane(frustum.planes[2], Vec4::new(0.0, diagonal, diagonal, 0.0));
+        assert_plane(frustum.planes[3], Vec4::new(0.0, -diagonal, diagonal, 0.0));
+        assert_plane(frustum.planes[4], Vec4::new(0.0, 0.0, 1.0, -1.0));
+        assert_plane(frustum.planes[5], Vec4::new(0.0, 0.0, -1.0, 10.0));
+
+        assert!(frustum.contains_point(Vec3::new(0.0, 0.0, 5.0)));
+        assert!(!frustum.contains_point(Vec3::new(0.0, 0.0, 0.5)));
+        assert!(!frustum.contains_point(Vec3::new(0.0, 0.0, 11.0)));
+        assert!(!frustum.contains_point(Vec3::new(6.0, 0.0, 5.0)));
+    }
+
+    #[test]
+    fn perspective_rejection() {
+        let frustum = frustum(Projection::perspective(90.0_f32.to_radians(), 1.0, 10.0));
+
+        // straddling the far plane
+        assert!(frustum.intersects_sphere(&sphere([0.0, 0.0, 11.0], 1.5)));
+        assert!(!frustum.intersects_sphere(&sphere([0.0, 0.0, 11.0], 0.5)));
+        // straddling the right plane, 1/sqrt(2) outside
+        assert!(frustum.intersects_sphere(&sphere([6.0, 0.0, 5.0], 1.0)));
+        assert!(!frustum.intersects_sphere(&sphere([6.0, 0.0, 5.0], 0.5)));
+        assert!(!frustum.intersects_sphere(&sphere([0.0, 0.0, -3.0], 1.0)));
+
+        // straddling the near plane
+        assert!(frustum.intersects_aabb(&aabb([-0.5, -0.5, 0.5], [0.5, 0.5, 1.5])));
+        // straddling the right plane, then just outside of it
+        assert!(frustum.intersects_aabb(&aabb([4.0, -1.0, 4.0], [6.0, 1.0, 5.0])));
+        assert!(!frustum.intersects_aabb(&aabb([5.5, -1.0, 4.0], [6.0, 1.0, 5.0])));
+        // behind the camera
+        assert!(!frustum.intersects_aabb(&aabb([-1.0, -1.0, -3.0], [1.0, 1.0, -2.0])));
+        // bigger than the whole frustum
+        assert!(frustum.intersects_aabb(&aabb([-100.0; 3], [100.0; 3])));
+    }
+
+    #[test]
+    fn reversed_z_infinite_has_no_far_plane() {
+        let frustum = frustum(Projection::reversed_infinite_perspective(90.0_f32.to_radians(), 1.0));
+        // the near plane comes from the last row, the first is empty
+        assert_plane(frustum.planes[5], Vec4::new(0.0, 0.0, 1.0, -1.0));
+        assert_eq!(frustum.planes[4].truncate(), Vec3::ZERO);
+
+        assert!(frustum.contains_point(Vec3::new(0.0, 0.0, 1.0e6)));
+        assert!(frustum.intersects_sphere(&sphere([0.0, 0.0, 1.0e7], 1.0)));
+        assert!(frustum.intersects_aabb(&aabb([-1.0, -1.0, 1.0e6], [1.0, 1.0, 2.0e6])));
+
+        assert!(!frustum.contains_point(Vec3::new(0.0, 0.0, 0.5)));
+        assert!(frustum.intersects_aabb(&aabb([-0.5, -0.5, 0.5], [0.5, 0.5, 1.5])));
+        assert!(!frustum.intersects_aabb(&aabb([-0.5, -0.5, 0.2], [0.5, 0.5, 0.8])));
+        // the sides still reject
+        assert!(!frustum.intersects_sphere(&sphere([1.0e6, 0.0, 10.0], 1.0)));
+    }
+
+    #[test]
+    fn orthographic_planes_are_axis_aligned() {
+        let frustum = frustum(Projection::orthographic(2.0, 1.0, 11.0));
+        assert_plane(frustum.planes[0], Vec4::new(1.0, 0.0, 0.0, 2.0));
+        assert_plane(frustum.planes[1], Vec4::new(-1.0, 0.0, 0.0, 2.0));
+        assert_plane(frustum.planes[2], Vec4::new(0.0, 1.0, 0.0, 2.0));
+        assert_plane(frustum.planes[3], Vec4::new(0.0, -1.0, 0.0, 2.0));
+        assert_plane(frustum.planes[4], Vec4::new(0.0, 0.0, 1.0, -1.0));
+        assert_plane(frustum.planes[5], Vec4::new(0.0, 0.0, -1.0, 11.0));
+
+        assert!(frustum.contains_point(Vec3::new(1.9, 0.0, 5.0)));
+        assert!(!frustum.contains_point(Vec3::new(2.1, 0.0, 5.0)));
+        // the size doesn't change with the distance
+        assert!(frustum.contains_point(Vec3::new(1.9, -1.9, 10.9)));
+
+        // straddling the top plane, then just above it
+        assert!(frustum.intersects_aabb(&aabb([-1.0, 1.5, 4.0], [1.0, 3.0, 6.0])));
+        assert!(!frustum.intersects_aabb(&aabb([-1.0, 2.5, 4.0], [1.0, 3.0, 6.0])));
+        assert!(frustum.intersects_sphere(&sphere([0.0, 3.0, 5.0], 1.01)));
+        assert!(!frustum.intersects_sphere(&sphere([0.0, 3.0, 5.0], 0.9)));
+    }
+}
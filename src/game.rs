@@ -0,0 +1,600 @@
+use glam::{Mat4, Quat, Vec2, Vec3};
+
+use crate::mesh::Aabb;
+use crate::physics::{self, SweepHit};
+
+pub mod ai;
+
+// collision resolutions per step before the rest of the motion is dropped
+const MAX_BOUNCES : usize = 4;
+// distance the ball is kept away from a surface after a bounce
+const CONTACT_OFFSET : f32 = 1e-4;
+
+/// Which side of the arena a paddle (or player) belongs to
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Side {
+    Left,
+    Right,
+}
+
+impl Side {
+    pub fn opponent(self) -> Self {
+        match self {
+            Side::Left => Side::Right,
+            Side::Right => Side::Left,
+        }
+    }
+
+    /// sign of the x axis pointing towards this side
+    pub fn direction(self) -> f32 {
+        match self {
+            Side::Left => -1.0,
+            Side::Right => 1.0,
+        }
+    }
+}
+
+/// Tunable rules and dimensions of a Pong match, all in world units / seconds
+#[derive(Copy, Clone, Debug)]
+pub struct PongConfig {
+    pub arena_half_size : Vec2,
+    pub paddle_half_size : Vec2,
+    pub paddle_margin : f32,
+    pub paddle_speed : f32,
+    pub ball_radius : f32,
+    pub serve_speed : f32,
+    pub max_ball_speed : f32,
+    pub speedup : f32,
+    pub serve_delay : f32,
+    pub serve_angle : f32,
+    pub max_bounce_angle : f32,
+    /// fraction of the speed into a wall or paddle edge that is kept, 1 is fully elastic
+    pub restitution : f32,
+    /// fraction of the paddle velocity that is passed on to the ball on a hit
+    pub paddle_spin : f32,
+    pub winning_score : u32,
+    pub first_serve : Side,
+}
+
+impl Default for PongConfig {
+    fn default() -> Self {
+        Self {
+            arena_half_size : Vec2::new(4.0, 2.5),
+            paddle_half_size : Vec2::new(0.1, 0.5),
+            paddle_margin : 0.3,
+            paddle_speed : 4.0,
+            ball_radius : 0.1,
+            serve_speed : 3.0,
+            max_ball_speed : 9.0,
+            speedup : 1.05,
+            serve_delay : 1.0,
+            serve_angle : 20.0_f32.to_radians(),
+            max_bounce_angle : 60.0_f32.to_radians(),
+            restitution : 1.0,
+            paddle_spin : 0.25,
+            winning_score : 11,
+            first_serve : Side::Left,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct Arena {
+    pub half_size : Vec2,
+}
+
+impl Arena {
+    /// collision boxes of the top and bottom walls, reaching past the arena ends
+    pub fn wall_aabbs(&self) -> [Aabb; 2] {
+        let half_x = 2.0 * self.half_size.x;
+        let y = self.half_size.y;
+        [
+            Aabb::new(Vec3::new(-half_x, y, -1.0), Vec3::new(half_x, y + 1.0, 1.0)),
+            Aabb::new(Vec3::new(-half_x, -y - 1.0, -1.0), Vec3::new(half_x, -y, 1.0)),
+        ]
+    }
+
+    /// model matrices for the top and bottom walls, each `thickness` thick
+    pub fn wall_matrices(&self, thickness : f32, depth : f32) -> [Mat4; 2] {
+        let scale = Vec3::new(2.0 * self.half_size.x + 2.0 * thickness, thickness, depth);
+        let offset = self.half_size.y + 0.5 * thickness;
+        [
+            Mat4::from_scale_rotation_translation(scale, Quat::IDENTITY, Vec3::new(0.0, offset, 0.0)),
+            Mat4::from_scale_rotation_translation(scale, Quat::IDENTITY, Vec3::new(0.0, -offset, 0.0)),
+        ]
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct Paddle {
+    pub side : Side,
+    pub position : Vec2,
+    pub half_size : Vec2,
+    /// vertical velocity of the last step, used to put spin on the ball
+    pub velocity : f32,
+}
+
+impl Paddle {
+    /// blend between a previous (`alpha` = 0) and this (`alpha` = 1) state
+    pub fn lerp(&self, previous : &Paddle, alpha : f32) -> Self {
+        Self {
+            position : previous.position.lerp(self.position, alpha),
+            ..*self
+        }
+    }
+
+    /// collision box, deeper than the ball so it is only ever hit from the sides
+    pub fn aabb(&self) -> Aabb {
+        let half_size = self.half_size.extend(1.0);
+        let center = self.position.extend(0.0);
+        Aabb::new(center - half_size, center + half_size)
+    }
+
+    pub fn model_matrix(&self, depth : f32) -> Mat4 {
+        Mat4::from_scale_rotation_translation(
+            Vec3::new(2.0 * self.half_size.x, 2.0 * self.half_size.y, depth),
+            Quat::IDENTITY,
+            self.position.extend(0.0))
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct Ball {
+    pub position : Vec2,
+    pub velocity : Vec2,
+    pub radius : f32,
+}
+
+impl Ball {
+    /// blend between a previous (`alpha` = 0) and this (`alpha` = 1) state
+    pub fn lerp(&self, previous : &Ball, alpha : f32) -> Self {
+        Self {
+            position : previous.position.lerp(self.position, alpha),
+            ..*self
+        }
+    }
+
+    pub fn model_matrix(&self) -> Mat4 {
+        Mat4::from_scale_rotation_translation(
+            Vec3::splat(2.0 * self.radius),
+            Quat::IDENTITY,
+            self.position.extend(0.0))
+    }
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Score {
+    pub left : u32,
+    pub right : u32,
+}
+
+impl Score {
+    pub fn get(&self, side : Side) -> u32 {
+        match side {
+            Side::Left => self.left,
+            Side::Right => self.right,
+        }
+    }
+
+    fn add_point(&mut self, side : Side) {
+        match side {
+            Side::Left => self.left += 1,
+            Side::Right => self.right += 1,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ServeState {
+    /// ball is parked at the center, waiting to be served towards `towards`
+    Waiting { timer : f32, towards : Side },
+    InPlay,
+    GameOver { winner : Side },
+}
+
+/// Input for one paddle, `direction` is -1.0 (down) ..= 1.0 (up)
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct PaddleInput {
+    pub direction : f32,
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct PongInputs {
+    pub left : PaddleInput,
+    pub right : PaddleInput,
+}
+
+impl PongInputs {
+    pub fn get_mut(&mut self, side : Side) -> &mut PaddleInput {
+        match side {
+            Side::Left => &mut self.left,
+            Side::Right => &mut self.right,
+        }
+    }
+}
+
+// what the ball bounced off
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Collider {
+    Wall,
+    Paddle(Side),
+}
+
+/// Things that happened during a single `PongWorld::step`
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PongEvent {
+    Served(Side),
+    WallBounce,
+    PaddleHit(Side),
+    PointScored(Side),
+    GameOver(Side),
+}
+
+/// CPU only simulation of a Pong match, independent of any rendering
+#[derive(Clone, Debug)]
+pub struct PongWorld {
+    pub config : PongConfig,
+    pub arena : Arena,
+    pub left : Paddle,
+    pub right : Paddle,
+    pub ball : Ball,
+    pub score : Score,
+    pub serve : ServeState,
+    serve_count : u32,
+    events : Vec<PongEvent>,
+}
+
+impl PongWorld {
+    pub fn new(config : PongConfig) -> Self {
+        let paddle_x = config.arena_half_size.x - config.paddle_margin - config.paddle_half_size.x;
+        let paddle = |side : Side| Paddle {
+            side,
+            position : Vec2::new(side.direction() * paddle_x, 0.0),
+            half_size : config.paddle_half_size,
+            velocity : 0.0,
+        };
+
+        Self {
+            config,
+            arena : Arena { half_size : config.arena_half_size },
+            left : paddle(Side::Left),
+            right : paddle(Side::Right),
+            ball : Ball {
+                position : Vec2::ZERO,
+                velocity : Vec2::ZERO,
+                radius : config.ball_radius,
+            },
+            score : Score::default(),
+            serve : ServeState::Waiting { timer : config.serve_delay, towards : config.first_serve },
+            serve_count : 0,
+            events : Vec::new(),
+        }
+    }
+
+    /// start a new match with the same configuration
+    pub fn reset(&mut self) {
+        *self = Self::new(self.config);
+    }
+
+    pub fn paddle(&self, side : Side) -> &Paddle {
+        match side {
+            Side::Left => &self.left,
+            Side::Right => &self.right,
+        }
+    }
+
+    pub fn paddle_mut(&mut self, side : Side) -> &mut Paddle {
+        match side {
+            Side::Left => &mut self.left,
+            Side::Right => &mut self.right,
+        }
+    }
+
+    pub fn winner(&self) -> Option<Side> {
+        match self.serve {
+            ServeState::GameOver { winner } => Some(winner),
+            _ => None,
+        }
+    }
+
+    /// events generated by the last call to `step`
+    pub fn events(&self) -> &[PongEvent] {
+        &self.events
+    }
+
+    /// true if the last step moved the ball back to the center instead of along its path,
+    /// interpolating from the previous step would streak it across the arena
+    pub fn ball_teleported(&self) -> bool {
+        self.events.iter().any(|event| matches!(event, PongEvent::PointScored(_) | PongEvent::Served(_)))
+    }
+
+    /// advance the simulation by `dt` seconds
+    pub fn step(&mut self, dt : f32, inputs : &PongInputs) {
+        self.events.clear();
+
+        if self.winner().is_some() {
+            return;
+        }
+
+        self.move_paddle(Side::Left, inputs.left, dt);
+        self.move_paddle(Side::Right, inputs.right, dt);
+
+        match self.serve {
+            ServeState::Waiting { timer, towards } => {
+                let timer = timer - dt;
+                if timer <= 0.0 {
+                    self.launch_ball(towards);
+                } else {
+                    self.serve = ServeState::Waiting { timer, towards };
+                }
+            }
+            ServeState::InPlay => self.move_ball(dt),
+            ServeState::GameOver { .. } => {}
+        }
+    }
+
+    fn move_paddle(&mut self, side : Side, input : PaddleInput, dt : f32) {
+        let speed = self.config.paddle_speed;
+        let limit = self.arena.half_size.y;
+        let paddle = self.paddle_mut(side);
+
+        let old_y = paddle.position.y;
+        let max_y = (limit - paddle.half_size.y).max(0.0);
+        paddle.position.y = (old_y + input.direction.clamp(-1.0, 1.0) * speed * dt).clamp(-max_y, max_y);
+        paddle.velocity = if dt > 0.0 { (paddle.position.y - old_y) / dt } else { 0.0 };
+    }
+
+    fn launch_ball(&mut self, towards : Side) {
+        // alternate the serve angle up/down so consecutive serves differ
+        let angle = if self.serve_count.is_multiple_of(2) { self.config.serve_angle } else { -self.config.serve_angle };
+        self.serve_count += 1;
+
+        self.ball.position = Vec2::ZERO;
+        self.ball.velocity = Vec2::new(towards.direction() * angle.cos(), angle.sin()) * self.config.serve_speed;
+        self.serve = ServeState::InPlay;
+        self.events.push(PongEvent::Served(towards));
+    }
+
+    fn move_ball(&mut self, dt : f32) {
+        // sweep the ball along its path, bounce at the first contact and continue with
+        // the rest of the step, so fast balls can't pass through paddles
+        let mut remaining = dt;
+        for _ in 0..MAX_BOUNCES {
+            if remaining <= 0.0 {
+                break;
+            }
+            let Some((collider, hit)) = self.first_contact(remaining) else {
+                self.ball.position += self.ball.velocity * remaining;
+                break;
+            };
+            let elapsed = hit.time * remaining;
+            self.ball.position += self.ball.velocity * elapsed + hit.normal.truncate() * CONTACT_OFFSET;
+            remaining -= elapsed;
+
+            match collider {
+                Collider::Wall => {
+                    let velocity = physics::reflect(self.ball.velocity.extend(0.0), hit.normal, self.config.restitution);
+                    self.ball.velocity = velocity.truncate();
+                    self.events.push(PongEvent::WallBounce);
+                }
+                Collider::Paddle(side) => self.bounce_off_paddle(side, &hit),
+            }
+        }
+
+        // ball left the arena behind one of the paddles
+        if self.ball.position.x - self.ball.radius > self.arena.half_size.x {
+            self.point_scored(Side::Left);
+        } else if self.ball.position.x + self.ball.radius < -self.arena.half_size.x {
+            self.point_scored(Side::Right);
+        }
+    }
+
+    // earliest contact of the ball within the next `dt` seconds. Paddles move during the
+    // step too, so they are swept with the velocity relative to the paddle.
+    fn first_contact(&self, dt : f32) -> Option<(Collider, SweepHit)> {
+        let ball = &self.ball;
+        let center = ball.position.extend(0.0);
+        let velocity = ball.velocity.extend(0.0);
+
+        let walls = self.arena.wall_aabbs().map(|wall| (Collider::Wall, wall, Vec3::ZERO));
+        let paddles = [Side::Left, Side::Right].map(|side| {
+            let paddle = self.paddle(side);
+            // the paddle already moved for this step, go back to where it was at the start of `dt`
+            let paddle_velocity = Vec3::new(0.0, paddle.velocity, 0.0);
+            let aabb = paddle.aabb();
+            let offset = paddle_velocity * dt;
+            (Collider::Paddle(side), Aabb::new(aabb.min - offset, aabb.max - offset), paddle_velocity)
+        });
+
+        walls.into_iter().chain(paddles)
+            .filter_map(|(collider, aabb, surface_velocity)| {
+                let relative = velocity - surface_velocity;
+                let hit = physics::sweep_sphere_aabb(center, ball.radius, relative * dt, &aabb)?;
+                // touching while already moving apart is not a collision
+                (relative.dot(hit.normal) < 0.0).then_some((collider, hit))
+            })
+            .min_by(|a, b| a.1.time.total_cmp(&b.1.time))
+    }
+
+    fn bounce_off_paddle(&mut self, side : Side, hit : &SweepHit) {
+        let paddle = *self.paddle(side);
+        let config = self.config;
+        let ball = &mut self.ball;
+        let paddle_velocity = Vec3::new(0.0, paddle.velocity, 0.0);
+
+        if hit.normal.x * side.direction() < -0.5 {
+            // front face: the further from the paddle center, the steeper the bounce,
+            // moving the paddle while hitting adds spin
+            let offset = ((ball.position.y - paddle.position.y) / (paddle.half_size.y + ball.radius)).clamp(-1.0, 1.0);
+            let angle = offset * config.max_bounce_angle;
+            let speed = (ball.velocity.length() * config.speedup * config.restitution).min(config.max_ball_speed);
+            let velocity = Vec2::new(-side.direction() * angle.cos(), angle.sin()) * speed
+                + physics::surface_spin(hit.normal, paddle_velocity, config.paddle_spin).truncate();
+
+            // spin must not make the ball steeper than the maximum bounce angle
+            let angle = velocity.y.atan2(velocity.x.abs()).clamp(-config.max_bounce_angle, config.max_bounce_angle);
+            let speed = velocity.length().min(config.max_ball_speed);
+            ball.velocity = Vec2::new(-side.direction() * angle.cos(), angle.sin()) * speed;
+        } else {
+            // top, bottom or back of the paddle: plain bounce in the paddle frame
+            let relative = ball.velocity.extend(0.0) - paddle_velocity;
+            let velocity = physics::reflect(relative, hit.normal, config.restitution) + paddle_velocity;
+            ball.velocity = velocity.truncate().clamp_length_max(config.max_ball_speed);
+        }
+        self.events.push(PongEvent::PaddleHit(side));
+    }
+
+    fn point_scored(&mut self, scorer : Side) {
+        self.score.add_point(scorer);
+        self.events.push(PongEvent::PointScored(scorer));
+
+        self.ball.position = Vec2::ZERO;
+        self.ball.velocity = Vec2::ZERO;
+
+        if self.score.get(scorer) >= self.config.winning_score {
+            self.serve = ServeState::GameOver { winner : scorer };
+            self.events.push(PongEvent::GameOver(scorer));
+        } else {
+            // serve towards the player that lost the point
+            self.serve = ServeState::Waiting {
+                timer : self.config.serve_delay,
+                towards : scorer.opponent(),
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DT : f32 = 1.0 / 120.0;
+
+    // world with the ball already in play
+    fn in_play(position : Vec2, velocity : Vec2) -> PongWorld {
+        let mut world = PongWorld::new(PongConfig::default());
+        world.serve = ServeState::InPlay;
+        world.ball.position = position;
+        world.ball.velocity = velocity;
+        world
+    }
+
+    // step until `event` happens, panics after `max_steps`
+    fn step_until(world : &mut PongWorld, event : PongEvent, max_steps : usize) {
+        for _ in 0..max_steps {
+            world.step(DT, &PongInputs::default());
+            if world.events().contains(&event) {
+                return;
+            }
+        }
+        panic!("no {event:?} within {max_steps} steps");
+    }
+
+    #[test]
+    fn ball_bounces_off_the_top_wall() {
+        let mut world = in_play(Vec2::new(0.0, 2.2), Vec2::new(0.5, 3.0));
+        step_until(&mut world, PongEvent::WallBounce, 60);
+        assert!(!world.ball_teleported());
+
+        assert!(world.ball.velocity.y < 0.0);
+        assert_eq!(world.ball.velocity.x, 0.5);
+        assert!(world.ball.position.y + world.ball.radius <= world.arena.half_size.y);
+    }
+
+    #[test]
+    fn paddle_hit_angle_follows_the_hit_offset() {
+        let config = PongConfig::default();
+        let offset = 0.25;
+        let mut world = in_play(Vec2::new(3.0, offset), Vec2::new(6.0, 0.0));
+        step_until(&mut world, PongEvent::PaddleHit(Side::Right), 60);
+
+        let velocity = world.ball.velocity;
+        let expected_angle = offset / (config.paddle_half_size.y + config.ball_radius) * config.max_bounce_angle;
+        assert!(velocity.x < 0.0);
+        assert!((velocity.y.atan2(-velocity.x) - expected_angle).abs() < 1e-3);
+        assert!((velocity.length() - 6.0 * config.speedup).abs() < 1e-3);
+    }
+
+    #[test]
+    fn center_hit_returns_the_ball_straight() {
+        let mut world = in_play(Vec2::new(-3.0, 0.0), Vec2::new(-6.0, 0.0));
+        step_until(&mut world, PongEvent::PaddleHit(Side::Left), 60);
+
+        assert!(world.ball.velocity.x > 0.0);
+        assert!(world.ball.velocity.y.abs() < 1e-4);
+    }
+
+    #[test]
+    fn missing_the_ball_scores_for_the_opponent() {
+        for (scorer, x) in [(Side::Left, 3.8), (Side::Right, -3.8)] {
+            // far above the paddles, which stay in the middle
+            let mut world = in_play(Vec2::new(x, 2.0), Vec2::new(scorer.opponent().direction() * 3.0, 0.0));
+            step_until(&mut world, PongEvent::PointScored(scorer), 60);
+
+            assert_eq!(world.score.get(scorer), 1);
+            assert_eq!(world.score.get(scorer.opponent()), 0);
+            assert_eq!(world.ball.position, Vec2::ZERO);
+            assert_eq!(world.ball.velocity, Vec2::ZERO);
+            assert_eq!(world.serve, ServeState::Waiting { timer : world.config.serve_delay, towards : scorer.opponent() });
+            assert!(world.ball_teleported());
+        }
+    }
+
+    #[test]
+    fn serve_waits_for_the_delay() {
+        let mut world = PongWorld::new(PongConfig::default());
+        let delay = world.config.serve_delay;
+        let steps = (delay / DT).round() as usize;
+
+        for _ in 0..steps - 2 {
+            world.step(DT, &PongInputs::default());
+            assert!(world.events().is_empty());
+        }
+        assert_eq!(world.ball.velocity, Vec2::ZERO);
+
+        step_until(&mut world, PongEvent::Served(Side::Left), 4);
+        assert_eq!(world.serve, ServeState::InPlay);
+        assert!(world.ball.velocity.x < 0.0);
+    }
+
+    #[test]
+    fn serve_goes_towards_the_player_who_lost_the_point() {
+        let mut world = in_play(Vec2::new(-3.8, 2.0), Vec2::new(-3.0, 0.0));
+        step_until(&mut world, PongEvent::PointScored(Side::Right), 60);
+
+        let steps = (world.config.serve_delay / DT).round() as usize + 2;
+        step_until(&mut world, PongEvent::Served(Side::Left), steps);
+        assert!(world.ball.velocity.x < 0.0);
+    }
+
+    #[test]
+    fn game_over_at_winning_score() {
+        let mut world = in_play(Vec2::new(3.8, 2.0), Vec2::new(3.0, 0.0));
+        world.score.left = world.config.winning_score - 1;
+        step_until(&mut world, PongEvent::GameOver(Side::Left), 60);
+
+        assert!(world.events().contains(&PongEvent::PointScored(Side::Left)));
+        assert_eq!(world.winner(), Some(Side::Left));
+        assert_eq!(world.score.left, world.config.winning_score);
+
+        // nothing moves after the match is over
+        world.step(DT, &PongInputs { left : PaddleInput { direction : 1.0 }, ..Default::default() });
+        assert!(world.events().is_empty());
+        assert_eq!(world.left.position.y, 0.0);
+    }
+
+    #[test]
+    fn reset_starts_a_new_match() {
+        let mut world = in_play(Vec2::new(1.0, 1.0), Vec2::new(3.0, 1.0));
+        world.score.left = 3;
+        world.score.right = 5;
+        world.left.position.y = 1.0;
+
+        world.reset();
+        assert_eq!(world.score, Score::default());
+        assert_eq!(world.ball.position, Vec2::ZERO);
+        assert_eq!(world.left.position.y, 0.0);
+        assert_eq!(world.serve, ServeState::Waiting { timer : world.config.serve_delay, towards : world.config.first_serve });
+        assert!(world.events().is_empty());
+    }
+}
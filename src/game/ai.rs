@@ -0,0 +1,300 @@
+use super::{PaddleInput, PongEvent, PongWorld, ServeState, Side};
+
+/// Preset skill levels for the computer player
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Difficulty {
+    Easy,
+    Normal,
+    Hard,
+    Perfect,
+}
+
+impl Difficulty {
+    pub const ALL : [Difficulty; 4] = [Difficulty::Easy, Difficulty::Normal, Difficulty::Hard, Difficulty::Perfect];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Difficulty::Easy => "easy",
+            Difficulty::Normal => "normal",
+            Difficulty::Hard => "hard",
+            Difficulty::Perfect => "perfect",
+        }
+    }
+
+    pub fn settings(self) -> AiSettings {
+        match self {
+            Difficulty::Easy => AiSettings { reaction_delay : 0.35, max_speed : 0.6, prediction_error : 0.7 },
+            Difficulty::Normal => AiSettings { reaction_delay : 0.25, max_speed : 0.75, prediction_error : 0.55 },
+            Difficulty::Hard => AiSettings { reaction_delay : 0.1, max_speed : 1.0, prediction_error : 0.3 },
+            Difficulty::Perfect => AiSettings { reaction_delay : 0.0, max_speed : 1.0, prediction_error : 0.0 },
+        }
+    }
+}
+
+/// Tuning of an `AiController`
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct AiSettings {
+    /// seconds between the ball changing course and the paddle reacting to it
+    pub reaction_delay : f32,
+    /// fraction of the paddle speed the AI uses, 0..=1
+    pub max_speed : f32,
+    /// the predicted intercept is off by up to this many world units
+    pub prediction_error : f32,
+}
+
+/// Computer player for one paddle. `update` produces the same `PaddleInput`
+/// the keyboard does, so it can stand in for either player.
+#[derive(Clone, Debug)]
+pub struct AiController {
+    pub side : Side,
+    pub settings : AiSettings,
+    // paddle y the AI is moving to
+    target : f32,
+    // time left until the next course change is noticed
+    reaction_timer : Option<f32>,
+    rng : u32,
+}
+
+impl AiController {
+    pub fn new(side : Side, difficulty : Difficulty) -> Self {
+        Self::with_settings(side, difficulty.settings())
+    }
+
+    pub fn with_settings(side : Side, settings : AiSettings) -> Self {
+        Self { side, settings, target : 0.0, reaction_timer : None, rng : 0x9e37_79b9 }
+    }
+
+    /// seed of the prediction error, for reproducible matches
+    pub fn with_seed(self, seed : u32) -> Self {
+        // xorshift gets stuck at 0
+        Self { rng : seed.max(1), ..self }
+    }
+
+    pub fn set_difficulty(&mut self, difficulty : Difficulty) {
+        self.settings = difficulty.settings();
+    }
+
+    /// preset matching the current settings, None for custom settings
+    pub fn difficulty(&self) -> Option<Difficulty> {
+        Difficulty::ALL.into_iter().find(|difficulty| difficulty.settings() == self.settings)
+    }
+
+    /// paddle y the AI is currently moving to
+    pub fn target(&self) -> f32 {
+        self.target
+    }
+
+    /// input for the next step of `world`, call once per step before `PongWorld::step`
+    pub fn update(&mut self, world : &PongWorld, dt : f32) -> PaddleInput {
+        // the events of the last step tell when the ball changed course,
+        // wall bounces are part of the prediction
+        let course_changed = world.events().iter().any(|event| matches!(event,
+            PongEvent::Served(_) | PongEvent::PaddleHit(_) | PongEvent::PointScored(_)));
+        if course_changed {
+            self.reaction_timer = Some(self.settings.reaction_delay);
+        }
+        if let Some(timer) = self.reaction_timer {
+            let timer = timer - dt;
+            if timer <= 0.0 {
+                self.reaction_timer = None;
+                self.plan(world);
+            } else {
+                self.reaction_timer = Some(timer);
+            }
+        }
+
+        // go straight to the target without overshooting it within the step
+        let paddle = world.paddle(self.side);
+        let step = world.config.paddle_speed * dt;
+        if step <= 0.0 {
+            return PaddleInput::default();
+        }
+        let max_speed = self.settings.max_speed.clamp(0.0, 1.0);
+        PaddleInput { direction : ((self.target - paddle.position.y) / step).clamp(-max_speed, max_speed) }
+    }
+
+    fn plan(&mut self, world : &PongWorld) {
+        let incoming = match world.serve {
+            ServeState::InPlay => predict_intercept(world, self.side),
+            _ => None,
+        };
+        // wait in the middle while the ball goes the other way
+        self.target = match incoming {
+            Some(y) => y + self.settings.prediction_error * self.next_signed(),
+            None => 0.0,
+        };
+    }
+
+    // xorshift32, uniform in -1..1
+    fn next_signed(&mut self) -> f32 {
+        let mut x = self.rng;
+        x ^= x << 13;
+        x ^= x >> 17;
+        x ^= x << 5;
+        self.rng = x;
+        (x >> 8) as f32 / (1 << 23) as f32 - 1.0
+    }
+}
+
+/// Height at which the ball will reach the face of `side`'s paddle, following its
+/// bounces off the walls. None when the ball is moving away from that paddle.
+pub fn predict_intercept(world : &PongWorld, side : Side) -> Option<f32> {
+    let ball = &world.ball;
+    let paddle = world.paddle(side);
+    if ball.velocity.x * side.direction() <= 0.0 {
+        return None;
+    }
+
+    let face_x = paddle.position.x - side.direction() * (paddle.half_size.x + ball.radius);
+    let time = ((face_x - ball.position.x) / ball.velocity.x).max(0.0);
+    let y = ball.position.y + ball.velocity.y * time;
+
+    // unfold the straight path into the zig-zag between the walls
+    let limit = (world.arena.half_size.y - ball.radius).max(0.0);
+    if limit == 0.0 {
+        return Some(0.0);
+    }
+    let folded = (y + limit).rem_euclid(4.0 * limit);
+    Some(if folded > 2.0 * limit { 4.0 * limit - folded } else { folded } - limit)
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::Vec2;
+    use super::*;
+    use crate::game::{PongConfig, PongInputs};
+
+    const DT : f32 = 1.0 / 120.0;
+
+    // ball in play at the center, reaches the right paddle face (x = 3.4) after one second
+    fn incoming(velocity_y : f32) -> PongWorld {
+        let mut world = PongWorld::new(PongConfig::default());
+        world.serve = ServeState::InPlay;
+        world.ball.velocity = Vec2::new(3.4, velocity_y);
+        world
+    }
+
+    // reflect off the walls one bounce at a time, returns the height and number of bounces
+    fn reflect(mut y : f32, limit : f32) -> (f32, u32) {
+        let mut bounces = 0;
+        while y.abs() > limit {
+            y = y.signum() * 2.0 * limit - y;
+            bounces += 1;
+        }
+        (y, bounces)
+    }
+
+    #[test]
+    fn intercept_folds_wall_bounces() {
+        let limit = 2.4;
+        for (velocity_y, expected_bounces) in [(0.0, 0), (1.0, 0), (-2.0, 0), (3.0, 1), (-7.0, 1), (8.0, 2), (20.0, 4), (-31.0, 6)] {
+            let world = incoming(velocity_y);
+            let (expected, bounces) = reflect(velocity_y, limit);
+            assert_eq!(bounces, expected_bounces, "{velocity_y}");
+            let y = predict_intercept(&world, Side::Right).unwrap();
+            assert!((y - expected).abs() < 1e-4, "{velocity_y}: {y} != {expected}");
+        }
+    }
+
+    #[test]
+    fn intercept_matches_the_simulation() {
+        let mut world = incoming(8.0);
+        let predicted = predict_intercept(&world, Side::Right).unwrap();
+        // the paddle stays out of the way at the top
+        world.right.position.y = 2.0;
+        for _ in 0..240 {
+            if world.ball.position.x >= 3.4 {
+                break;
+            }
+            world.step(DT, &PongInputs::default());
+        }
+        assert_eq!(world.ball.velocity.x, 3.4);
+        assert!((world.ball.position.y - predicted).abs() < 0.1, "{} != {predicted}", world.ball.position.y);
+    }
+
+    #[test]
+    fn no_intercept_when_moving_away() {
+        let mut world = incoming(1.0);
+        assert!(predict_intercept(&world, Side::Left).is_none());
+        world.ball.velocity.x = -3.4;
+        assert!(predict_intercept(&world, Side::Right).is_none());
+        assert!(predict_intercept(&world, Side::Left).is_some());
+        world.ball.velocity.x = 0.0;
+        assert!(predict_intercept(&world, Side::Left).is_none());
+        assert!(predict_intercept(&world, Side::Right).is_none());
+    }
+
+    // number of updates after a course change until the AI picks a new target
+    fn reaction_steps(ai : &mut AiController, world : &mut PongWorld) -> u32 {
+        world.events = vec![PongEvent::PaddleHit(Side::Left)];
+        for steps in 1..1000 {
+            ai.update(world, DT);
+            world.events.clear();
+            if ai.target() != 0.0 {
+                return steps;
+            }
+        }
+        panic!("the AI never reacted");
+    }
+
+    #[test]
+    fn reaction_delay_per_difficulty() {
+        for difficulty in Difficulty::ALL {
+            let mut world = incoming(1.0);
+            let mut ai = AiController::new(Side::Right, difficulty);
+            let delay = reaction_steps(&mut ai, &mut world) as f32 * DT;
+            let expected = difficulty.settings().reaction_delay;
+            assert!(delay >= expected - 1e-4 && delay <= expected + DT + 1e-4, "{difficulty:?}: {delay}");
+        }
+    }
+
+    #[test]
+    fn speed_is_limited_per_difficulty() {
+        for difficulty in Difficulty::ALL {
+            let mut world = incoming(2.0);
+            let mut ai = AiController::new(Side::Right, difficulty);
+            reaction_steps(&mut ai, &mut world);
+            // the target is far away, so the AI goes as fast as it may
+            let input = ai.update(&world, DT);
+            assert_eq!(input.direction, difficulty.settings().max_speed, "{difficulty:?}");
+        }
+
+        // custom settings can't go faster than the paddle
+        let settings = AiSettings { reaction_delay : 0.0, max_speed : 3.0, prediction_error : 0.0 };
+        let mut world = incoming(2.0);
+        let mut ai = AiController::with_settings(Side::Right, settings);
+        reaction_steps(&mut ai, &mut world);
+        assert_eq!(ai.update(&world, DT).direction, 1.0);
+        assert_eq!(ai.difficulty(), None);
+
+        // and slow down instead of overshooting
+        world.right.position.y = ai.target() - 0.5 * world.config.paddle_speed * DT;
+        assert!((ai.update(&world, DT).direction - 0.5).abs() < 1e-3);
+    }
+
+    #[test]
+    fn prediction_error_per_difficulty() {
+        for difficulty in Difficulty::ALL {
+            let error = difficulty.settings().prediction_error;
+            let mut largest : f32 = 0.0;
+            for seed in 1..50 {
+                let mut world = incoming(1.0);
+                let mut ai = AiController::new(Side::Right, difficulty).with_seed(seed);
+                reaction_steps(&mut ai, &mut world);
+                let miss = (ai.target() - 1.0).abs();
+                assert!(miss <= error + 1e-4, "{difficulty:?} seed {seed}: {miss}");
+                largest = largest.max(miss);
+
+                // the same seed plays the same
+                let mut again = AiController::new(Side::Right, difficulty).with_seed(seed);
+                reaction_steps(&mut again, &mut world);
+                assert_eq!(again.target(), ai.target());
+            }
+            if error > 0.0 {
+                assert!(largest > 0.5 * error, "{difficulty:?}: {largest}");
+            } else {
+                assert!(largest < 1e-4, "{difficulty:?}: {largest}");
+            }
+        }
+    }
+}
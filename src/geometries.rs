@@ -88,6 +88,124 @@ impl TexCoordElement {
     }
 }  
 
+/// Tangent along +U, w is the handedness of the bitangent:
+/// bitangent = cross(normal, tangent.xyz) * tangent.w
+#[repr(C)]
+#[derive(Default, Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct TangentElement {
+    pub tangent: [f32; 4],
+}
+
+impl TangentElement {   
+    pub fn desc<const N: u32>() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<Self>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &[
+                wgpu::VertexAttribute {
+                    offset: 0,
+                    shader_location: N,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
+            ]
+        }
+
+    }
+}
+
+/// Per vertex tangents of an indexed triangle list, MikkTSpace style:
+/// the per triangle UV derivatives are weighted by the corner angle,
+/// orthogonalized against the vertex normal and given a handedness sign.
+pub fn compute_tangents(
+    positions : &[PositionElement],
+    normals : &[NormalElement],
+    tex_coords : &[TexCoordElement],
+    indices : &[u32] ) -> Vec<TangentElement> {
+
+    use glam::{Vec2, Vec3};
+
+    let mut tangents = vec![Vec3::ZERO; positions.len()];
+    let mut bitangents = vec![Vec3::ZERO; positions.len()];
+
+    for triangle in indices.chunks_exact(3) {
+        let i = [triangle[0] as usize, triangle[1] as usize, triangle[2] as usize];
+        let p = i.map(|i| Vec3::from(positions[i].position));
+        let uv = i.map(|i| Vec2::from(tex_coords[i].position));
+
+        let (e1, e2) = (p[1] - p[0], p[2] - p[0]);
+        let (d1, d2) = (uv[1] - uv[0], uv[2] - uv[0]);
+        let det = d1.x * d2.y - d2.x * d1.y;
+        // no usable UV mapping on this triangle
+        if det.abs() < f32::EPSILON {
+            continue;
+        }
+        let tangent = (e1 * d2.y - e2 * d1.y) / det;
+        let bitangent = (e2 * d1.x - e1 * d2.x) / det;
+
+        for corner in 0..3 {
+            let a = p[(corner + 1) % 3] - p[corner];
+            let b = p[(corner + 2) % 3] - p[corner];
+            let angle = a.angle_between(b);
+            if angle.is_finite() {
+                tangents[i[corner]] += tangent * angle;
+                bitangents[i[corner]] += bitangent * angle;
+            }
+        }
+    }
+
+    tangents.iter().zip(&bitangents).zip(normals).map(|((tangent, bitangent), normal)| {
+        let normal = Vec3::from(normal.normal).normalize_or_zero();
+        // Gram-Schmidt, fall back to any perpendicular for vertices without UV derivatives
+        let mut t = (*tangent - normal * normal.dot(*tangent)).normalize_or_zero();
+        if t == Vec3::ZERO {
+            t = normal.any_orthonormal_vector();
+        }
+        let w = if normal.cross(t).dot(*bitangent) < 0.0 { -1.0 } else { 1.0 };
+        TangentElement { tangent: [t.x, t.y, t.z, w] }
+    }).collect()
+}
+
+/// Per instance data for instanced drawing: model matrix and tint color.
+/// Takes 5 shader locations: N..N+3 for the matrix columns and N+4 for the color.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct InstanceElement {
+    pub model_matrix: [[f32; 4]; 4],
+    pub color: [f32; 4],
+}
+
+impl Default for InstanceElement {
+    fn default() -> Self {
+        Self::new(glam::Mat4::IDENTITY, [1.0, 1.0, 1.0, 1.0])
+    }
+}
+
+struct InstanceAttributes<const N: u32>;
+
+impl<const N: u32> InstanceAttributes<N> {
+    const ATTRIBUTES: [wgpu::VertexAttribute; 5] = [
+        wgpu::VertexAttribute { offset: 0, shader_location: N, format: wgpu::VertexFormat::Float32x4 },
+        wgpu::VertexAttribute { offset: 16, shader_location: N + 1, format: wgpu::VertexFormat::Float32x4 },
+        wgpu::VertexAttribute { offset: 32, shader_location: N + 2, format: wgpu::VertexFormat::Float32x4 },
+        wgpu::VertexAttribute { offset: 48, shader_location: N + 3, format: wgpu::VertexFormat::Float32x4 },
+        wgpu::VertexAttribute { offset: 64, shader_location: N + 4, format: wgpu::VertexFormat::Float32x4 },
+    ];
+}
+
+impl InstanceElement {
+    pub fn new(model_matrix: glam::Mat4, color: [f32; 4]) -> Self {
+        Self { model_matrix: model_matrix.to_cols_array_2d(), color }
+    }
+
+    pub fn desc<const N: u32>() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<Self>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &InstanceAttributes::<N>::ATTRIBUTES,
+        }
+    }
+}
+
 // create array with position and position data
 pub const QUAD_POSITIONS: &[PositionElement] = &[
     PositionElement { position: [-0.5, -0.5, 0.0] },    // bottom left
@@ -133,14 +251,20 @@ pub struct QuadGeometry<'a> {
     pub indices : &'a[u32],
 }
 
+impl<'a> Default for QuadGeometry<'a> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl<'a> QuadGeometry<'a> {
     pub fn new() -> Self {
         Self {
-            positions : &QUAD_POSITIONS,
-            colors : &QUAD_COLORS,
-            indices : &QUAD_INDICES,
-            normals : &QUAD_NORMALS,
-            tex_coords : &QUAD_TEXCOORDS,
+            positions : QUAD_POSITIONS,
+            colors : QUAD_COLORS,
+            indices : QUAD_INDICES,
+            normals : QUAD_NORMALS,
+            tex_coords : QUAD_TEXCOORDS,
         }
     }
 }
@@ -330,15 +454,64 @@ pub const CUBE_NORMALS: &[NormalElement] = &[
         pub indices : &'a[u32],
     }
     
+    impl<'a> Default for CubeGeometry<'a> {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
     impl<'a> CubeGeometry<'a> {
         pub fn new() -> Self {
             Self {
-                positions : &CUBE_POSITIONS,
-                normals : &CUBE_NORMALS,
-                colors : &CUBE_COLORS,
-                indices : &CUBE_INDICES,
-                tex_coords : &CUBE_TEXCOORDS,
+                positions : CUBE_POSITIONS,
+                normals : CUBE_NORMALS,
+                colors : CUBE_COLORS,
+                indices : CUBE_INDICES,
+                tex_coords : CUBE_TEXCOORDS,
             }
         }
     }
-    
\ No newline at end of file
+    
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // unit quad in the XY plane facing +Z, corners bottom left, bottom right, top left, top right
+    fn quad_tangents(tex_coords : [[f32; 2]; 4]) -> Vec<TangentElement> {
+        let positions = [[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [1.0, 1.0, 0.0]]
+            .map(|position| PositionElement { position });
+        let normals = [NormalElement { normal: [0.0, 0.0, 1.0] }; 4];
+        let tex_coords = tex_coords.map(|position| TexCoordElement { position });
+        compute_tangents(&positions, &normals, &tex_coords, QUAD_INDICES)
+    }
+
+    #[test]
+    fn tangent_follows_u() {
+        // V up along +Y, right handed
+        for t in quad_tangents([[0.0, 0.0], [1.0, 0.0], [0.0, 1.0], [1.0, 1.0]]) {
+            assert_eq!(t.tangent, [1.0, 0.0, 0.0, 1.0]);
+        }
+        // U mirrored, the tangent points the other way
+        for t in quad_tangents([[1.0, 0.0], [0.0, 0.0], [1.0, 1.0], [0.0, 1.0]]) {
+            assert_eq!(t.tangent, [-1.0, 0.0, 0.0, -1.0]);
+        }
+    }
+
+    #[test]
+    fn mirrored_v_flips_the_handedness() {
+        // V down along -Y, like image rows
+        for t in quad_tangents([[0.0, 1.0], [1.0, 1.0], [0.0, 0.0], [1.0, 0.0]]) {
+            assert_eq!(t.tangent, [1.0, 0.0, 0.0, -1.0]);
+        }
+    }
+
+    #[test]
+    fn degenerate_uvs_still_give_a_perpendicular_tangent() {
+        for t in quad_tangents([[0.5, 0.5]; 4]) {
+            let [x, y, z, w] = t.tangent;
+            assert!((glam::Vec3::new(x, y, z).length() - 1.0).abs() < 1e-6);
+            assert_eq!(z, 0.0);
+            assert_eq!(w.abs(), 1.0);
+        }
+    }
+}
@@ -0,0 +1,67 @@
+use crate::errors::WvtError;
+
+/// wgpu instance, adapter, device and queue, shared by windowed and headless rendering
+#[derive(Debug)]
+pub struct Gpu {
+    pub instance : wgpu::Instance,
+    pub adapter : wgpu::Adapter,
+    pub device : wgpu::Device,
+    pub queue : wgpu::Queue,
+}
+
+impl Gpu {
+    pub fn create_instance() -> wgpu::Instance {
+        wgpu::Instance::new(wgpu::InstanceDescriptor {
+            backends: wgpu::Backends::all(),
+            ..Default::default()
+        })
+    }
+
+    /// request an adapter (compatible with `surface` if given) and a device on `instance`
+    pub async fn new(
+        instance : wgpu::Instance,
+        surface : Option<&wgpu::Surface>,
+        force_fallback_adapter : bool ) -> Result<Self, WvtError> {
+
+        let adapter = instance.request_adapter(
+            &wgpu::RequestAdapterOptions {
+                power_preference: wgpu::PowerPreference::default(),
+                compatible_surface: surface,
+                force_fallback_adapter,
+            },
+        ).await.ok_or(WvtError::NoAdapter)?;
+
+        let (device, queue) = adapter.request_device(
+            &wgpu::DeviceDescriptor {
+                features: wgpu::Features::empty(),
+                limits: if cfg!(target_arch = "wasm32") {
+                    wgpu::Limits::downlevel_webgl2_defaults()
+                } else {
+                    wgpu::Limits::default()
+                },
+                label: None,
+            },
+            None, // Trace path
+        ).await?;
+
+        Ok(Self { instance, adapter, device, queue })
+    }
+
+    /// gpu without a window, e.g. for tests and screenshots. With `force_fallback_adapter`
+    /// a software adapter is used, so it also runs on CI machines without a GPU.
+    pub async fn headless(force_fallback_adapter : bool) -> Result<Self, WvtError> {
+        Self::new(Self::create_instance(), None, force_fallback_adapter).await
+    }
+}
+
+/// software gpu for unit tests, `None` when the machine has no fallback adapter
+#[cfg(test)]
+pub(crate) fn test_gpu() -> Option<Gpu> {
+    match pollster::block_on(Gpu::headless(true)) {
+        Ok(gpu) => Some(gpu),
+        Err(e) => {
+            eprintln!("skipping gpu test, no fallback adapter: {e}");
+            None
+        }
+    }
+}
@@ -0,0 +1,605 @@
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+use glam::Vec2;
+use winit::event::{ElementState, KeyboardInput, MouseButton, MouseScrollDelta, VirtualKeyCode, WindowEvent};
+
+use crate::errors::WvtError;
+use crate::game::{PaddleInput, PongInputs, Side};
+
+#[cfg(feature = "gamepad")]
+pub mod gamepad;
+
+// how far a stick has to be pushed before it counts as a button press
+const STICK_THRESHOLD : f32 = 0.5;
+
+/// Named game actions, decoupled from the physical keys/buttons that trigger them
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Action {
+    PaddleUp(Side),
+    PaddleDown(Side),
+    Pause,
+    Confirm,
+    /// leave the current screen, pauses a running match
+    Back,
+    /// show frame rate and debug text
+    ToggleDebug,
+}
+
+impl Action {
+    pub const ALL : [Action; 8] = [
+        Action::PaddleUp(Side::Left),
+        Action::PaddleDown(Side::Left),
+        Action::PaddleUp(Side::Right),
+        Action::PaddleDown(Side::Right),
+        Action::Pause,
+        Action::Confirm,
+        Action::Back,
+        Action::ToggleDebug,
+    ];
+
+    /// name used in input config files
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::PaddleUp(Side::Left) => "left_paddle_up",
+            Action::PaddleDown(Side::Left) => "left_paddle_down",
+            Action::PaddleUp(Side::Right) => "right_paddle_up",
+            Action::PaddleDown(Side::Right) => "right_paddle_down",
+            Action::Pause => "pause",
+            Action::Confirm => "confirm",
+            Action::Back => "back",
+            Action::ToggleDebug => "toggle_debug",
+        }
+    }
+
+    pub fn from_name(name : &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|a| a.name() == name)
+    }
+}
+
+/// Gamepad buttons by position, sticks pushed past a threshold count as buttons too
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum GamepadButton {
+    South, East, North, West,
+    LeftTrigger, LeftTrigger2, RightTrigger, RightTrigger2,
+    Select, Start, LeftThumb, RightThumb,
+    DPadUp, DPadDown, DPadLeft, DPadRight,
+    LeftStickUp, LeftStickDown, RightStickUp, RightStickDown,
+}
+
+/// A physical input that can be bound to an action.
+/// winit has no gamepad events, gamepads are polled by `gamepad::Gamepads`
+/// with the `gamepad` feature and fed through `InputState::set_binding`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Binding {
+    Key(VirtualKeyCode),
+    Mouse(MouseButton),
+    /// `pad` counts from 1 in the order the gamepads were connected
+    Gamepad { pad : u8, button : GamepadButton },
+}
+
+macro_rules! key_names {
+    ($($key:ident),* $(,)?) => {
+        fn key_from_name(name : &str) -> Option<VirtualKeyCode> {
+            match name {
+                $(stringify!($key) => Some(VirtualKeyCode::$key),)*
+                _ => None,
+            }
+        }
+    };
+}
+
+key_names!(
+    A, B, C, D, E, F, G, H, I, J, K, L, M, N, O, P, Q, R, S, T, U, V, W, X, Y, Z,
+    Key0, Key1, Key2, Key3, Key4, Key5, Key6, Key7, Key8, Key9,
+    Numpad0, Numpad1, Numpad2, Numpad3, Numpad4, Numpad5, Numpad6, Numpad7, Numpad8, Numpad9,
+    F1, F2, F3, F4, F5, F6, F7, F8, F9, F10, F11, F12,
+    Up, Down, Left, Right, Space, Return, NumpadEnter, Escape, Tab, Back, Delete, Insert,
+    Home, End, PageUp, PageDown, LShift, RShift, LControl, RControl, LAlt, RAlt,
+    Minus, Equals, Comma, Period, Slash, Semicolon, Apostrophe, LBracket, RBracket, Backslash, Grave,
+);
+
+macro_rules! gamepad_button_names {
+    ($($button:ident),* $(,)?) => {
+        fn gamepad_button_from_name(name : &str) -> Option<GamepadButton> {
+            match name {
+                $(stringify!($button) => Some(GamepadButton::$button),)*
+                _ => None,
+            }
+        }
+    };
+}
+
+gamepad_button_names!(
+    South, East, North, West,
+    LeftTrigger, LeftTrigger2, RightTrigger, RightTrigger2,
+    Select, Start, LeftThumb, RightThumb,
+    DPadUp, DPadDown, DPadLeft, DPadRight,
+    LeftStickUp, LeftStickDown, RightStickUp, RightStickDown,
+);
+
+impl Binding {
+    /// parse a binding as written in input config files, e.g. `W`, `Up`, `MouseLeft` or `Pad1South`
+    pub fn from_name(name : &str) -> Option<Self> {
+        match name {
+            "MouseLeft" => Some(Binding::Mouse(MouseButton::Left)),
+            "MouseRight" => Some(Binding::Mouse(MouseButton::Right)),
+            "MouseMiddle" => Some(Binding::Mouse(MouseButton::Middle)),
+            _ => key_from_name(name).map(Binding::Key).or_else(|| Self::gamepad_from_name(name)),
+        }
+    }
+
+    // `Pad<number><button>`, pads count from 1
+    fn gamepad_from_name(name : &str) -> Option<Self> {
+        let rest = name.strip_prefix("Pad")?;
+        let digits = rest.find(|c : char| !c.is_ascii_digit())?;
+        let pad = rest[..digits].parse().ok().filter(|pad| *pad > 0)?;
+        let button = gamepad_button_from_name(&rest[digits..])?;
+        Some(Binding::Gamepad { pad, button })
+    }
+
+    pub fn is_gamepad(&self) -> bool {
+        matches!(self, Binding::Gamepad { .. })
+    }
+}
+
+/// Rebindable table from actions to the bindings that trigger them
+#[derive(Clone, Debug)]
+pub struct InputMap {
+    bindings : HashMap<Action, Vec<Binding>>,
+}
+
+impl Default for InputMap {
+    fn default() -> Self {
+        let mut map = Self { bindings : HashMap::new() };
+        map.bind(Action::PaddleUp(Side::Left), Binding::Key(VirtualKeyCode::W));
+        map.bind(Action::PaddleDown(Side::Left), Binding::Key(VirtualKeyCode::S));
+        map.bind(Action::PaddleUp(Side::Right), Binding::Key(VirtualKeyCode::Up));
+        map.bind(Action::PaddleDown(Side::Right), Binding::Key(VirtualKeyCode::Down));
+        map.bind(Action::Pause, Binding::Key(VirtualKeyCode::P));
+        map.bind(Action::Confirm, Binding::Key(VirtualKeyCode::Return));
+        map.bind(Action::Confirm, Binding::Key(VirtualKeyCode::Space));
+        map.bind(Action::Back, Binding::Key(VirtualKeyCode::Escape));
+        map.bind(Action::ToggleDebug, Binding::Key(VirtualKeyCode::F3));
+
+        // the first gamepad plays the left paddle, the second one the right paddle
+        for (pad, side) in [(1, Side::Left), (2, Side::Right)] {
+            let button = |button| Binding::Gamepad { pad, button };
+            map.bind(Action::PaddleUp(side), button(GamepadButton::DPadUp));
+            map.bind(Action::PaddleUp(side), button(GamepadButton::LeftStickUp));
+            map.bind(Action::PaddleDown(side), button(GamepadButton::DPadDown));
+            map.bind(Action::PaddleDown(side), button(GamepadButton::LeftStickDown));
+            map.bind(Action::Pause, button(GamepadButton::Start));
+            map.bind(Action::Confirm, button(GamepadButton::South));
+            map.bind(Action::Back, button(GamepadButton::East));
+        }
+        map
+    }
+}
+
+impl InputMap {
+    pub fn empty() -> Self {
+        Self { bindings : HashMap::new() }
+    }
+
+    pub fn bind(&mut self, action : Action, binding : Binding) {
+        let bindings = self.bindings.entry(action).or_default();
+        if !bindings.contains(&binding) {
+            bindings.push(binding);
+        }
+    }
+
+    pub fn unbind_all(&mut self, action : Action) {
+        self.bindings.remove(&action);
+    }
+
+    pub fn bindings(&self, action : Action) -> &[Binding] {
+        self.bindings.get(&action).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    pub fn is_bound(&self, binding : Binding) -> bool {
+        self.bindings.values().any(|b| b.contains(&binding))
+    }
+
+    /// Apply an input config on top of this map. Each line has the form
+    /// `action = binding, binding, ...` and replaces all bindings of that action,
+    /// empty lines and lines starting with `#` are ignored.
+    /// A listed binding is taken away from the actions the config doesn't mention,
+    /// so an older `pause = Escape` doesn't also go back.
+    pub fn apply_config(&mut self, config : &str) -> Result<(), WvtError> {
+        let mut configured = HashSet::new();
+        for (index, line) in config.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let error = |message : String| WvtError::InputConfig { line : index + 1, message };
+
+            let (name, bindings) = line.split_once('=')
+                .ok_or_else(|| error(format!("expected `action = binding`, got `{line}`")))?;
+            let action = Action::from_name(name.trim())
+                .ok_or_else(|| error(format!("unknown action `{}`", name.trim())))?;
+
+            self.unbind_all(action);
+            configured.insert(action);
+            for name in bindings.split(',').map(str::trim).filter(|b| !b.is_empty()) {
+                let binding = Binding::from_name(name)
+                    .ok_or_else(|| error(format!("unknown binding `{name}`")))?;
+                self.bind(action, binding);
+                for (other, bindings) in self.bindings.iter_mut().filter(|(other, _)| !configured.contains(*other)) {
+                    if bindings.contains(&binding) {
+                        log::info!("input config line {}: {name} no longer triggers {}", index + 1, other.name());
+                        bindings.retain(|b| *b != binding);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// default bindings overridden by the config file at `path`
+    pub fn load(path : impl AsRef<Path>) -> Result<Self, WvtError> {
+        let config = std::fs::read_to_string(path)?;
+        let mut map = Self::default();
+        map.apply_config(&config)?;
+        Ok(map)
+    }
+}
+
+/// Tracks keyboard/mouse state from window events and answers per tick queries on actions.
+/// Presses and releases are kept until `end_tick` so a fixed step never misses them.
+#[derive(Debug)]
+pub struct InputState {
+    pub map : InputMap,
+    held : HashSet<Binding>,
+    pressed : HashSet<Binding>,
+    released : HashSet<Binding>,
+    cursor_position : Option<Vec2>,
+    cursor_delta : Vec2,
+    scroll_delta : f32,
+}
+
+impl InputState {
+    pub fn new(map : InputMap) -> Self {
+        Self {
+            map,
+            held : HashSet::new(),
+            pressed : HashSet::new(),
+            released : HashSet::new(),
+            cursor_position : None,
+            cursor_delta : Vec2::ZERO,
+            scroll_delta : 0.0,
+        }
+    }
+
+    /// feed a window event, returns true if the event was bound to an action
+    pub fn handle_event(&mut self, event : &WindowEvent) -> bool {
+        match event {
+            WindowEvent::KeyboardInput {
+                input: KeyboardInput {
+                    state,
+                    virtual_keycode: Some(keycode),
+                    ..
+                },
+                ..
+            } => self.set_binding(Binding::Key(*keycode), *state),
+            WindowEvent::MouseInput { state, button, .. } => self.set_binding(Binding::Mouse(*button), *state),
+            WindowEvent::CursorMoved { position, .. } => {
+                let position = Vec2::new(position.x as f32, position.y as f32);
+                // no jump when the cursor comes back into the window
+                if let Some(previous) = self.cursor_position {
+                    self.cursor_delta += position - previous;
+                }
+                self.cursor_position = Some(position);
+                false
+            }
+            WindowEvent::CursorLeft { .. } => {
+                self.cursor_position = None;
+                false
+            }
+            WindowEvent::MouseWheel { delta, .. } => {
+                self.scroll_delta += match delta {
+                    MouseScrollDelta::LineDelta(_, y) => *y,
+                    MouseScrollDelta::PixelDelta(p) => p.y as f32 / 100.0,
+                };
+                false
+            }
+            WindowEvent::Focused(false) => {
+                // we won't get the keyboard and mouse release events, so let go of those.
+                // gamepads are polled and still report their releases.
+                let lost : Vec<_> = self.held.iter().filter(|b| !b.is_gamepad()).copied().collect();
+                for binding in lost {
+                    self.set_binding(binding, ElementState::Released);
+                }
+                false
+            }
+            _ => false,
+        }
+    }
+
+    /// press or release a binding directly, e.g. from another input device
+    pub fn set_binding(&mut self, binding : Binding, state : ElementState) -> bool {
+        match state {
+            ElementState::Pressed => {
+                // ignore key repeats
+                if self.held.insert(binding) {
+                    self.pressed.insert(binding);
+                }
+            }
+            ElementState::Released => {
+                if self.held.remove(&binding) {
+                    self.released.insert(binding);
+                }
+            }
+        }
+        self.map.is_bound(binding)
+    }
+
+    /// stick position on one axis as two bindings, `value` in -1..=1.
+    /// Pushing past the threshold presses `negative` or `positive`, going back releases it.
+    pub fn set_axis(&mut self, negative : Binding, positive : Binding, value : f32) -> bool {
+        let state = |pushed : bool| if pushed { ElementState::Pressed } else { ElementState::Released };
+        let handled_negative = self.set_binding(negative, state(value <= -STICK_THRESHOLD));
+        let handled_positive = self.set_binding(positive, state(value >= STICK_THRESHOLD));
+        handled_negative || handled_positive
+    }
+
+    /// release everything held on gamepad `pad`, e.g. when it is disconnected
+    pub fn release_gamepad(&mut self, pad : u8) {
+        let held : Vec<_> = self.held.iter()
+            .filter(|b| matches!(b, Binding::Gamepad { pad : p, .. } if *p == pad))
+            .copied()
+            .collect();
+        for binding in held {
+            self.set_binding(binding, ElementState::Released);
+        }
+    }
+
+    /// true while any binding of `action` is down
+    pub fn is_held(&self, action : Action) -> bool {
+        self.map.bindings(action).iter().any(|b| self.held.contains(b))
+    }
+
+    /// true during the first tick after any binding of `action` went down
+    pub fn is_pressed(&self, action : Action) -> bool {
+        self.map.bindings(action).iter().any(|b| self.pressed.contains(b))
+    }
+
+    /// true during the first tick after a binding of `action` went up
+    pub fn is_released(&self, action : Action) -> bool {
+        self.map.bindings(action).iter().any(|b| self.released.contains(b))
+    }
+
+    /// -1.0, 0.0 or 1.0 depending on which of the two actions is held
+    pub fn axis(&self, negative : Action, positive : Action) -> f32 {
+        let value = |action| if self.is_held(action) { 1.0 } else { 0.0 };
+        value(positive) - value(negative)
+    }
+
+    pub fn paddle_input(&self, side : Side) -> PaddleInput {
+        PaddleInput { direction : self.axis(Action::PaddleDown(side), Action::PaddleUp(side)) }
+    }
+
+    pub fn pong_inputs(&self) -> PongInputs {
+        PongInputs {
+            left : self.paddle_input(Side::Left),
+            right : self.paddle_input(Side::Right),
+        }
+    }
+
+    pub fn is_button_held(&self, button : MouseButton) -> bool {
+        self.held.contains(&Binding::Mouse(button))
+    }
+
+    pub fn is_key_held(&self, key : VirtualKeyCode) -> bool {
+        self.held.contains(&Binding::Key(key))
+    }
+
+    /// cursor position in physical window pixels, None when outside the window
+    pub fn cursor_position(&self) -> Option<Vec2> {
+        self.cursor_position
+    }
+
+    /// cursor movement in physical pixels since the last tick
+    pub fn cursor_delta(&self) -> Vec2 {
+        self.cursor_delta
+    }
+
+    /// scroll wheel movement (in lines) since the last tick
+    pub fn scroll_delta(&self) -> f32 {
+        self.scroll_delta
+    }
+
+    /// call after every simulation tick to clear the pressed/released edges
+    pub fn end_tick(&mut self) {
+        self.pressed.clear();
+        self.released.clear();
+        self.cursor_delta = Vec2::ZERO;
+        self.scroll_delta = 0.0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(key : VirtualKeyCode) -> Binding {
+        Binding::Key(key)
+    }
+
+    #[test]
+    fn config_skips_comments_and_empty_lines() {
+        let mut map = InputMap::default();
+        map.apply_config("# bindings\n\n   # indented comment\nconfirm = C\n").unwrap();
+        assert_eq!(map.bindings(Action::Confirm), &[key(VirtualKeyCode::C)]);
+        // untouched actions keep their defaults
+        assert_eq!(map.bindings(Action::PaddleUp(Side::Left)), &[
+            key(VirtualKeyCode::W),
+            Binding::Gamepad { pad : 1, button : GamepadButton::DPadUp },
+            Binding::Gamepad { pad : 1, button : GamepadButton::LeftStickUp },
+        ]);
+    }
+
+    #[test]
+    fn config_replaces_bindings_with_all_listed() {
+        let mut map = InputMap::default();
+        map.apply_config("left_paddle_up = I, Up ,MouseLeft,\nright_paddle_down =").unwrap();
+        assert_eq!(map.bindings(Action::PaddleUp(Side::Left)),
+            &[key(VirtualKeyCode::I), key(VirtualKeyCode::Up), Binding::Mouse(MouseButton::Left)]);
+        assert!(map.bindings(Action::PaddleDown(Side::Right)).is_empty());
+    }
+
+    #[test]
+    fn config_bindings_override_other_defaults() {
+        // an older config from when Escape paused
+        let mut map = InputMap::default();
+        map.apply_config("pause = Escape, P").unwrap();
+        assert_eq!(map.bindings(Action::Pause), &[key(VirtualKeyCode::Escape), key(VirtualKeyCode::P)]);
+        assert_eq!(map.bindings(Action::Back), &[
+            Binding::Gamepad { pad : 1, button : GamepadButton::East },
+            Binding::Gamepad { pad : 2, button : GamepadButton::East },
+        ]);
+
+        // actions listed in the same config can share a binding
+        let mut map = InputMap::default();
+        map.apply_config("back = Escape\npause = Escape").unwrap();
+        assert_eq!(map.bindings(Action::Back), &[key(VirtualKeyCode::Escape)]);
+        assert_eq!(map.bindings(Action::Pause), &[key(VirtualKeyCode::Escape)]);
+    }
+
+    #[test]
+    fn config_errors_name_the_line() {
+        let error = |config : &str| match InputMap::default().apply_config(config) {
+            Err(WvtError::InputConfig { line, message }) => (line, message),
+            other => panic!("expected an input config error, got {other:?}"),
+        };
+
+        let (line, message) = error("pause = P\njump = Space");
+        assert_eq!(line, 2);
+        assert!(message.contains("jump"), "{message}");
+
+        let (line, message) = error("# keys\npause = Pause");
+        assert_eq!(line, 2);
+        assert!(message.contains("Pause"), "{message}");
+
+        let (line, _) = error("pause P");
+        assert_eq!(line, 1);
+    }
+
+    #[test]
+    fn gamepad_binding_names() {
+        assert_eq!(Binding::from_name("Pad1South"), Some(Binding::Gamepad { pad : 1, button : GamepadButton::South }));
+        assert_eq!(Binding::from_name("Pad12DPadUp"), Some(Binding::Gamepad { pad : 12, button : GamepadButton::DPadUp }));
+        for name in ["Pad0South", "PadSouth", "Pad1", "Pad1Jump", "Pad999South"] {
+            assert_eq!(Binding::from_name(name), None, "{name}");
+        }
+
+        let mut map = InputMap::default();
+        map.apply_config("confirm = Pad2West, Return").unwrap();
+        assert_eq!(map.bindings(Action::Confirm),
+            &[Binding::Gamepad { pad : 2, button : GamepadButton::West }, key(VirtualKeyCode::Return)]);
+    }
+
+    #[test]
+    fn gamepads_play_one_paddle_each() {
+        let mut input = InputState::new(InputMap::default());
+        let pad = |pad, button| Binding::Gamepad { pad, button };
+        assert!(input.set_binding(pad(2, GamepadButton::DPadUp), ElementState::Pressed));
+        assert_eq!(input.paddle_input(Side::Right).direction, 1.0);
+        assert_eq!(input.paddle_input(Side::Left).direction, 0.0);
+        assert!(input.set_binding(pad(1, GamepadButton::South), ElementState::Pressed));
+        assert!(input.is_pressed(Action::Confirm));
+    }
+
+    #[test]
+    fn stick_past_the_threshold_is_a_button() {
+        let mut input = InputState::new(InputMap::default());
+        let up = Binding::Gamepad { pad : 1, button : GamepadButton::LeftStickUp };
+        let down = Binding::Gamepad { pad : 1, button : GamepadButton::LeftStickDown };
+
+        assert!(input.set_axis(down, up, 0.3));
+        assert_eq!(input.paddle_input(Side::Left).direction, 0.0);
+        input.set_axis(down, up, 0.8);
+        assert!(input.is_pressed(Action::PaddleUp(Side::Left)));
+        assert_eq!(input.paddle_input(Side::Left).direction, 1.0);
+        input.set_axis(down, up, -0.9);
+        assert!(input.is_released(Action::PaddleUp(Side::Left)));
+        assert_eq!(input.paddle_input(Side::Left).direction, -1.0);
+        input.set_axis(down, up, 0.0);
+        assert_eq!(input.paddle_input(Side::Left).direction, 0.0);
+    }
+
+    #[test]
+    fn disconnect_and_focus_loss_release_their_bindings() {
+        let mut input = InputState::new(InputMap::default());
+        let pad = |pad| Binding::Gamepad { pad, button : GamepadButton::DPadDown };
+        input.set_binding(pad(1), ElementState::Pressed);
+        input.set_binding(pad(2), ElementState::Pressed);
+        input.release_gamepad(1);
+        assert_eq!(input.paddle_input(Side::Left).direction, 0.0);
+        assert_eq!(input.paddle_input(Side::Right).direction, -1.0);
+
+        // gamepads keep reporting while the window is in the background
+        input.set_binding(key(VirtualKeyCode::W), ElementState::Pressed);
+        input.handle_event(&WindowEvent::Focused(false));
+        assert_eq!(input.paddle_input(Side::Left).direction, 0.0);
+        assert_eq!(input.paddle_input(Side::Right).direction, -1.0);
+    }
+
+    #[test]
+    fn every_action_name_parses() {
+        for action in Action::ALL {
+            assert_eq!(Action::from_name(action.name()), Some(action));
+        }
+    }
+
+    #[test]
+    fn pressed_and_released_last_one_tick() {
+        let mut input = InputState::new(InputMap::default());
+        let action = Action::PaddleUp(Side::Left);
+
+        assert!(input.set_binding(key(VirtualKeyCode::W), ElementState::Pressed));
+        assert!(input.is_pressed(action) && input.is_held(action));
+        input.end_tick();
+        assert!(!input.is_pressed(action) && input.is_held(action));
+
+        // key repeat is not a new press
+        input.set_binding(key(VirtualKeyCode::W), ElementState::Pressed);
+        assert!(!input.is_pressed(action));
+
+        input.set_binding(key(VirtualKeyCode::W), ElementState::Released);
+        assert!(input.is_released(action) && !input.is_held(action));
+        input.end_tick();
+        assert!(!input.is_released(action));
+    }
+
+    #[test]
+    fn press_and_release_within_one_tick_is_seen() {
+        let mut input = InputState::new(InputMap::default());
+        input.set_binding(key(VirtualKeyCode::Return), ElementState::Pressed);
+        input.set_binding(key(VirtualKeyCode::Return), ElementState::Released);
+        assert!(input.is_pressed(Action::Confirm));
+        assert!(input.is_released(Action::Confirm));
+        assert!(!input.is_held(Action::Confirm));
+    }
+
+    #[test]
+    fn unbound_keys_are_not_handled() {
+        let mut input = InputState::new(InputMap::default());
+        assert!(!input.set_binding(key(VirtualKeyCode::Z), ElementState::Pressed));
+        assert!(input.is_key_held(VirtualKeyCode::Z));
+    }
+
+    #[test]
+    fn paddle_axis() {
+        let mut input = InputState::new(InputMap::default());
+        input.set_binding(key(VirtualKeyCode::Up), ElementState::Pressed);
+        assert_eq!(input.paddle_input(Side::Right).direction, 1.0);
+        input.set_binding(key(VirtualKeyCode::Down), ElementState::Pressed);
+        assert_eq!(input.paddle_input(Side::Right).direction, 0.0);
+        input.set_binding(key(VirtualKeyCode::Up), ElementState::Released);
+        assert_eq!(input.pong_inputs().right.direction, -1.0);
+        assert_eq!(input.pong_inputs().left.direction, 0.0);
+    }
+}
@@ -0,0 +1,82 @@
+use gilrs::{Axis, Button, EventType, GamepadId, Gilrs};
+use winit::event::ElementState;
+
+use crate::errors::WvtError;
+use super::{Binding, GamepadButton, InputState};
+
+/// Polls connected gamepads through gilrs and feeds them into an `InputState`
+pub struct Gamepads {
+    gilrs : Gilrs,
+}
+
+impl std::fmt::Debug for Gamepads {
+    fn fmt(&self, f : &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Gamepads")
+            .field("connected", &self.gilrs.gamepads().count())
+            .finish()
+    }
+}
+
+// gilrs hands out ids from 0 in connection order, bindings count pads from 1
+fn pad_number(id : GamepadId) -> u8 {
+    u8::try_from(usize::from(id) + 1).unwrap_or(u8::MAX)
+}
+
+fn button(button : Button) -> Option<GamepadButton> {
+    Some(match button {
+        Button::South => GamepadButton::South,
+        Button::East => GamepadButton::East,
+        Button::North => GamepadButton::North,
+        Button::West => GamepadButton::West,
+        Button::LeftTrigger => GamepadButton::LeftTrigger,
+        Button::LeftTrigger2 => GamepadButton::LeftTrigger2,
+        Button::RightTrigger => GamepadButton::RightTrigger,
+        Button::RightTrigger2 => GamepadButton::RightTrigger2,
+        Button::Select => GamepadButton::Select,
+        Button::Start => GamepadButton::Start,
+        Button::LeftThumb => GamepadButton::LeftThumb,
+        Button::RightThumb => GamepadButton::RightThumb,
+        Button::DPadUp => GamepadButton::DPadUp,
+        Button::DPadDown => GamepadButton::DPadDown,
+        Button::DPadLeft => GamepadButton::DPadLeft,
+        Button::DPadRight => GamepadButton::DPadRight,
+        _ => return None,
+    })
+}
+
+impl Gamepads {
+    pub fn new() -> Result<Self, WvtError> {
+        // the error can carry a whole dummy `Gilrs`, only keep its message
+        let gilrs = Gilrs::new().map_err(|e| WvtError::Gamepad(e.to_string()))?;
+        Ok(Self { gilrs })
+    }
+
+    /// apply all gamepad events since the last call, once per simulation tick
+    pub fn poll(&mut self, input : &mut InputState) {
+        while let Some(event) = self.gilrs.next_event() {
+            let pad = pad_number(event.id);
+            let binding = |button| Binding::Gamepad { pad, button };
+            match event.event {
+                EventType::ButtonPressed(b, _) => if let Some(b) = button(b) {
+                    input.set_binding(binding(b), ElementState::Pressed);
+                },
+                EventType::ButtonReleased(b, _) => if let Some(b) = button(b) {
+                    input.set_binding(binding(b), ElementState::Released);
+                },
+                // stick Y points up
+                EventType::AxisChanged(Axis::LeftStickY, value, _) => {
+                    input.set_axis(binding(GamepadButton::LeftStickDown), binding(GamepadButton::LeftStickUp), value);
+                }
+                EventType::AxisChanged(Axis::RightStickY, value, _) => {
+                    input.set_axis(binding(GamepadButton::RightStickDown), binding(GamepadButton::RightStickUp), value);
+                }
+                EventType::Connected => log::info!("gamepad {pad} connected: {}", self.gilrs.gamepad(event.id).name()),
+                EventType::Disconnected => {
+                    log::info!("gamepad {pad} disconnected");
+                    input.release_gamepad(pad);
+                }
+                _ => {}
+            }
+        }
+    }
+}
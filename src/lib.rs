@@ -1,7 +1,10 @@
 
 
-use camera::Camera;
-use glam::{Mat4, Vec3};
+use std::time::Instant;
+
+use camera::{Camera, Projection};
+use camera::controller::{CameraController, OrbitController};
+use glam::{Mat4, Vec2, Vec3, Vec4};
 use winit::{
     event::*,
     event_loop::{ControlFlow, EventLoop},
@@ -18,25 +21,112 @@ pub mod renderers;
 pub mod texture2d;
 pub mod camera;
 pub mod model;
+pub mod game;
+pub mod timestep;
+pub mod input;
+pub mod gpu;
+pub mod render_target;
+pub mod scene;
+pub mod assets;
+pub mod mesh;
+pub mod frustum;
+pub mod picking;
+pub mod physics;
+pub mod screen;
+pub mod text;
+
+use game::{PongConfig, PongWorld};
+use game::ai::{AiController, Difficulty};
+use screen::{Anchor, Overlay, ScreenContext, ScreenStack};
+use screen::pong::TitleScreen;
+use input::{InputMap, InputState};
+use gpu::Gpu;
+use render_target::RenderTarget;
+use scene::{NodeId, Scene, Transform};
+use timestep::{FixedTimestep, FpsCounter};
+use renderers::material_buffer::Material;
+use renderers::light_buffer::{DirectionalLight, LightBuffer, Lights, PointLight};
+
+use geometries::InstanceElement;
+use mesh::procedural;
+use renderers::unlit_material::UnlitMaterial;
+use renderers::pipeline_cache::PipelineCache;
+use renderers::text_renderer::TextRenderer;
+use input::Action;
+use assets::{AssetServer, Handle};
+use model::ModelGeometry;
+use picking::PickHit;
+
+// optional key binding overrides, see `InputMap::apply_config`
+const INPUT_CONFIG_PATH : &str = "./input.cfg";
+
+// mode selected on the title screen at startup, None for two players on one keyboard
+const AI_OPPONENT : Option<Difficulty> = Some(Difficulty::Normal);
+
+// depth (z extent) of the paddles and walls in world units
+const PADDLE_DEPTH : f32 = 0.4;
+const WALL_THICKNESS : f32 = 0.1;
+// dashes of the center line, drawn as instances of one cube
+const NET_SEGMENTS : usize = 9;
+const NET_WIDTH : f32 = 0.05;
+// rounding of the paddle, wall and net edges in world units
+const EDGE_RADIUS : f32 = 0.03;
+const NET_EDGE_RADIUS : f32 = 0.02;
+
+
+// 3D view of the arena. `Projection::orthographic(3.0, 0.01, 20.0)` gives the classic flat
+// Pong look, `Projection::reversed_infinite_perspective` removes the far plane.
+const PROJECTION : Projection = Projection::Perspective {
+    fov_y : std::f32::consts::FRAC_PI_2,
+    near : 0.01,
+    far : 20.0,
+};
 
-use model::Model;
-
-
-use geometries::{CubeGeometry, QuadGeometry};
+// window height in font pixels at the normal text size, text is scaled by whole pixels
+const OVERLAY_HEIGHT : f32 = 240.0;
+// dark text reads best on the light background
+const OVERLAY_COLOR : Vec4 = Vec4::new(0.05, 0.05, 0.05, 1.0);
 
+// background color of the 3D scene
+const CLEAR_COLOR : wgpu::Color = wgpu::Color { r: 0.8, g: 0.8, b: 0.8, a: 1.0 };
 
 struct State {
-    surface: wgpu::Surface,
-    device: wgpu::Device,
-    queue: wgpu::Queue,
-    config: wgpu::SurfaceConfiguration,
+    gpu : Gpu,
+    target : RenderTarget,
     size: winit::dpi::PhysicalSize<u32>,
 
     camera : Camera,
+    // right drag rotates, middle drag pans, scroll zooms around the arena
+    camera_controller : OrbitController,
+    lights : LightBuffer,
     renderers : Vec<renderers::material_shader::MaterialShader>,
-    depth_texture : texture2d::Texture2d,
-
-    lv : f32,
+    net : UnlitMaterial,
+    scene : Scene,
+    // scene nodes of the world objects, in the order of `State::world_matrices`
+    object_nodes : Vec<NodeId>,
+    // cpu side geometry of the world objects, for picking
+    object_geometries : Vec<Handle<ModelGeometry>>,
+    // renderers skipped by frustum culling in the last frame
+    num_culled : usize,
+
+    world : PongWorld,
+    // world state before the last step, for interpolation while rendering
+    previous_world : PongWorld,
+    // plays the right paddle in single player mode
+    ai : Option<AiController>,
+    input : InputState,
+    // None when gamepads couldn't be opened, the keyboard still works then
+    #[cfg(feature = "gamepad")]
+    gamepads : Option<input::gamepad::Gamepads>,
+    // title, match, pause, ... the top screen drives the world
+    screens : ScreenStack,
+    // screen text, collected again every frame
+    overlay : Overlay,
+    // score, menus and debug text, drawn after the 3D pass
+    hud : TextRenderer,
+    fps : FpsCounter,
+    // frame rate and culling stats in the top left corner, toggled with F3
+    show_debug : bool,
     //render_pipeline : wgpu::RenderPipeline,
     window: Window,
 }
@@ -44,69 +134,18 @@ struct State {
 
 impl State {
 
-    fn camera_mat(width: u32, height: u32) -> Mat4 {
-        // let mat = Mat4::orthographic_lh(
-        //     -1.0,1.0,
-        //     -1.0,1.0,
-        //     0.0,1.0 );
-
-        let fov = 90.0_f32.to_radians();
-        let z_near = 0.01_f32;
-        let z_far = 5_f32;
-        let aspect_ratio  = width as f32 / height as f32;
-
-        Mat4::perspective_lh(
-            fov, aspect_ratio,
-            z_near, z_far)
-    }
-
     async fn new(window: Window) -> Self {
         let size = window.inner_size();
 
-        //let mm = Model::new("./src/assets/cube/cube.obj");
-        //let mm = Model::new("./src/assets/models/teacup.obj");
-        let mm = Model::new("./src/assets/models/suzanne.obj");
-        //let mm = Model::new("./src/assets/models/spoon.obj");
-
-        dbg!(mm.positions.len());
-        dbg!(mm.colors.len());
-        dbg!(mm.tex_coords.len());
-        dbg!(mm.normals.len());
-        dbg!(mm.indices.len());
-
-        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
-            backends: wgpu::Backends::all(),
-            ..Default::default()
-        });
-        
+        let instance = Gpu::create_instance();
         let surface = unsafe { instance.create_surface(&window) }.unwrap();
+        let gpu = Gpu::new(instance, Some(&surface), false).await.unwrap();
+        let Gpu { device, queue, .. } = &gpu;
 
-        let adapter = instance.request_adapter(
-            &wgpu::RequestAdapterOptions {
-                power_preference: wgpu::PowerPreference::default(),
-                compatible_surface: Some(&surface),
-                force_fallback_adapter: false,
-            },
-        ).await.unwrap();
-  
-         let (device, queue) = adapter.request_device(
-            &wgpu::DeviceDescriptor {
-                features: wgpu::Features::empty(),
-                limits: if cfg!(target_arch = "wasm32") {
-                    wgpu::Limits::downlevel_webgl2_defaults()
-                } else {
-                    wgpu::Limits::default()
-                },
-                label: None,
-            },
-            None, // Trace path
-        ).await.unwrap();
-
-        let surface_caps = surface.get_capabilities(&adapter);
+        let surface_caps = surface.get_capabilities(&gpu.adapter);
         let surface_format = surface_caps.formats.iter()
             .copied()
-            .filter(|f| f.is_srgb())
-            .next()
+            .find(|f| f.is_srgb())
             .unwrap_or(surface_caps.formats[0]);
         let config = wgpu::SurfaceConfiguration {
             usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
@@ -117,48 +156,171 @@ impl State {
             alpha_mode: surface_caps.alpha_modes[0],
             view_formats: vec![],
         };
-        surface.configure(&device, &config);
+        surface.configure(device, &config);
+
+        // color + DEPTH buffer
+        let mut target = RenderTarget::from_surface(device, surface, config);
+        target.set_reversed_z(PROJECTION.is_reversed_z());
 
-        // DEPTH buffer
-        let depth_texture = texture2d::Texture2d::create_depth_texture(&device, &config, "depth_texture");
 
+        let world = PongWorld::new(PongConfig::default());
+
+        // renderers with the same shader and layout share one pipeline
+        let mut pipelines = PipelineCache::new();
+        pipelines.set_reversed_z(PROJECTION.is_reversed_z());
 
-        //let geo = QuadGeometry::new();
-        let geo = CubeGeometry::new();
-       
         let camera = Camera::new(
-            &device,
-            Self::camera_mat(size.width, size.height),
-            Vec3::new( 0.0, 0.0,0.0 ),
-            Vec3::new(0.0,0.0,3.0 ),
+            device,
+            &mut pipelines,
+            PROJECTION,
+            size.width,
+            size.height,
+            Vec3::new( 0.0, 0.0,-3.5 ),
+            Vec3::new(0.0,0.0,0.0 ),
             Vec3::Y,
             "Main camera" );
+        let camera_controller = OrbitController::from_camera(&camera).with_distance_range(1.0, 10.0);
+
+        let mut scene_lights = Lights::new(DirectionalLight::new(
+            Vec3::new(-0.3, -0.5, 1.0),
+            Vec3::ONE,
+            0.8));
+        // glow around the ball, follows it in `render`
+        scene_lights.add_point_light(PointLight::new(Vec3::ZERO, Vec3::new(1.0, 0.6, 0.2), 2.0, 3.0));
+        let lights = LightBuffer::new(device, scene_lights, "Scene lights");
+
+        // all world objects are grouped under the arena node
+        let mut scene = Scene::new();
+        let arena = scene.add_node("arena", Transform::IDENTITY, None);
+        let object_nodes : Vec<NodeId> = ["left paddle", "right paddle", "ball", "top wall", "bottom wall"]
+            .iter()
+            .map( |name| scene.add_node(name, Transform::IDENTITY, Some(arena)))
+            .collect();
+
+        // one renderer per world object, in the order of `State::world_matrices`
+        let paddle_material = Material::new(
+            Vec3::new(0.1,0.1,0.1),
+            Vec3::new(0.9,0.9,0.9),
+            Vec3::new(1.0,1.0,1.0));
+        let ball_material = Material::new(
+            Vec3::new(0.1,0.1,0.1),
+            Vec3::new(1.0,0.5,0.1),
+            Vec3::new(1.0,1.0,1.0));
+        let wall_material = Material::new(
+            Vec3::new(0.1,0.1,0.1),
+            Vec3::new(0.2,0.3,0.6),
+            Vec3::new(1.0,1.0,1.0));
+        let materials = [paddle_material, paddle_material, ball_material, wall_material, wall_material];
+
+        let mut assets = AssetServer::new();
+        let white = assets.white_texture(device, queue);
+        let paddle_size = (2.0 * world.left.half_size).extend(PADDLE_DEPTH);
+        let wall_size = Vec3::new(2.0 * world.arena.half_size.x + 2.0 * WALL_THICKNESS, WALL_THICKNESS, PADDLE_DEPTH);
+        let paddle = Handle::new(Self::unit_rounded_box(paddle_size, EDGE_RADIUS));
+        let wall = Handle::new(Self::unit_rounded_box(wall_size, EDGE_RADIUS));
+        let ball = Handle::new(procedural::uv_sphere(0.5, 32, 16));
+        let shapes = [&paddle, &paddle, &ball, &wall, &wall];
+        let object_geometries = shapes.iter().map(|geo| (*geo).clone()).collect();
+        let renderers = materials.iter().zip(shapes).zip(&object_nodes).map( |((material, geo), node)| {
+            let mut renderer = renderers::material_shader::MaterialShader::new(
+                device, 
+                &mut pipelines,
+                target.format(), 
+                &geo.positions,
+                &geo.normals,
+                &geo.colors,
+                &geo.tex_coords,
+                &geo.indices,
+                white.clone(),
+                &lights,
+            );
+            renderer.set_material(queue, *material);
+            renderer.set_node(Some(*node));
+            renderer
+        }).collect();
 
-        let mut renderers = Vec::new();
-        renderers.push( renderers::material_shader::MaterialShader::new(
-            &device, 
-            &queue,
-            config.format, 
-            &mm.positions,
-            &mm.normals,
-            &mm.colors,
-            &[],//&mm.tex_coords,
-            &mm.indices,
-            &camera,
-        ));
-
-        Self {
-            surface,
+        let net_segment = Self::unit_rounded_box(Self::net_segment_size(&world), NET_EDGE_RADIUS);
+        let mut net = UnlitMaterial::new(
             device,
-            queue,
-            config,
+            &mut pipelines,
+            target.format(),
+            &net_segment.positions,
+            &net_segment.colors,
+            &net_segment.tex_coords,
+            &net_segment.indices,
+            white,
+            &Self::net_instances(&world));
+        net.set_node(Some(arena));
+        let hud = TextRenderer::new(device, queue, &mut pipelines, target.format());
+        log::debug!("{} render pipelines", pipelines.num_pipelines());
+
+        let mut state = Self {
+            gpu,
+            target,
             size,
             camera,
+            camera_controller,
+            lights,
             renderers,
-            depth_texture,
-            lv : 0.0,
+            net,
+            scene,
+            object_nodes,
+            object_geometries,
+            num_culled : 0,
+            previous_world : world.clone(),
+            world,
+            ai : None,
+            input : InputState::new(Self::load_input_map()),
+            #[cfg(feature = "gamepad")]
+            gamepads : input::gamepad::Gamepads::new()
+                .map_err(|e| log::warn!("No gamepad support: {e}"))
+                .ok(),
+            screens : ScreenStack::new(),
+            overlay : Overlay::default(),
+            hud,
+            fps : FpsCounter::default(),
+            show_debug : false,
             window,
+        };
+
+        let mut ctx = ScreenContext { world : &mut state.world, ai : &mut state.ai, input : &state.input };
+        state.screens.push(&mut ctx, Box::new(TitleScreen::new(AI_OPPONENT)));
+        state
+    }
+
+    /// unit cube with edges that are rounded by `radius` once it is scaled to `size`,
+    /// so the world objects can keep their scaling model matrices
+    fn unit_rounded_box(size : Vec3, radius : f32) -> ModelGeometry {
+        let mut geometry = procedural::rounded_box(size, radius, 4);
+        mesh::transform(&mut geometry, Mat4::from_scale(size.recip()));
+        geometry
+    }
+
+    fn net_segment_size(world : &PongWorld) -> Vec3 {
+        let spacing = 2.0 * world.arena.half_size.y / NET_SEGMENTS as f32;
+        Vec3::new(NET_WIDTH, 0.5 * spacing, NET_WIDTH)
+    }
+
+    /// dashed center line, just behind the play plane
+    fn net_instances(world : &PongWorld) -> Vec<InstanceElement> {
+        let spacing = 2.0 * world.arena.half_size.y / NET_SEGMENTS as f32;
+        let scale = Self::net_segment_size(world);
+        (0..NET_SEGMENTS).map(|i| {
+            let y = -world.arena.half_size.y + (i as f32 + 0.5) * spacing;
+            let matrix = Mat4::from_translation(Vec3::new(0.0, y, PADDLE_DEPTH)) * Mat4::from_scale(scale);
+            // the unlit pipeline is opaque, dimmed instead of see through
+            InstanceElement::new(matrix, [0.8, 0.8, 0.8, 1.0])
+        }).collect()
+    }
+
+    fn load_input_map() -> InputMap {
+        if !std::path::Path::new(INPUT_CONFIG_PATH).exists() {
+            return InputMap::default();
         }
+        InputMap::load(INPUT_CONFIG_PATH).unwrap_or_else(|e| {
+            log::warn!("Ignoring {INPUT_CONFIG_PATH}: {e}");
+            InputMap::default()
+        })
     }
 
     pub fn window(&self) -> &Window {
@@ -169,84 +331,172 @@ impl State {
     pub fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
         if new_size.width > 0 && new_size.height > 0 {
             self.size = new_size;
-            self.config.width = new_size.width;
-            self.config.height = new_size.height;
-
-            self.camera.update_projection(
-                &self.queue, 
-                Self::camera_mat(self.size.width, self.size.height));
-
-            self.surface.configure(&self.device, &self.config);
-            self.depth_texture = texture2d::Texture2d::create_depth_texture(
-                &self.device, 
-                &self.config,
-                "depth_texture");
+
+            self.camera.resize(&self.gpu.queue, new_size.width, new_size.height);
+
+            self.target.resize(&self.gpu.device, new_size.width, new_size.height);
+        }
+    }
+
+    fn input(&mut self, event: &WindowEvent) -> bool {
+        if let WindowEvent::MouseInput { state: ElementState::Pressed, button: MouseButton::Left, .. } = event {
+            let hit = self.input.cursor_position().and_then(|cursor| self.pick(cursor.x, cursor.y));
+            if let Some(hit) = hit {
+                log::debug!("clicked {} at {}", self.scene.node(hit.object).name, hit.point);
+            }
         }
+        let handled = self.input.handle_event(event);
+        let mut ctx = ScreenContext { world : &mut self.world, ai : &mut self.ai, input : &self.input };
+        self.screens.input(&mut ctx, event);
+        handled
     }
 
-    fn input(&mut self, _event: &WindowEvent) -> bool {
-        false
+    /// true once the screens asked to quit the game
+    fn should_quit(&self) -> bool {
+        self.screens.should_quit()
     }
 
-    fn update(&mut self) {
+    /// nearest world object under a point in physical window pixels, as drawn in the last frame
+    pub fn pick(&self, x : f32, y : f32) -> Option<PickHit<NodeId>> {
+        let ray = self.camera.screen_ray(x, y);
+        let objects = self.object_nodes.iter().zip(&self.object_geometries)
+            .map(|(node, geometry)| (*node, &**geometry, self.scene.world_matrix(*node)));
+        picking::pick(&ray, objects)
+    }
 
-        // self.lv += 0.01;
-        // self.camera.center.z -= self.lv;
+    // the ball between the previous and current step, no blending across a reset to the center
+    fn interpolated_ball(&self, alpha : f32) -> game::Ball {
+        if self.world.ball_teleported() {
+            self.world.ball
+        } else {
+            self.world.ball.lerp(&self.previous_world.ball, alpha)
+        }
+    }
 
-        // self.camera.update_projection(
-        //     &self.queue, 
-        //     Self::camera_mat(self.size.width, self.size.height));
+    /// model matrices of all world objects: left paddle, right paddle, ball, top and bottom wall,
+    /// interpolated between the previous and current step by `alpha`
+    fn world_matrices(&self, alpha : f32) -> [Mat4; 5] {
+        let (world, previous) = (&self.world, &self.previous_world);
+        let [top_wall, bottom_wall] = world.arena.wall_matrices(WALL_THICKNESS, PADDLE_DEPTH);
+        [
+            world.left.lerp(&previous.left, alpha).model_matrix(PADDLE_DEPTH),
+            world.right.lerp(&previous.right, alpha).model_matrix(PADDLE_DEPTH),
+            self.interpolated_ball(alpha).model_matrix(),
+            top_wall,
+            bottom_wall,
+        ]
+    }
 
+    /// advance the simulation by one fixed step of `dt` seconds
+    fn update(&mut self, dt : f32) {
+        #[cfg(feature = "gamepad")]
+        if let Some(gamepads) = &mut self.gamepads {
+            gamepads.poll(&mut self.input);
+        }
+        self.previous_world.clone_from(&self.world);
+        let mut ctx = ScreenContext { world : &mut self.world, ai : &mut self.ai, input : &self.input };
+        self.screens.update(&mut ctx, dt);
+        self.camera_controller.update(&self.input, dt);
+        if self.input.is_pressed(Action::ToggleDebug) {
+            self.show_debug = !self.show_debug;
+        }
+        self.input.end_tick();
+    }
+
+    /// lay out the text of the screens and the debug text for the overlay pass
+    fn queue_overlay(&mut self) {
+        self.overlay.clear();
+        self.screens.render(&self.world, &mut self.overlay);
+
+        let (width, height) = self.target.size();
+        let size = Vec2::new(width as f32, height as f32);
+        let unit = (size.y / OVERLAY_HEIGHT).floor().max(1.0);
+        let margin = 4.0 * unit;
+        let line_height = self.hud.font().line_height();
+
+        for anchor in [Anchor::Top, Anchor::Center, Anchor::Bottom] {
+            let texts : Vec<_> = self.overlay.texts.iter().filter(|t| t.anchor == anchor).collect();
+            let block_height : f32 = texts.iter().map(|t| line_height * unit * t.scale).sum();
+            let mut y = match anchor {
+                Anchor::Top => margin,
+                Anchor::Center => 0.5 * (size.y - block_height),
+                Anchor::Bottom => size.y - margin - block_height,
+            };
+            for text in texts {
+                let scale = unit * text.scale;
+                let x = 0.5 * (size.x - self.hud.measure(&text.text, scale).x);
+                self.hud.queue(&text.text, Vec2::new(x, y).round(), scale, OVERLAY_COLOR);
+                y += line_height * scale;
+            }
+        }
+
+        if self.show_debug {
+            let debug = format!("{:.0} fps\n{} of {} objects culled\n{} screen",
+                self.fps.fps(),
+                self.num_culled,
+                self.renderers.len(),
+                self.screens.top().map_or("no", |screen| screen.name()));
+            self.hud.queue(&debug, Vec2::splat(margin), unit, OVERLAY_COLOR);
+        }
+    }
+
+    fn render(&mut self, alpha : f32) -> Result<(), wgpu::SurfaceError> {
+        let matrices = self.world_matrices(alpha);
+        self.object_nodes.iter().zip(matrices).for_each( |(node, model_matrix)| 
+            self.scene.set_local(*node, Transform::from_matrix(model_matrix))
+        );
+        self.scene.update_world_matrices();
         self.renderers.iter_mut().for_each( |r| 
-            r.update(&self.queue)
+            r.update_transform(&self.gpu.queue, &self.scene)
         );
-    }
+        self.net.update_transform(&self.gpu.queue, &self.scene);
+        self.camera_controller.apply(&self.gpu.queue, &mut self.camera);
 
-    fn render(&mut self) -> Result<(), wgpu::SurfaceError> {
-        let output = self.surface.get_current_texture()?;
-        let view = output.texture.create_view(&wgpu::TextureViewDescriptor::default());
-        
-        
-        let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+        self.fps.frame(Instant::now());
+
+        let ball = self.interpolated_ball(alpha);
+        let lights = self.lights.lights_mut();
+        lights.point_lights[0].position = ball.position.extend(-2.0 * ball.radius);
+        self.lights.update(&self.gpu.queue);
+
+        let frame = self.target.acquire()?;
+        // after acquiring, text queued for a skipped frame would be drawn twice on the next one
+        self.queue_overlay();
+
+        let mut encoder = self.gpu.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
             label: Some("Render Encoder"),
         });
 
         {
             // _render_pass must have limited scope in order to release the encoder
-            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                label: Some("Render Pass"),
-                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &view, // to where we rendering to, in this case a view on the window output texture
-                    resolve_target: None,
-                    ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(wgpu::Color {
-                            r: 0.8,
-                            g: 0.8,
-                            b: 0.8,
-                            a: 1.0,
-                        }),
-                        store: wgpu::StoreOp::Store,
-                    },
-                })],
-                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
-                    view: &self.depth_texture.view,
-                    depth_ops: Some(wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(1.0),
-                        store: wgpu::StoreOp::Store,
-                    }),
-                    stencil_ops: None,
-                }),                occlusion_query_set: None,
-                timestamp_writes: None,
-            });
-
-            self.renderers.iter().for_each( |r| 
-                r.draw(&mut render_pass, &self.camera)
-            );
+            let mut render_pass = self.target.begin_render_pass(&mut encoder, &frame, CLEAR_COLOR);
+
+            let frustum = self.camera.frustum();
+            let mut num_culled = 0;
+            for r in &self.renderers {
+                if r.is_visible(&frustum) {
+                    r.draw(&mut render_pass, &self.camera);
+                } else {
+                    num_culled += 1;
+                }
+            }
+            if num_culled != self.num_culled {
+                log::debug!("{num_culled} of {} objects culled", self.renderers.len());
+                self.num_culled = num_culled;
+            }
+            self.net.draw(&mut render_pass, &self.camera);
+        }
+
+        let (width, height) = self.target.size();
+        self.hud.prepare(&self.gpu.device, &self.gpu.queue, width, height);
+        {
+            let mut overlay_pass = self.target.begin_overlay_pass(&mut encoder, &frame);
+            self.hud.draw(&mut overlay_pass);
         }
 
         // submit will accept anything that implements IntoIter
-        self.queue.submit(std::iter::once(encoder.finish()));
-        output.present();
+        self.gpu.queue.submit(std::iter::once(encoder.finish()));
+        frame.present();
 
         Ok(())                
     }
@@ -254,6 +504,11 @@ impl State {
 
 
 pub async fn run() {
+    run_with_timestep(FixedTimestep::default()).await
+}
+
+/// run the game with a custom simulation tick rate / catch-up limit
+pub async fn run_with_timestep(mut timestep : FixedTimestep) {
 
     let event_loop = EventLoop::new();
     let window = WindowBuilder::new().build(&event_loop).unwrap();
@@ -263,11 +518,17 @@ pub async fn run() {
     event_loop.run(move |event, _, control_flow| match event {
 
         Event::RedrawRequested(window_id) if window_id == state.window().id() => {
-            state.update();
-            match state.render() {
+            for _ in 0..timestep.tick(Instant::now()) {
+                state.update(timestep.dt());
+            }
+            if state.should_quit() {
+                *control_flow = ControlFlow::Exit;
+                return;
+            }
+            match state.render(timestep.alpha()) {
                 Ok(_) => {}
                 // Reconfigure the surface if lost
-                Err(wgpu::SurfaceError::Lost) => state.resize(state.size),
+                Err(wgpu::SurfaceError::Lost) => state.target.reconfigure(&state.gpu.device),
                 // The system is out of memory, we should probably quit
                 Err(wgpu::SurfaceError::OutOfMemory) => *control_flow = ControlFlow::Exit,
                 // All other errors (Outdated, Timeout) should be resolved by the next frame
@@ -283,7 +544,7 @@ pub async fn run() {
         Event::WindowEvent {
             ref event,
             window_id,
-        } if window_id == state.window.id()  => if !state.input(event) {
+        } if window_id == state.window.id() && !state.input(event) => {
             
             match event {
                 WindowEvent::Resized(physical_size) => {
@@ -294,15 +555,8 @@ pub async fn run() {
                     state.resize(**new_inner_size);
                 }
 
-                WindowEvent::CloseRequested | WindowEvent::KeyboardInput {
-                    input:
-                        KeyboardInput {
-                            state: ElementState::Pressed,
-                            virtual_keycode: Some(VirtualKeyCode::Escape),
-                            ..
-                        },
-                    ..
-                } => *control_flow = ControlFlow::Exit,
+                // escape is handled by the screens, it pauses the match
+                WindowEvent::CloseRequested => *control_flow = ControlFlow::Exit,
 
                 _ => {}
             }
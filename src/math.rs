@@ -2,6 +2,7 @@
 use glam::{Mat2, Mat3, Mat4, Vec2, Vec3, Vec4};
 
 use crate::buffers::uniform::UniformBufferData;
+use crate::mesh::{Aabb, BoundingSphere};
 
 // implement uniform buffer data trait, to ease use
 // with uniform buffers
@@ -14,6 +15,82 @@ impl UniformBufferData for Mat3 {}
 impl UniformBufferData for Mat4 {}
 
 
+/// Half line from `origin` along `direction`. Hits are reported as the ray parameter `t`,
+/// so they stay valid for the untransformed ray when testing in object space.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Ray {
+    pub origin : Vec3,
+    pub direction : Vec3,
+}
+
+impl Ray {
+    pub fn new(origin : Vec3, direction : Vec3) -> Self {
+        Self { origin, direction }
+    }
+
+    pub fn at(&self, t : f32) -> Vec3 {
+        self.origin + t * self.direction
+    }
+
+    /// the direction is not renormalized, `t` means the same point before and after
+    pub fn transformed(&self, matrix : &Mat4) -> Ray {
+        Self::new(matrix.transform_point3(self.origin), matrix.transform_vector3(self.direction))
+    }
+
+    /// slab test, `t` of the entry point or 0 when starting inside
+    pub fn intersect_aabb(&self, aabb : &Aabb) -> Option<f32> {
+        // a parallel axis only needs the origin inside its slab, on a face 0 * inf would be NaN
+        let parallel = self.direction.cmpeq(Vec3::ZERO);
+        if (parallel & (self.origin.cmplt(aabb.min) | self.origin.cmpgt(aabb.max))).any() {
+            return None;
+        }
+        let inverse = self.direction.recip();
+        let t0 = (aabb.min - self.origin) * inverse;
+        let t1 = (aabb.max - self.origin) * inverse;
+        let near = Vec3::select(parallel, Vec3::NEG_INFINITY, t0.min(t1)).max_element().max(0.0);
+        let far = Vec3::select(parallel, Vec3::INFINITY, t0.max(t1)).min_element();
+        (near <= far).then_some(near)
+    }
+
+    pub fn intersect_sphere(&self, sphere : &BoundingSphere) -> Option<f32> {
+        let offset = self.origin - sphere.center;
+        let a = self.direction.length_squared();
+        let half_b = offset.dot(self.direction);
+        let c = offset.length_squared() - sphere.radius * sphere.radius;
+        let discriminant = half_b * half_b - a * c;
+        if a == 0.0 || discriminant < 0.0 {
+            return None;
+        }
+        let root = discriminant.sqrt();
+        [(-half_b - root) / a, (-half_b + root) / a].into_iter().find(|t| *t >= 0.0)
+    }
+
+    /// Möller-Trumbore, hits both sides of the triangle
+    pub fn intersect_triangle(&self, [a, b, c] : [Vec3; 3]) -> Option<f32> {
+        let edge1 = b - a;
+        let edge2 = c - a;
+        let p = self.direction.cross(edge2);
+        let determinant = edge1.dot(p);
+        if determinant.abs() < f32::EPSILON {
+            return None;
+        }
+        let inverse = 1.0 / determinant;
+        let offset = self.origin - a;
+        let u = offset.dot(p) * inverse;
+        if !(0.0..=1.0).contains(&u) {
+            return None;
+        }
+        let q = offset.cross(edge1);
+        let v = self.direction.dot(q) * inverse;
+        if v < 0.0 || u + v > 1.0 {
+            return None;
+        }
+        let t = edge2.dot(q) * inverse;
+        (t >= 0.0).then_some(t)
+    }
+}
+
+
 // #[rustfmt::skip]
 // pub const OPENGL_TO_WGPU_MATRIX: = ::new(
 //     1.0, 0.0, 0.0, 0.0,
@@ -21,3 +98,74 @@ impl UniformBufferData for Mat4 {}
 //     0.0, 0.0, 0.5, 0.5,
 //     0.0, 0.0, 0.0, 1.0,
 // );
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unit_box() -> Aabb {
+        Aabb::new(Vec3::splat(-1.0), Vec3::splat(1.0))
+    }
+
+    const TRIANGLE : [Vec3; 3] = [Vec3::new(-1.0, -1.0, 0.0), Vec3::new(1.0, -1.0, 0.0), Vec3::new(0.0, 1.0, 0.0)];
+
+    #[test]
+    fn ray_aabb() {
+        // hit and miss
+        assert_eq!(Ray::new(Vec3::new(-5.0, 0.0, 0.0), Vec3::X).intersect_aabb(&unit_box()), Some(4.0));
+        assert_eq!(Ray::new(Vec3::new(-5.0, 0.0, 0.0), -Vec3::X).intersect_aabb(&unit_box()), None);
+        assert_eq!(Ray::new(Vec3::new(-5.0, 0.0, 0.0), Vec3::new(1.0, 1.0, 0.0)).intersect_aabb(&unit_box()), None);
+        // diagonal through an edge, t is in units of the unnormalized direction
+        assert_eq!(Ray::new(Vec3::new(-3.0, -3.0, 0.0), Vec3::new(2.0, 2.0, 0.0)).intersect_aabb(&unit_box()), Some(1.0));
+        // starting inside
+        assert_eq!(Ray::new(Vec3::new(0.5, 0.0, 0.0), Vec3::Y).intersect_aabb(&unit_box()), Some(0.0));
+        // parallel to a slab, inside, outside and exactly on it
+        assert_eq!(Ray::new(Vec3::new(-5.0, 0.5, 0.5), Vec3::X).intersect_aabb(&unit_box()), Some(4.0));
+        assert_eq!(Ray::new(Vec3::new(-5.0, 2.0, 0.0), Vec3::X).intersect_aabb(&unit_box()), None);
+        assert_eq!(Ray::new(Vec3::new(-5.0, 1.0, 0.0), Vec3::X).intersect_aabb(&unit_box()), Some(4.0));
+    }
+
+    #[test]
+    fn ray_sphere() {
+        let sphere = BoundingSphere::new(Vec3::new(0.0, 0.0, 5.0), 1.0);
+        assert_eq!(Ray::new(Vec3::ZERO, Vec3::Z).intersect_sphere(&sphere), Some(4.0));
+        assert_eq!(Ray::new(Vec3::ZERO, Vec3::Z * 2.0).intersect_sphere(&sphere), Some(2.0));
+        assert_eq!(Ray::new(Vec3::ZERO, -Vec3::Z).intersect_sphere(&sphere), None);
+        assert_eq!(Ray::new(Vec3::new(0.0, 1.5, 0.0), Vec3::Z).intersect_sphere(&sphere), None);
+        // grazing
+        assert_eq!(Ray::new(Vec3::new(0.0, 1.0, 0.0), Vec3::Z).intersect_sphere(&sphere), Some(5.0));
+        // starting inside, the exit point
+        assert_eq!(Ray::new(Vec3::new(0.0, 0.0, 5.0), Vec3::Z).intersect_sphere(&sphere), Some(1.0));
+        // parallel to the surface doesn't apply to spheres, but a degenerate direction never hits
+        assert_eq!(Ray::new(Vec3::ZERO, Vec3::ZERO).intersect_sphere(&sphere), None);
+    }
+
+    #[test]
+    fn ray_triangle() {
+        let ray = |origin : Vec3, direction : Vec3| Ray::new(origin, direction).intersect_triangle(TRIANGLE);
+        assert_eq!(ray(Vec3::new(0.0, 0.0, -2.0), Vec3::Z), Some(2.0));
+        // both sides
+        assert_eq!(ray(Vec3::new(0.0, 0.0, 3.0), -Vec3::Z), Some(3.0));
+        // outside an edge, and behind the origin
+        assert_eq!(ray(Vec3::new(0.9, 0.5, -2.0), Vec3::Z), None);
+        assert_eq!(ray(Vec3::new(0.0, 0.0, -2.0), -Vec3::Z), None);
+        // on a corner
+        assert_eq!(ray(Vec3::new(-1.0, -1.0, -1.0), Vec3::Z), Some(1.0));
+        // starting on the triangle
+        assert_eq!(ray(Vec3::ZERO, Vec3::Z), Some(0.0));
+        // parallel to the triangle plane, in it and next to it
+        assert_eq!(ray(Vec3::new(-5.0, 0.0, 0.0), Vec3::X), None);
+        assert_eq!(ray(Vec3::new(-5.0, 0.0, 1.0), Vec3::X), None);
+    }
+
+    #[test]
+    fn transformed_rays_keep_t() {
+        let ray = Ray::new(Vec3::new(0.0, 0.0, -10.0), Vec3::Z);
+        // into the object space of a box scaled by 2 and moved along +Z
+        let world = Mat4::from_scale_rotation_translation(Vec3::splat(2.0), glam::Quat::IDENTITY, Vec3::new(0.0, 0.0, 4.0));
+        let local = ray.transformed(&world.inverse());
+        let t = local.intersect_aabb(&unit_box()).unwrap();
+        assert!((t - 12.0).abs() < 1e-5);
+        assert!(ray.at(t).abs_diff_eq(Vec3::new(0.0, 0.0, 2.0), 1e-5));
+    }
+}
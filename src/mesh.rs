@@ -0,0 +1,366 @@
+pub mod procedural;
+
+use std::collections::HashMap;
+
+use glam::{Mat4, Vec3};
+
+use crate::geometries::{NormalElement, PositionElement};
+use crate::model::ModelGeometry;
+
+/// Axis aligned bounding box
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Aabb {
+    pub min : Vec3,
+    pub max : Vec3,
+}
+
+impl Aabb {
+    pub fn new(min : Vec3, max : Vec3) -> Self {
+        Self { min, max }
+    }
+
+    /// smallest box around all points, `None` without points
+    pub fn from_points(points : impl IntoIterator<Item = Vec3>) -> Option<Self> {
+        let mut points = points.into_iter();
+        let first = points.next()?;
+        Some(points.fold(Self::new(first, first), |aabb, p| Self::new(aabb.min.min(p), aabb.max.max(p))))
+    }
+
+    pub fn center(&self) -> Vec3 {
+        0.5 * (self.min + self.max)
+    }
+
+    pub fn size(&self) -> Vec3 {
+        self.max - self.min
+    }
+
+    pub fn half_extents(&self) -> Vec3 {
+        0.5 * self.size()
+    }
+
+    pub fn contains(&self, point : Vec3) -> bool {
+        point.cmpge(self.min).all() && point.cmple(self.max).all()
+    }
+
+    pub fn union(&self, other : &Aabb) -> Aabb {
+        Self::new(self.min.min(other.min), self.max.max(other.max))
+    }
+
+    pub fn corners(&self) -> [Vec3; 8] {
+        let (min, max) = (self.min, self.max);
+        [
+            Vec3::new(min.x, min.y, min.z),
+            Vec3::new(max.x, min.y, min.z),
+            Vec3::new(min.x, max.y, min.z),
+            Vec3::new(max.x, max.y, min.z),
+            Vec3::new(min.x, min.y, max.z),
+            Vec3::new(max.x, min.y, max.z),
+            Vec3::new(min.x, max.y, max.z),
+            Vec3::new(max.x, max.y, max.z),
+        ]
+    }
+
+    /// box around the transformed corners, grows under rotation
+    pub fn transformed(&self, matrix : &Mat4) -> Aabb {
+        Self::from_points(self.corners().map(|c| matrix.transform_point3(c))).expect("8 corners")
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct BoundingSphere {
+    pub center : Vec3,
+    pub radius : f32,
+}
+
+impl BoundingSphere {
+    pub fn new(center : Vec3, radius : f32) -> Self {
+        Self { center, radius }
+    }
+
+    /// Ritter's approximation, at most a few percent larger than the minimal sphere
+    pub fn from_points(points : &[Vec3]) -> Option<Self> {
+        let first = *points.first()?;
+        let farthest = |from : Vec3| *points.iter()
+            .max_by(|a, b| from.distance_squared(**a).total_cmp(&from.distance_squared(**b)))
+            .expect("not empty");
+
+        let a = farthest(first);
+        let b = farthest(a);
+        let mut sphere = Self::new(0.5 * (a + b), 0.5 * a.distance(b));
+
+        // grow the sphere to include points that are still outside
+        for p in points {
+            let distance = sphere.center.distance(*p);
+            if distance > sphere.radius {
+                let radius = 0.5 * (sphere.radius + distance);
+                sphere.center += (distance - radius) / distance * (*p - sphere.center);
+                sphere.radius = radius;
+            }
+        }
+        Some(sphere)
+    }
+
+    pub fn contains(&self, point : Vec3) -> bool {
+        self.center.distance_squared(point) <= self.radius * self.radius
+    }
+
+    /// sphere around the transformed sphere, scaled by the largest axis scale
+    pub fn transformed(&self, matrix : &Mat4) -> BoundingSphere {
+        let scale = matrix.x_axis.truncate().length()
+            .max(matrix.y_axis.truncate().length())
+            .max(matrix.z_axis.truncate().length());
+        Self::new(matrix.transform_point3(self.center), self.radius * scale)
+    }
+}
+
+fn positions(geometry : &ModelGeometry) -> impl Iterator<Item = Vec3> + '_ {
+    geometry.positions.iter().map(|p| Vec3::from(p.position))
+}
+
+pub fn aabb(geometry : &ModelGeometry) -> Option<Aabb> {
+    Aabb::from_points(positions(geometry))
+}
+
+pub fn bounding_sphere(geometry : &ModelGeometry) -> Option<BoundingSphere> {
+    BoundingSphere::from_points(&positions(geometry).collect::<Vec<_>>())
+}
+
+// triangles as index triples, a mesh without index buffer is a plain triangle list
+pub(crate) fn triangles(geometry : &ModelGeometry) -> Vec<[usize; 3]> {
+    if geometry.indices.is_empty() {
+        (0..geometry.positions.len() / 3).map(|t| [3 * t, 3 * t + 1, 3 * t + 2]).collect()
+    } else {
+        geometry.indices.chunks_exact(3).map(|t| [t[0] as usize, t[1] as usize, t[2] as usize]).collect()
+    }
+}
+
+// `values` reordered by `indices`, attributes a mesh doesn't have stay empty
+fn gather<T : Copy>(values : &[T], indices : &[usize]) -> Vec<T> {
+    if values.is_empty() {
+        return Vec::new();
+    }
+    indices.iter().map(|i| values[*i]).collect()
+}
+
+// tangents depend on normals and the vertex layout, keep them in sync
+fn refresh_tangents(geometry : &mut ModelGeometry) {
+    if !geometry.tangents.is_empty() {
+        geometry.generate_tangents();
+    }
+}
+
+/// one normal per triangle. Vertices are split so no two triangles share one.
+pub fn flat_normals(geometry : &mut ModelGeometry) {
+    let triangles = triangles(geometry);
+    let corners : Vec<usize> = triangles.iter().flatten().copied().collect();
+
+    geometry.positions = gather(&geometry.positions, &corners);
+    geometry.colors = gather(&geometry.colors, &corners);
+    geometry.tex_coords = gather(&geometry.tex_coords, &corners);
+    geometry.normals = geometry.positions.chunks_exact(3).flat_map(|t| {
+        let [a, b, c] = [0, 1, 2].map(|i| Vec3::from(t[i].position));
+        let normal = (b - a).cross(c - a).normalize_or_zero();
+        [NormalElement { normal : normal.to_array() }; 3]
+    }).collect();
+    geometry.indices = (0..corners.len() as u32).collect();
+    refresh_tangents(geometry);
+}
+
+/// per vertex normals averaged over the adjacent triangles, weighted by the corner angle.
+/// Vertices at the same position get the same normal, so UV seams don't show.
+pub fn smooth_normals(geometry : &mut ModelGeometry) {
+    let key = |p : &PositionElement| p.position.map(f32::to_bits);
+    let mut sums : HashMap<[u32; 3], Vec3> = HashMap::new();
+
+    for [a, b, c] in triangles(geometry) {
+        let p = [a, b, c].map(|i| Vec3::from(geometry.positions[i].position));
+        let normal = (p[1] - p[0]).cross(p[2] - p[0]).normalize_or_zero();
+        for (corner, index) in [a, b, c].into_iter().enumerate() {
+            let angle = (p[(corner + 1) % 3] - p[corner]).angle_between(p[(corner + 2) % 3] - p[corner]);
+            if angle.is_finite() {
+                *sums.entry(key(&geometry.positions[index])).or_default() += normal * angle;
+            }
+        }
+    }
+
+    geometry.normals = geometry.positions.iter().map(|p| {
+        let normal = sums.get(&key(p)).copied().unwrap_or_default().normalize_or_zero();
+        NormalElement { normal : normal.to_array() }
+    }).collect();
+    refresh_tangents(geometry);
+}
+
+/// merge vertices whose attributes are all within `epsilon`, returns the number of removed vertices
+pub fn weld(geometry : &mut ModelGeometry, epsilon : f32) -> usize {
+    let epsilon = epsilon.max(f32::EPSILON);
+    let quantize = |values : &[f32]| values.iter().map(|v| (v / epsilon).round() as i64).collect::<Vec<_>>();
+
+    let vertex_count = geometry.positions.len();
+    let mut welded : HashMap<Vec<i64>, u32> = HashMap::new();
+    let mut remap = Vec::with_capacity(vertex_count);
+    let mut kept = Vec::new();
+
+    for i in 0..vertex_count {
+        let mut key = quantize(&geometry.positions[i].position);
+        if let Some(n) = geometry.normals.get(i) { key.extend(quantize(&n.normal)); }
+        if let Some(c) = geometry.colors.get(i) { key.extend(quantize(&c.color)); }
+        if let Some(t) = geometry.tex_coords.get(i) { key.extend(quantize(&t.position)); }
+
+        let index = *welded.entry(key).or_insert_with(|| {
+            kept.push(i);
+            kept.len() as u32 - 1
+        });
+        remap.push(index);
+    }
+
+    let corners : Vec<usize> = triangles(geometry).into_iter().flatten().collect();
+    geometry.indices = corners.iter().map(|i| remap[*i]).collect();
+
+    geometry.positions = gather(&geometry.positions, &kept);
+    geometry.normals = gather(&geometry.normals, &kept);
+    geometry.colors = gather(&geometry.colors, &kept);
+    geometry.tex_coords = gather(&geometry.tex_coords, &kept);
+    refresh_tangents(geometry);
+
+    vertex_count - kept.len()
+}
+
+/// move the center of the bounding box to the origin
+pub fn recenter(geometry : &mut ModelGeometry) {
+    if let Some(aabb) = aabb(geometry) {
+        transform_positions(geometry, Mat4::from_translation(-aabb.center()));
+    }
+}
+
+/// recenter and scale uniformly so the largest side of the bounding box is 1
+pub fn fit_unit_box(geometry : &mut ModelGeometry) {
+    if let Some(aabb) = aabb(geometry) {
+        let largest = aabb.size().max_element();
+        let scale = if largest > 0.0 { 1.0 / largest } else { 1.0 };
+        transform_positions(geometry, Mat4::from_scale(Vec3::splat(scale)) * Mat4::from_translation(-aabb.center()));
+    }
+}
+
+/// transform positions by `matrix` and normals by its inverse transpose
+pub fn transform(geometry : &mut ModelGeometry, matrix : Mat4) {
+    transform_positions(geometry, matrix);
+    let normal_matrix = matrix.inverse().transpose();
+    for n in &mut geometry.normals {
+        n.normal = normal_matrix.transform_vector3(Vec3::from(n.normal)).normalize_or_zero().to_array();
+    }
+    refresh_tangents(geometry);
+}
+
+// normals and tangents stay valid, `matrix` is only translation and uniform scale
+fn transform_positions(geometry : &mut ModelGeometry, matrix : Mat4) {
+    for p in &mut geometry.positions {
+        p.position = matrix.transform_point3(Vec3::from(p.position)).to_array();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometries::TexCoordElement;
+
+    fn cube() -> ModelGeometry {
+        procedural::rounded_box(Vec3::splat(2.0), 0.0, 1)
+    }
+
+    fn normal(geometry : &ModelGeometry, index : usize) -> Vec3 {
+        Vec3::from(geometry.normals[index].normal)
+    }
+
+    #[test]
+    fn flat_normals_of_a_cube_follow_the_faces() {
+        let mut geometry = cube();
+        flat_normals(&mut geometry);
+        assert_eq!(geometry.positions.len(), 36);
+        assert_eq!(geometry.indices, (0..36).collect::<Vec<u32>>());
+        assert_eq!(geometry.tangents.len(), 36);
+        for (i, p) in geometry.positions.iter().enumerate() {
+            let n = normal(&geometry, i);
+            // axis aligned and on the face the vertex lies on
+            assert_eq!(n.abs().max_element(), 1.0, "{n}");
+            assert!((Vec3::from(p.position).dot(n) - 1.0).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn smooth_normals_of_a_cube_point_out_of_the_corners() {
+        let mut geometry = cube();
+        smooth_normals(&mut geometry);
+        assert_eq!(geometry.positions.len(), 24);
+        for (i, p) in geometry.positions.iter().enumerate() {
+            // every corner has three faces with the same angle
+            let expected = Vec3::from(p.position).normalize();
+            assert!(normal(&geometry, i).abs_diff_eq(expected, 1e-5), "{} != {expected}", normal(&geometry, i));
+        }
+    }
+
+    #[test]
+    fn weld_merges_duplicated_cube_vertices() {
+        let mut geometry = cube();
+        flat_normals(&mut geometry);
+        // the two triangles of a face share two corners
+        assert_eq!(weld(&mut geometry, 1e-4), 12);
+        assert_eq!(geometry.positions.len(), 24);
+        assert_eq!(geometry.indices.len(), 36);
+
+        // without per face normals and texture coordinates only the corners are left
+        geometry.tex_coords = vec![TexCoordElement::default(); 24];
+        smooth_normals(&mut geometry);
+        assert_eq!(weld(&mut geometry, 1e-4), 16);
+        assert_eq!(geometry.positions.len(), 8);
+        assert!(geometry.indices.iter().all(|i| *i < 8));
+        assert_eq!(weld(&mut geometry, 1e-4), 0);
+    }
+
+    #[test]
+    fn bounds_of_known_points() {
+        let points = [Vec3::new(-1.0, 0.0, 2.0), Vec3::new(3.0, -2.0, 0.0), Vec3::new(1.0, 4.0, 1.0)];
+        let aabb = Aabb::from_points(points).unwrap();
+        assert_eq!(aabb, Aabb::new(Vec3::new(-1.0, -2.0, 0.0), Vec3::new(3.0, 4.0, 2.0)));
+        assert_eq!(aabb.center(), Vec3::new(1.0, 1.0, 1.0));
+        assert_eq!(aabb.half_extents(), Vec3::new(2.0, 3.0, 1.0));
+        assert!(Aabb::from_points(std::iter::empty()).is_none());
+
+        let sphere = BoundingSphere::from_points(&points).unwrap();
+        assert!(points.iter().all(|p| sphere.center.distance(*p) <= sphere.radius + 1e-5));
+        assert!(BoundingSphere::from_points(&[]).is_none());
+
+        // the 8 corners of a cube, the minimal sphere is found exactly
+        let corners = Aabb::new(Vec3::splat(-1.0), Vec3::ONE).corners();
+        let sphere = BoundingSphere::from_points(&corners).unwrap();
+        assert!(sphere.center.abs_diff_eq(Vec3::ZERO, 1e-6));
+        assert!((sphere.radius - 3.0_f32.sqrt()).abs() < 1e-5);
+    }
+
+    #[test]
+    fn geometry_bounds() {
+        let mut geometry = cube();
+        transform(&mut geometry, Mat4::from_translation(Vec3::new(1.0, 2.0, 3.0)));
+        assert_eq!(aabb(&geometry), Some(Aabb::new(Vec3::new(0.0, 1.0, 2.0), Vec3::new(2.0, 3.0, 4.0))));
+        let sphere = bounding_sphere(&geometry).unwrap();
+        assert!(sphere.center.abs_diff_eq(Vec3::new(1.0, 2.0, 3.0), 1e-5));
+
+        fit_unit_box(&mut geometry);
+        assert_eq!(aabb(&geometry), Some(Aabb::new(Vec3::splat(-0.5), Vec3::splat(0.5))));
+    }
+
+    #[test]
+    fn transform_keeps_normals_perpendicular() {
+        // 45 degree slope, stretching along X makes it flatter
+        let mut geometry = procedural::plane(1.0, 1.0, 1, 1);
+        transform(&mut geometry, Mat4::from_rotation_z(std::f32::consts::FRAC_PI_4));
+        transform(&mut geometry, Mat4::from_scale(Vec3::new(4.0, 1.0, 1.0)));
+        for t in geometry.indices.chunks_exact(3) {
+            let [a, b, c] = [0, 1, 2].map(|i| Vec3::from(geometry.positions[t[i] as usize].position));
+            let face_normal = (b - a).cross(c - a).normalize();
+            for i in t {
+                let normal = Vec3::from(geometry.normals[*i as usize].normal);
+                assert!(normal.abs_diff_eq(face_normal, 1e-5), "{normal} != {face_normal}");
+            }
+        }
+    }
+}
@@ -0,0 +1,370 @@
+use std::collections::HashMap;
+use std::f32::consts::{FRAC_PI_2, PI, TAU};
+
+use glam::{Vec2, Vec3};
+
+use crate::geometries::{ColorElement, NormalElement, PositionElement, TexCoordElement};
+use crate::model::ModelGeometry;
+
+// Generated meshes are centered on the origin, white, and wound like the
+// `CUBE_*` arrays: cross(b - a, c - a) points out of the surface.
+// Texture V runs from 0 at the top to 1 at the bottom.
+
+#[derive(Default)]
+struct MeshBuilder {
+    positions : Vec<Vec3>,
+    normals : Vec<Vec3>,
+    tex_coords : Vec<Vec2>,
+    indices : Vec<u32>,
+}
+
+impl MeshBuilder {
+    fn vertex(&mut self, position : Vec3, normal : Vec3, tex_coord : Vec2) -> u32 {
+        self.positions.push(position);
+        self.normals.push(normal.normalize_or_zero());
+        self.tex_coords.push(tex_coord);
+        self.positions.len() as u32 - 1
+    }
+
+    /// adds the triangle, flipped if needed so it faces along its vertex normals
+    fn triangle(&mut self, a : u32, b : u32, c : u32) {
+        let [pa, pb, pc] = [a, b, c].map(|i| self.positions[i as usize]);
+        let normal = [a, b, c].iter().map(|i| self.normals[*i as usize]).sum::<Vec3>();
+        if (pb - pa).cross(pc - pa).dot(normal) < 0.0 {
+            self.indices.extend_from_slice(&[a, c, b]);
+        } else {
+            self.indices.extend_from_slice(&[a, b, c]);
+        }
+    }
+
+    /// quads between a grid of `rows` x `columns` vertices starting at `first`
+    fn grid_indices(&mut self, first : u32, rows : u32, columns : u32) {
+        for row in 0..rows - 1 {
+            for column in 0..columns - 1 {
+                let top_left = first + row * columns + column;
+                let bottom_left = top_left + columns;
+                self.triangle(top_left, bottom_left, top_left + 1);
+                self.triangle(top_left + 1, bottom_left, bottom_left + 1);
+            }
+        }
+    }
+
+    /// surface of revolution around Y. Each profile point is (radius, y),
+    /// its normal (radial, y) and the texture V of that row.
+    fn revolve(&mut self, profile : &[(Vec2, Vec2, f32)], segments : u32) {
+        let first = self.positions.len() as u32;
+        for (point, normal, v) in profile {
+            for segment in 0..=segments {
+                let u = segment as f32 / segments as f32;
+                let (sin, cos) = (u * TAU).sin_cos();
+                self.vertex(
+                    Vec3::new(point.x * cos, point.y, point.x * sin),
+                    Vec3::new(normal.x * cos, normal.y, normal.x * sin),
+                    Vec2::new(u, *v));
+            }
+        }
+        self.grid_indices(first, profile.len() as u32, segments + 1);
+    }
+
+    /// flat disk at height `y` facing up or down
+    fn disk(&mut self, radius : f32, y : f32, up : bool, segments : u32) {
+        let normal = if up { Vec3::Y } else { Vec3::NEG_Y };
+        let center = self.vertex(Vec3::new(0.0, y, 0.0), normal, Vec2::splat(0.5));
+        for segment in 0..=segments {
+            let (sin, cos) = (segment as f32 / segments as f32 * TAU).sin_cos();
+            self.vertex(
+                Vec3::new(radius * cos, y, radius * sin),
+                normal,
+                Vec2::new(0.5 + 0.5 * cos, 0.5 - 0.5 * sin));
+        }
+        for segment in 0..segments {
+            self.triangle(center, center + 1 + segment, center + 2 + segment);
+        }
+    }
+
+    fn build(self) -> ModelGeometry {
+        let mut geometry = ModelGeometry {
+            positions : self.positions.iter().map(|p| PositionElement { position : p.to_array() }).collect(),
+            colors : vec![ColorElement { color : [1.0, 1.0, 1.0, 1.0] }; self.positions.len()],
+            normals : self.normals.iter().map(|n| NormalElement { normal : n.to_array() }).collect(),
+            tex_coords : self.tex_coords.iter().map(|t| TexCoordElement { position : t.to_array() }).collect(),
+            tangents : Vec::new(),
+            indices : self.indices,
+        };
+        geometry.generate_tangents();
+        geometry
+    }
+}
+
+/// sphere made of `segments` slices around Y and `rings` stacks from pole to pole
+pub fn uv_sphere(radius : f32, segments : u32, rings : u32) -> ModelGeometry {
+    let (segments, rings) = (segments.max(3), rings.max(2));
+    let profile : Vec<_> = (0..=rings).map(|ring| {
+        let v = ring as f32 / rings as f32;
+        let normal = Vec2::new((v * PI).sin(), (v * PI).cos());
+        (normal * radius, normal, v)
+    }).collect();
+
+    let mut builder = MeshBuilder::default();
+    builder.revolve(&profile, segments);
+    builder.build()
+}
+
+/// subdivided icosahedron, evenly spread triangles without pole pinching.
+/// UVs are spherical, so textures show a seam where U wraps around.
+pub fn icosphere(radius : f32, subdivisions : u32) -> ModelGeometry {
+    let t = (1.0 + 5.0_f32.sqrt()) / 2.0;
+    let mut directions : Vec<Vec3> = [
+        (-1.0, t, 0.0), (1.0, t, 0.0), (-1.0, -t, 0.0), (1.0, -t, 0.0),
+        (0.0, -1.0, t), (0.0, 1.0, t), (0.0, -1.0, -t), (0.0, 1.0, -t),
+        (t, 0.0, -1.0), (t, 0.0, 1.0), (-t, 0.0, -1.0), (-t, 0.0, 1.0),
+    ].iter().map(|(x, y, z)| Vec3::new(*x, *y, *z).normalize()).collect();
+
+    let mut triangles : Vec<[u32; 3]> = vec![
+        [0, 11, 5], [0, 5, 1], [0, 1, 7], [0, 7, 10], [0, 10, 11],
+        [1, 5, 9], [5, 11, 4], [11, 10, 2], [10, 7, 6], [7, 1, 8],
+        [3, 9, 4], [3, 4, 2], [3, 2, 6], [3, 6, 8], [3, 8, 9],
+        [4, 9, 5], [2, 4, 11], [6, 2, 10], [8, 6, 7], [9, 8, 1],
+    ];
+
+    for _ in 0..subdivisions {
+        // edge midpoints are shared between the two triangles of an edge
+        let mut midpoints : HashMap<(u32, u32), u32> = HashMap::new();
+        let mut midpoint = |a : u32, b : u32| *midpoints.entry((a.min(b), a.max(b))).or_insert_with(|| {
+            directions.push((directions[a as usize] + directions[b as usize]).normalize());
+            directions.len() as u32 - 1
+        });
+        triangles = triangles.iter().flat_map(|&[a, b, c]| {
+            let (ab, bc, ca) = (midpoint(a, b), midpoint(b, c), midpoint(c, a));
+            [[a, ab, ca], [b, bc, ab], [c, ca, bc], [ab, bc, ca]]
+        }).collect();
+    }
+
+    let mut builder = MeshBuilder::default();
+    for direction in &directions {
+        let u = direction.z.atan2(direction.x).rem_euclid(TAU) / TAU;
+        let v = direction.y.clamp(-1.0, 1.0).acos() / PI;
+        builder.vertex(*direction * radius, *direction, Vec2::new(u, v));
+    }
+    for [a, b, c] in triangles {
+        builder.triangle(a, b, c);
+    }
+    builder.build()
+}
+
+/// capped cylinder along Y
+pub fn cylinder(radius : f32, height : f32, segments : u32, height_segments : u32) -> ModelGeometry {
+    let (segments, height_segments) = (segments.max(3), height_segments.max(1));
+    let profile : Vec<_> = (0..=height_segments).map(|row| {
+        let v = row as f32 / height_segments as f32;
+        (Vec2::new(radius, height * (0.5 - v)), Vec2::X, v)
+    }).collect();
+
+    let mut builder = MeshBuilder::default();
+    builder.revolve(&profile, segments);
+    builder.disk(radius, 0.5 * height, true, segments);
+    builder.disk(radius, -0.5 * height, false, segments);
+    builder.build()
+}
+
+/// cylinder along Y with half sphere ends, `height` includes the ends.
+/// `rings` is the number of stacks per half sphere.
+pub fn capsule(radius : f32, height : f32, segments : u32, rings : u32) -> ModelGeometry {
+    let (segments, rings) = (segments.max(3), rings.max(1));
+    let half_straight = (0.5 * height - radius).max(0.0);
+
+    // top half sphere down to the equator, then the bottom one from its equator
+    let mut profile = Vec::new();
+    for (center, from) in [(half_straight, 0.0), (-half_straight, FRAC_PI_2)] {
+        for ring in 0..=rings {
+            let angle = from + ring as f32 / rings as f32 * FRAC_PI_2;
+            let normal = Vec2::new(angle.sin(), angle.cos());
+            profile.push((normal * radius + Vec2::new(0.0, center), normal, 0.0));
+        }
+    }
+    // V follows the height, so the texture isn't stretched over the straight part
+    let top = half_straight + radius;
+    for (point, _, v) in &mut profile {
+        *v = (top - point.y) / (2.0 * top);
+    }
+
+    let mut builder = MeshBuilder::default();
+    builder.revolve(&profile, segments);
+    builder.build()
+}
+
+/// flat plane in XZ facing +Y, split into a grid of quads
+pub fn plane(width : f32, depth : f32, subdivisions_x : u32, subdivisions_z : u32) -> ModelGeometry {
+    let (columns, rows) = (subdivisions_x.max(1) + 1, subdivisions_z.max(1) + 1);
+    let mut builder = MeshBuilder::default();
+    for row in 0..rows {
+        for column in 0..columns {
+            let uv = Vec2::new(column as f32 / (columns - 1) as f32, row as f32 / (rows - 1) as f32);
+            builder.vertex(
+                Vec3::new(width * (uv.x - 0.5), 0.0, depth * (0.5 - uv.y)),
+                Vec3::Y,
+                uv);
+        }
+    }
+    builder.grid_indices(0, rows, columns);
+    builder.build()
+}
+
+/// ring in the XZ plane, `segments` around the ring and `sides` around the tube
+pub fn torus(major_radius : f32, minor_radius : f32, segments : u32, sides : u32) -> ModelGeometry {
+    let (segments, sides) = (segments.max(3), sides.max(3));
+    let profile : Vec<_> = (0..=sides).map(|side| {
+        let v = side as f32 / sides as f32;
+        let (sin, cos) = (v * TAU).sin_cos();
+        let normal = Vec2::new(cos, sin);
+        (Vec2::new(major_radius, 0.0) + normal * minor_radius, normal, v)
+    }).collect();
+
+    let mut builder = MeshBuilder::default();
+    builder.revolve(&profile, segments);
+    builder.build()
+}
+
+/// box with edges and corners rounded by `radius`, `segments` steps per rounded edge
+pub fn rounded_box(size : Vec3, radius : f32, segments : u32) -> ModelGeometry {
+    let half = 0.5 * size;
+    let radius = radius.clamp(0.0, half.min_element());
+    let segments = segments.max(1);
+    let inner = half - Vec3::splat(radius);
+
+    // coordinates along one face axis: the rounded part at both ends, the flat part in between
+    let samples = |half : f32| -> Vec<f32> {
+        if radius <= 0.0 {
+            return vec![-half, half];
+        }
+        let arc : Vec<f32> = (0..=segments)
+            .map(|step| (step as f32 / segments as f32 * FRAC_PI_2).cos())
+            .collect();
+        arc.iter().map(|c| -(half - radius) - radius * c)
+            .chain(arc.iter().rev().map(|c| (half - radius) + radius * c))
+            .collect()
+    };
+
+    let mut builder = MeshBuilder::default();
+    for axis in 0..3 {
+        let (u_axis, v_axis) = ((axis + 1) % 3, (axis + 2) % 3);
+        let (us, vs) = (samples(half[u_axis]), samples(half[v_axis]));
+        for side in [1.0, -1.0] {
+            let first = builder.positions.len() as u32;
+            for (row, v) in vs.iter().enumerate() {
+                for (column, u) in us.iter().enumerate() {
+                    let mut point = Vec3::ZERO;
+                    point[axis] = side * half[axis];
+                    point[u_axis] = *u;
+                    point[v_axis] = *v;
+                    // push the point onto the rounded surface around the inner box
+                    let core = point.clamp(-inner, inner);
+                    let normal = (point - core).normalize_or_zero();
+                    let position = if radius > 0.0 { core + normal * radius } else { point };
+                    let mut face_normal = Vec3::ZERO;
+                    face_normal[axis] = side;
+                    builder.vertex(
+                        position,
+                        if radius > 0.0 { normal } else { face_normal },
+                        Vec2::new(column as f32 / (us.len() - 1) as f32, row as f32 / (vs.len() - 1) as f32));
+                }
+            }
+            builder.grid_indices(first, vs.len() as u32, us.len() as u32);
+        }
+    }
+    builder.build()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(geometry : &ModelGeometry, vertices : usize, indices : usize) {
+        assert_eq!(geometry.positions.len(), vertices);
+        assert_eq!(geometry.indices.len(), indices);
+        for count in [geometry.normals.len(), geometry.colors.len(), geometry.tex_coords.len(), geometry.tangents.len()] {
+            assert_eq!(count, vertices);
+        }
+        assert!(geometry.indices.iter().all(|i| (*i as usize) < vertices));
+        for n in &geometry.normals {
+            assert!((Vec3::from(n.normal).length() - 1.0).abs() < 1e-4, "{:?}", n.normal);
+        }
+    }
+
+    // every triangle of a convex mesh around the origin faces away from it
+    fn check_outward(geometry : &ModelGeometry) {
+        for t in geometry.indices.chunks_exact(3) {
+            let [a, b, c] = [0, 1, 2].map(|i| Vec3::from(geometry.positions[t[i] as usize].position));
+            let normal = (b - a).cross(c - a);
+            if normal.length() > 1e-6 {
+                assert!(normal.dot(a + b + c) > 0.0, "{a} {b} {c}");
+            }
+        }
+    }
+
+    #[test]
+    fn uv_sphere() {
+        let sphere = super::uv_sphere(2.0, 8, 4);
+        check(&sphere, 5 * 9, 4 * 8 * 6);
+        check_outward(&sphere);
+        assert!(sphere.positions.iter().all(|p| (Vec3::from(p.position).length() - 2.0).abs() < 1e-5));
+    }
+
+    #[test]
+    fn icosphere() {
+        check(&super::icosphere(1.0, 0), 12, 20 * 3);
+        let sphere = super::icosphere(1.0, 2);
+        check(&sphere, 162, 320 * 3);
+        check_outward(&sphere);
+    }
+
+    #[test]
+    fn cylinder() {
+        let cylinder = super::cylinder(0.5, 2.0, 6, 2);
+        check(&cylinder, 3 * 7 + 2 * 8, 2 * 6 * 6 + 2 * 6 * 3);
+        check_outward(&cylinder);
+    }
+
+    #[test]
+    fn capsule() {
+        let capsule = super::capsule(0.5, 2.0, 6, 3);
+        check(&capsule, 8 * 7, 7 * 6 * 6);
+        check_outward(&capsule);
+        let top = capsule.positions.iter().map(|p| p.position[1]).fold(f32::MIN, f32::max);
+        assert!((top - 1.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn plane() {
+        let plane = super::plane(2.0, 1.0, 3, 2);
+        check(&plane, 4 * 3, 3 * 2 * 6);
+        for t in plane.indices.chunks_exact(3) {
+            let [a, b, c] = [0, 1, 2].map(|i| Vec3::from(plane.positions[t[i] as usize].position));
+            assert!((b - a).cross(c - a).y > 0.0);
+        }
+    }
+
+    #[test]
+    fn torus() {
+        check(&super::torus(1.0, 0.25, 8, 6), 7 * 9, 6 * 8 * 6);
+    }
+
+    #[test]
+    fn box_without_rounding() {
+        let cube = rounded_box(Vec3::new(2.0, 1.0, 1.0), 0.0, 4);
+        check(&cube, 6 * 4, 6 * 6);
+        check_outward(&cube);
+    }
+
+    #[test]
+    fn rounded_box_stays_inside_its_size() {
+        let size = Vec3::new(2.0, 1.0, 0.5);
+        let rounded = rounded_box(size, 0.1, 3);
+        // 2 * (segments + 1) samples along each face axis
+        check(&rounded, 6 * 8 * 8, 6 * 7 * 7 * 6);
+        check_outward(&rounded);
+        for p in &rounded.positions {
+            assert!(Vec3::from(p.position).abs().cmple(0.5 * size + 1e-5).all());
+        }
+    }
+}
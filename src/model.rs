@@ -1,208 +1,364 @@
-use std::ops::Deref;
 use std::path::Path;
+use glam::{Vec3, Vec4};
+use crate::assets::{AssetServer, Handle};
+use crate::errors::WvtError;
+use crate::mesh;
+use crate::scene::{NodeId, Scene, Transform};
 use crate::texture2d::Texture2d;
-use crate::geometries::{ColorElement, NormalElement, PositionElement, TexCoordElement};
+use crate::geometries::{self, ColorElement, NormalElement, PositionElement, TangentElement, TexCoordElement};
+use crate::renderers::material_buffer;
+
+mod gltf_loader;
+
+/// Metallic / roughness parameters of a material. Read as is from glTF files,
+/// estimated from the Phong parameters for OBJ files.
+#[derive(Debug)]
+pub struct PbrMaterial {
+    /// linear rgba, multiplied with the base color texture
+    pub base_color: Vec4,
+    pub metallic: f32,
+    pub roughness: f32,
+    /// linear rgb, multiplied with the emissive texture
+    pub emissive: Vec3,
+    /// roughness in the green, metalness in the blue channel
+    pub metallic_roughness_texture: Option<Handle<Texture2d>>,
+    /// ambient occlusion in the red channel
+    pub occlusion_texture: Option<Handle<Texture2d>>,
+    pub emissive_texture: Option<Handle<Texture2d>>,
+}
+
+impl Default for PbrMaterial {
+    fn default() -> Self {
+        Self {
+            base_color: Vec4::ONE,
+            metallic: 0.0,
+            roughness: 1.0,
+            emissive: Vec3::ZERO,
+            metallic_roughness_texture: None,
+            occlusion_texture: None,
+            emissive_texture: None,
+        }
+    }
+}
 
+/// Material of a model, as read from the MTL file or the glTF material
+#[derive(Debug)]
 pub struct Material {
     pub name: String,
-    pub diffuse_texture: Texture2d,
-    pub bind_group: wgpu::BindGroup,
+    /// Ka / Kd / Ks / Ns mapped onto the renderer material uniform,
+    /// approximated from `pbr` for glTF files
+    pub params: material_buffer::Material,
+    pub pbr: PbrMaterial,
+    /// map_Kd or the glTF base color texture
+    pub diffuse_texture: Option<Handle<Texture2d>>,
+    /// map_Bump or the glTF normal texture
+    pub normal_texture: Option<Handle<Texture2d>>,
 }
 
+#[derive(Debug)]
 pub struct Mesh {
     pub name: String,
-    pub vertex_buffer: wgpu::Buffer,
-    pub index_buffer: wgpu::Buffer,
-    pub num_elements: u32,
-    pub material: usize,
+    pub geometry: Handle<ModelGeometry>,
+    /// index into `Model::materials`
+    pub material: Option<usize>,
+}
+
+/// Node of the model hierarchy. OBJ files have a single node holding all meshes.
+#[derive(Debug)]
+pub struct ModelNode {
+    pub name: String,
+    pub transform: Transform,
+    /// indices into `Model::nodes`
+    pub children: Vec<usize>,
+    /// indices into `Model::meshes`
+    pub meshes: Vec<usize>,
 }
 
 // model.rs
+#[derive(Debug)]
 pub struct Model {
     pub meshes: Vec<Mesh>,
-    pub materials: Vec<Material>,
+    pub materials: Vec<Handle<Material>>,
+    pub nodes: Vec<ModelNode>,
+    /// indices of the top level nodes
+    pub roots: Vec<usize>,
 }
 
 
+#[derive(Debug)]
 pub struct ModelGeometry {
     pub positions: Vec<PositionElement>,
     pub colors : Vec<ColorElement>,
     pub normals : Vec<NormalElement>,
     pub tex_coords : Vec<TexCoordElement>,
+    /// empty when the mesh has no texture coordinates
+    pub tangents : Vec<TangentElement>,
     pub indices : Vec<u32>,
 }
 
-// impl<'a> ModelGeometry<'a> {
-//     pub fn new() -> Self {
-//         Self {
-//             positions : &CUBE_POSITIONS,
-//             normals : &CUBE_NORMALS,
-//             colors : &CUBE_COLORS,
-//             indices : &CUBE_INDICES,
-//             tex_coords : &CUBE_TEXCOORDS,
-//         }
-//     }
-// }
+impl ModelGeometry {
+    fn from_tobj(mesh : &tobj::Mesh) -> Self {
+        let positions:Vec<PositionElement> = mesh.positions.
+            chunks(3).
+            map(  | f| {
+                PositionElement { position: [f[0],f[1],f[2]] }
+            }).collect();
+
+        // every vertex buffer needs an entry per vertex, default to white
+        let colors:Vec<ColorElement> = if mesh.vertex_color.is_empty() {
+            vec![ColorElement { color: [1.0, 1.0, 1.0, 1.0] }; positions.len()]
+        } else {
+            mesh.vertex_color.
+                chunks(3).
+                map(  | f| {
+                    ColorElement { color: [f[0],f[1],f[2], 1.0] }
+                }).collect()
+        };
+
+        let normals:Vec<NormalElement> = mesh.normals.
+            chunks(3).
+            map(  | f| {
+                NormalElement { normal: [f[0],f[1],f[2]] }
+            }).collect();
+
+        // OBJ has the V axis pointing up, wgpu down
+        let tex_coords:Vec<TexCoordElement> = if mesh.texcoords.is_empty() {
+            vec![TexCoordElement::default(); positions.len()]
+        } else {
+            mesh.texcoords.
+                chunks(2).
+                map(  | f| {
+                    TexCoordElement { position: [f[0], 1.0 - f[1]] }
+                }).collect()
+        };
+
+        let indices:Vec<u32> = mesh.indices.clone();
+
+        let mut geometry = Self {
+            positions,
+            colors,
+            normals,
+            tex_coords,
+            tangents : Vec::new(),
+            indices,
+        };
+        // MaterialShader always binds a normal buffer
+        if mesh.normals.is_empty() {
+            mesh::smooth_normals(&mut geometry);
+        }
+        if !mesh.texcoords.is_empty() {
+            geometry.generate_tangents();
+        }
+        geometry
+    }
+
+    /// (re)compute the tangents from positions, normals and texture coordinates
+    pub fn generate_tangents(&mut self) {
+        self.tangents = geometries::compute_tangents(&self.positions, &self.normals, &self.tex_coords, &self.indices);
+    }
+}
+
+impl Material {
+    fn from_tobj(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        assets: &mut AssetServer,
+        material : &tobj::Material,
+        directory : &Path ) -> Result<Self, WvtError> {
+
+        let color = |c : Option<[f32; 3]>, default : Vec3| c.map(Vec3::from).unwrap_or(default);
+        let mut params = material_buffer::Material::new(
+            color(material.ambient, Vec3::ZERO),
+            color(material.diffuse, Vec3::ONE),
+            color(material.specular, Vec3::ZERO));
+        if let Some(shininess) = material.shininess {
+            params.shininess = shininess;
+        }
+        // inverse of the usual Blinn-Phong exponent to GGX roughness mapping
+        let pbr = PbrMaterial {
+            base_color: color(material.diffuse, Vec3::ONE).extend(material.dissolve.unwrap_or(1.0)),
+            roughness: (2.0 / (params.shininess.max(0.0) + 2.0)).sqrt().sqrt(),
+            ..Default::default()
+        };
+
+        // texture paths in the MTL file are relative to the OBJ file
+        let diffuse_texture = material.diffuse_texture.as_ref()
+            .map( |file| assets.load_texture(device, queue, directory.join(file)))
+            .transpose()?;
+        // normal maps hold vectors, not colors, so they are not sRGB decoded
+        let normal_texture = material.normal_texture.as_ref()
+            .map( |file| assets.load_linear_texture(device, queue, directory.join(file)))
+            .transpose()?;
+
+        Ok(Self {
+            name: material.name.clone(),
+            params,
+            pbr,
+            diffuse_texture,
+            normal_texture,
+        })
+    }
+}
 
-impl Model {
-    /// constructor, expects a filepath to a 3D model.
-    pub fn new( path: &str) -> ModelGeometry { //-> Self {
-        
-        //let mut meshes = Vec::new();
-        // let mut texture_pool = TexturePool::new(gl.clone());
+fn obj_load_options() -> tobj::LoadOptions {
+    tobj::LoadOptions {
+        triangulate : true,
+        single_index : true,
+        ..Default::default()
+    }
+}
 
-        let path = Path::new(path);
+// meshes and materials of a parsed OBJ file, errors keep the path
+fn check_obj(path : &Path, loaded : tobj::LoadResult) -> Result<(Vec<tobj::Model>, Vec<tobj::Material>), WvtError> {
+    let (mut models, materials_result) = loaded
+        .map_err(|source| WvtError::ObjLoad { path : path.into(), source })?;
 
-        // retrieve the directory path of the filepath
-        let directory : String = path.parent().unwrap_or_else(|| Path::new("")).to_str().unwrap().into();
-        let mut load_options = tobj::LoadOptions::default();
-        load_options.triangulate = true;
-        load_options.single_index = true;
+    // tobj keeps an unnamed object even when the file has no faces
+    models.retain(|model| !model.mesh.indices.is_empty());
+    if models.is_empty() {
+        return Err(WvtError::EmptyModel { path : path.into() });
+    }
+
+    let materials = materials_result
+        .map_err(|source| WvtError::MissingMaterial { path : path.into(), source })?;
+    Ok((models, materials))
+}
 
-        let obj = tobj::load_obj(path, &load_options);
+impl Model {
+    /// constructor, expects a filepath to a 3D model.
+    /// Same as `load`, textures and materials are shared through `assets`.
+    pub fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        assets: &mut AssetServer,
+        path: &str) -> Result<Self, WvtError> {
+
+        Self::load(device, queue, assets, Path::new(path))
+    }
 
-        //dbg!(obj);
-        let (models, materials_result) = obj.unwrap();
+    /// load a 3D model, sharing geometry, materials and textures already loaded by `assets`
+    pub fn load(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        assets: &mut AssetServer,
+        path: &Path) -> Result<Self, WvtError> {
 
+        let extension = path.extension().and_then(|e| e.to_str()).unwrap_or_default().to_ascii_lowercase();
+        if extension == "gltf" || extension == "glb" {
+            return gltf_loader::load(device, queue, assets, path);
+        }
 
-        let mut geos = Vec::new();
+        // retrieve the directory path of the filepath
+        let directory = path.parent().unwrap_or_else(|| Path::new(""));
+        let (models, materials) = check_obj(path, tobj::load_obj(path, &obj_load_options()))?;
+
+        let materials = materials
+            .iter()
+            .map( |m| assets.material_or_try_insert_with(path, &m.name, |assets|
+                Material::from_tobj(device, queue, assets, m, directory)))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let meshes : Vec<Mesh> = models.into_iter()
+            .enumerate()
+            .map( |(index, model)| Mesh {
+                geometry: assets.geometry_or_insert_with(path, index, || ModelGeometry::from_tobj(&model.mesh)),
+                material: model.mesh.material_id.filter(|id| *id < materials.len()),
+                name: model.name,
+            })
+            .collect();
+
+        let root = ModelNode {
+            name: path.file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default(),
+            transform: Transform::IDENTITY,
+            children: Vec::new(),
+            meshes: (0..meshes.len()).collect(),
+        };
+
+        Ok(Self {
+            meshes,
+            materials,
+            nodes: vec![root],
+            roots: vec![0],
+        })
+    }
 
-        dbg!(models.len());
+    /// add the node hierarchy below `parent`, returns the scene node of every mesh
+    /// as `(node, index into meshes)`
+    pub fn instantiate(&self, scene : &mut Scene, parent : Option<NodeId>) -> Vec<(NodeId, usize)> {
+        let mut meshes = Vec::new();
+        for root in &self.roots {
+            self.instantiate_node(scene, *root, parent, &mut meshes);
+        }
+        meshes
+    }
 
-        dbg!(materials_result);
+    fn instantiate_node(&self, scene : &mut Scene, index : usize, parent : Option<NodeId>, meshes : &mut Vec<(NodeId, usize)>) {
+        let node = &self.nodes[index];
+        let id = scene.add_node(&node.name, node.transform, parent);
+        meshes.extend(node.meshes.iter().map(|mesh| (id, *mesh)));
+        for child in &node.children {
+            self.instantiate_node(scene, *child, Some(id), meshes);
+        }
+    }
 
-        for model in models.into_iter(){
-            let mesh = &model.mesh;
+    pub fn material(&self, mesh : &Mesh) -> Option<&Material> {
+        mesh.material.and_then(|id| self.materials.get(id)).map(|m| &**m)
+    }
+}
 
-            dbg!( mesh.material_id );           
-            let positions:Vec<PositionElement> = mesh.positions.
-                chunks(3).
-                map(  | f| {
-                    PositionElement { position: [f[0],f[1],f[2]] }
-                }).collect();
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-            let colors:Vec<ColorElement> = mesh.vertex_color.
-                chunks(3).
-                map(  | f| {
-                    ColorElement { color: [f[0],f[1],f[2], 1.0] }
-                }).collect();
+    const TRIANGLE : &str = "v 0 0 0\nv 1 0 0\nv 0 1 0\n";
 
-            let normals:Vec<NormalElement> = mesh.normals.
-                chunks(3).
-                map(  | f| {
-                    NormalElement { normal: [f[0],f[1],f[2]] }
-                }).collect();
+    // parse `obj` from memory, every MTL file is missing
+    fn parse(obj : &str) -> Result<(Vec<tobj::Model>, Vec<tobj::Material>), WvtError> {
+        let loaded = tobj::load_obj_buf(&mut obj.as_bytes(), &obj_load_options(), |_| Err(tobj::LoadError::OpenFileFailed));
+        check_obj(Path::new("test.obj"), loaded)
+    }
 
-            let tex_coords:Vec<TexCoordElement> = mesh.texcoords.
-                chunks(2).
-                map(  | f| {
-                    TexCoordElement { position: [f[0],f[1]] }
-                }).collect();
-
-            let indices:Vec<u32> = mesh.indices.clone();
-
-            geos.push( ModelGeometry {
-                positions,
-                colors,
-                normals,
-                tex_coords,
-                indices,
-            } );
+    #[test]
+    fn missing_normals_are_generated() {
+        let (models, materials) = parse(&format!("{TRIANGLE}f 1 2 3\n")).unwrap();
+        assert!(materials.is_empty());
+        let geometry = ModelGeometry::from_tobj(&models[0].mesh);
+        assert_eq!(geometry.indices, [0, 1, 2]);
+        for normal in &geometry.normals {
+            assert_eq!(normal.normal, [0.0, 0.0, 1.0]);
         }
+        // every vertex buffer is filled, tangents need texture coordinates
+        assert_eq!(geometry.colors.len(), 3);
+        assert_eq!(geometry.tex_coords.len(), 3);
+        assert!(geometry.tangents.is_empty());
+    }
 
-        geos.pop().unwrap()
+    #[test]
+    fn file_normals_and_texture_coordinates_are_kept() {
+        let obj = format!("{TRIANGLE}vn 0 0 -1\nvt 0 0\nvt 1 0\nvt 0.25 0.75\nf 1/1/1 2/2/1 3/3/1\n");
+        let (models, _) = parse(&obj).unwrap();
+        let geometry = ModelGeometry::from_tobj(&models[0].mesh);
+        assert!(geometry.normals.iter().all(|n| n.normal == [0.0, 0.0, -1.0]));
+        // V is flipped to point down
+        assert_eq!(geometry.tex_coords[2].position, [0.25, 0.25]);
+        assert_eq!(geometry.tangents.len(), 3);
     }
-}
 
-// pub fn load_model(
-//     file_name: &str,
-//     device: &wgpu::Device,
-//     queue: &wgpu::Queue,
-//     layout: &wgpu::BindGroupLayout,
-// ) -> anyhow::Result<model::Model> {
-//     let obj_text = load_string(file_name).await?;
-//     let obj_cursor = Cursor::new(obj_text);
-//     let mut obj_reader = BufReader::new(obj_cursor);
-
-//     let (models, obj_materials) = tobj::load_obj_buf_async(
-//         &mut obj_reader,
-//         &tobj::LoadOptions {
-//             triangulate: true,
-//             single_index: true,
-//             ..Default::default()
-//         },
-//         |p| async move {
-//             let mat_text = load_string(&p).await.unwrap();
-//             tobj::load_mtl_buf(&mut BufReader::new(Cursor::new(mat_text)))
-//         },
-//     )
-//     .await?;
-
-//     let mut materials = Vec::new();
-//     for m in obj_materials? {
-//         let diffuse_texture = load_texture(&m.diffuse_texture, device, queue).await?;
-//         let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-//             layout,
-//             entries: &[
-//                 wgpu::BindGroupEntry {
-//                     binding: 0,
-//                     resource: wgpu::BindingResource::TextureView(&diffuse_texture.view),
-//                 },
-//                 wgpu::BindGroupEntry {
-//                     binding: 1,
-//                     resource: wgpu::BindingResource::Sampler(&diffuse_texture.sampler),
-//                 },
-//             ],
-//             label: None,
-//         });
-
-//         materials.push(model::Material {
-//             name: m.name,
-//             diffuse_texture,
-//             bind_group,
-//         })
-//     }
-
-//     let meshes = models
-//         .into_iter()
-//         .map(|m| {
-//             let vertices = (0..m.mesh.positions.len() / 3)
-//                 .map(|i| model::ModelVertex {
-//                     position: [
-//                         m.mesh.positions[i * 3],
-//                         m.mesh.positions[i * 3 + 1],
-//                         m.mesh.positions[i * 3 + 2],
-//                     ],
-//                     tex_coords: [m.mesh.texcoords[i * 2], 1.0 - m.mesh.texcoords[i * 2 + 1]],
-//                     normal: [
-//                         m.mesh.normals[i * 3],
-//                         m.mesh.normals[i * 3 + 1],
-//                         m.mesh.normals[i * 3 + 2],
-//                     ],
-//                 })
-//                 .collect::<Vec<_>>();
-
-//             let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-//                 label: Some(&format!("{:?} Vertex Buffer", file_name)),
-//                 contents: bytemuck::cast_slice(&vertices),
-//                 usage: wgpu::BufferUsages::VERTEX,
-//             });
-//             let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-//                 label: Some(&format!("{:?} Index Buffer", file_name)),
-//                 contents: bytemuck::cast_slice(&m.mesh.indices),
-//                 usage: wgpu::BufferUsages::INDEX,
-//             });
-
-//             model::Mesh {
-//                 name: file_name.to_string(),
-//                 vertex_buffer,
-//                 index_buffer,
-//                 num_elements: m.mesh.indices.len() as u32,
-//                 material: m.mesh.material_id.unwrap_or(0),
-//             }
-//         })
-//         .collect::<Vec<_>>();
-
-//     Ok(model::Model { meshes, materials })
-// }
+    #[test]
+    fn missing_material_library_is_an_error() {
+        let error = parse(&format!("mtllib missing.mtl\n{TRIANGLE}f 1 2 3\n")).unwrap_err();
+        assert!(matches!(&error, WvtError::MissingMaterial { path, .. } if path == Path::new("test.obj")), "{error:?}");
+    }
 
+    #[test]
+    fn file_without_faces_is_an_empty_model() {
+        let error = parse(TRIANGLE).unwrap_err();
+        assert!(matches!(error, WvtError::EmptyModel { .. }), "{error:?}");
+    }
+
+    #[test]
+    fn broken_faces_are_a_load_error() {
+        let error = parse("v 0 0 0\nf 1 2 3\n").unwrap_err();
+        assert!(matches!(error, WvtError::ObjLoad { .. }), "{error:?}");
+    }
+}
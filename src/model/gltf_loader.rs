@@ -0,0 +1,324 @@
+use std::ops::Range;
+use std::path::Path;
+use glam::{Quat, Vec3, Vec4};
+use gltf::image::Format;
+use image::{DynamicImage, ImageBuffer};
+use crate::assets::{AssetServer, Handle};
+use crate::errors::WvtError;
+use crate::geometries::{ColorElement, NormalElement, PositionElement, TexCoordElement};
+use crate::mesh;
+use crate::renderers::material_buffer;
+use crate::scene::Transform;
+use crate::texture2d::{SamplerPreset, Texture2d};
+use super::{Material, Mesh, Model, ModelGeometry, ModelNode, PbrMaterial};
+
+// decoded buffers and images of the file, looked up by index while reading
+struct Source<'a> {
+    path : &'a Path,
+    buffers : &'a [gltf::buffer::Data],
+    images : &'a [gltf::image::Data],
+}
+
+/// load a `.gltf` (with external or embedded buffers) or `.glb` file.
+/// Every triangle primitive becomes a `Mesh`, glTF nodes map one to one onto `Model::nodes`.
+pub(super) fn load(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    assets: &mut AssetServer,
+    path: &Path) -> Result<Model, WvtError> {
+
+    let (document, buffers, images) = gltf::import(path)
+        .map_err(|source| WvtError::GltfLoad { path : path.into(), source })?;
+    let source = Source { path, buffers : &buffers, images : &images };
+
+    let materials = document.materials()
+        .filter_map(|material| material.index().map(|index| (index, material)))
+        .map( |(index, material)| assets.material_or_try_insert_with(path, &format!("#{index}"), |assets|
+            read_material(device, queue, assets, &source, &material)))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    // primitives are flattened, remember which meshes belong to which glTF mesh
+    let mut meshes = Vec::new();
+    let mut mesh_ranges : Vec<Range<usize>> = Vec::new();
+    for gltf_mesh in document.meshes() {
+        let start = meshes.len();
+        for primitive in gltf_mesh.primitives() {
+            if primitive.mode() != gltf::mesh::Mode::Triangles {
+                log::warn!("{}: skipping {:?} primitive of mesh {}", path.display(), primitive.mode(), gltf_mesh.index());
+                continue;
+            }
+            // only decoded when the geometry isn't loaded yet
+            let index = meshes.len();
+            let Ok(geometry) = assets.geometry_or_try_insert_with(path, index, || read_geometry(&source, &primitive).ok_or(())) else {
+                log::warn!("{}: skipping primitive without positions in mesh {}", path.display(), gltf_mesh.index());
+                continue;
+            };
+            meshes.push(Mesh {
+                name: gltf_mesh.name().map(String::from).unwrap_or_else(|| format!("mesh{}", gltf_mesh.index())),
+                geometry,
+                material: primitive.material().index().filter(|id| *id < materials.len()),
+            });
+        }
+        mesh_ranges.push(start..meshes.len());
+    }
+
+    if meshes.is_empty() {
+        return Err(WvtError::EmptyModel { path : path.into() });
+    }
+
+    let nodes = document.nodes()
+        .map( |node| {
+            let (translation, rotation, scale) = node.transform().decomposed();
+            ModelNode {
+                name: node.name().map(String::from).unwrap_or_else(|| format!("node{}", node.index())),
+                transform: Transform {
+                    translation: Vec3::from(translation),
+                    rotation: Quat::from_array(rotation),
+                    scale: Vec3::from(scale),
+                },
+                children: node.children().map(|child| child.index()).collect(),
+                meshes: node.mesh().map(|m| mesh_ranges[m.index()].clone().collect()).unwrap_or_default(),
+            }
+        })
+        .collect::<Vec<_>>();
+
+    // without scenes every node that is nobody's child is a root
+    let roots = match document.default_scene().or_else(|| document.scenes().next()) {
+        Some(scene) => scene.nodes().map(|node| node.index()).collect(),
+        None => (0..nodes.len())
+            .filter(|index| !nodes.iter().any(|node| node.children.contains(index)))
+            .collect(),
+    };
+
+    Ok(Model {
+        meshes,
+        materials,
+        nodes,
+        roots,
+    })
+}
+
+fn read_geometry(source : &Source, primitive : &gltf::Primitive) -> Option<ModelGeometry> {
+    let reader = primitive.reader(|buffer| source.buffers.get(buffer.index()).map(|data| &data[..]));
+
+    let positions : Vec<PositionElement> = reader.read_positions()?
+        .map(|position| PositionElement { position })
+        .collect();
+
+    let colors = match reader.read_colors(0) {
+        Some(colors) => colors.into_rgba_f32().map(|color| ColorElement { color }).collect(),
+        None => vec![ColorElement { color: [1.0, 1.0, 1.0, 1.0] }; positions.len()],
+    };
+
+    // glTF texture coordinates already have V pointing down
+    let mut has_tex_coords = false;
+    let tex_coords = match reader.read_tex_coords(0) {
+        Some(tex_coords) => {
+            has_tex_coords = true;
+            tex_coords.into_f32().map(|position| TexCoordElement { position }).collect()
+        }
+        None => vec![TexCoordElement::default(); positions.len()],
+    };
+
+    let normals : Vec<NormalElement> = reader.read_normals()
+        .map(|normals| normals.map(|normal| NormalElement { normal }).collect())
+        .unwrap_or_default();
+
+    let indices = match reader.read_indices() {
+        Some(indices) => indices.into_u32().collect(),
+        None => (0..positions.len() as u32).collect(),
+    };
+
+    let mut geometry = ModelGeometry {
+        positions,
+        colors,
+        normals,
+        tex_coords,
+        tangents : Vec::new(),
+        indices,
+    };
+    if geometry.normals.is_empty() {
+        mesh::smooth_normals(&mut geometry);
+    }
+    // stored tangents are ignored, generated ones match the handedness the shader expects
+    if has_tex_coords {
+        geometry.generate_tangents();
+    }
+    Some(geometry)
+}
+
+fn read_material(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    assets: &mut AssetServer,
+    source : &Source,
+    material : &gltf::Material ) -> Result<Material, WvtError> {
+
+    let pbr_params = material.pbr_metallic_roughness();
+    let mut texture = |texture : Option<gltf::Texture>, srgb : bool|
+        texture.map(|texture| load_texture(device, queue, assets, source, &texture, srgb)).transpose();
+
+    let diffuse_texture = texture(pbr_params.base_color_texture().map(|info| info.texture()), true)?;
+    let normal_texture = texture(material.normal_texture().map(|info| info.texture()), false)?;
+    let pbr = PbrMaterial {
+        base_color: Vec4::from(pbr_params.base_color_factor()),
+        metallic: pbr_params.metallic_factor(),
+        roughness: pbr_params.roughness_factor(),
+        emissive: Vec3::from(material.emissive_factor()),
+        metallic_roughness_texture: texture(pbr_params.metallic_roughness_texture().map(|info| info.texture()), false)?,
+        occlusion_texture: texture(material.occlusion_texture().map(|info| info.texture()), false)?,
+        emissive_texture: texture(material.emissive_texture().map(|info| info.texture()), true)?,
+    };
+
+    Ok(Material {
+        name: material.name().map(String::from).unwrap_or_else(|| format!("material{}", material.index().unwrap_or_default())),
+        params: phong_params(&pbr),
+        pbr,
+        diffuse_texture,
+        normal_texture,
+    })
+}
+
+// approximate the metallic / roughness parameters with the Blinn-Phong material uniform
+fn phong_params(pbr : &PbrMaterial) -> material_buffer::Material {
+    let base_color = pbr.base_color.truncate();
+    let metallic = pbr.metallic.clamp(0.0, 1.0);
+    // dielectrics reflect ~4% uncolored, metals reflect their base color
+    let specular = Vec3::splat(0.04).lerp(base_color, metallic);
+    let mut params = material_buffer::Material::new(base_color * (1.0 - metallic), base_color * (1.0 - metallic), specular);
+
+    let alpha = pbr.roughness.clamp(0.05, 1.0).powi(2);
+    params.shininess = (2.0 / (alpha * alpha) - 2.0).clamp(1.0, 2048.0);
+    params
+}
+
+fn load_texture(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    assets: &mut AssetServer,
+    source : &Source,
+    texture : &gltf::Texture,
+    srgb : bool ) -> Result<Handle<Texture2d>, WvtError> {
+
+    let index = texture.source().index();
+    assets.embedded_texture_or_try_insert_with(source.path, index, srgb, || {
+        let data = &source.images[index];
+        let img = to_dynamic_image(data).ok_or_else(|| WvtError::UnsupportedImageFormat {
+            path : source.path.into(),
+            index,
+            format : format!("{:?}", data.format),
+        })?;
+        let sampler = match texture.sampler().mag_filter() {
+            Some(gltf::texture::MagFilter::Nearest) => SamplerPreset::Nearest,
+            _ => SamplerPreset::default(),
+        };
+        let format = if srgb { wgpu::TextureFormat::Rgba8UnormSrgb } else { wgpu::TextureFormat::Rgba8Unorm };
+        let label = texture.name().map(String::from).unwrap_or_else(|| format!("{} image {index}", source.path.display()));
+        Texture2d::from_image_with_format(device, queue, &img, Some(&label), sampler, format)
+    })
+}
+
+// two channel images are red and green (e.g. metallic / roughness), not luma and alpha
+fn expand_rg<T : Copy>(pixels : &[T], zero : T, one : T) -> Vec<T> {
+    pixels.chunks_exact(2).flat_map(|rg| [rg[0], rg[1], zero, one]).collect()
+}
+
+// gltf decodes images through the image crate, so the formats map back onto its variants
+fn to_dynamic_image(data : &gltf::image::Data) -> Option<DynamicImage> {
+    let (width, height) = (data.width, data.height);
+    let bytes = || data.pixels.clone();
+    let wide = || data.pixels.chunks_exact(2).map(|b| u16::from_ne_bytes([b[0], b[1]])).collect::<Vec<_>>();
+    match data.format {
+        Format::R8 => ImageBuffer::from_raw(width, height, bytes()).map(DynamicImage::ImageLuma8),
+        Format::R8G8 => ImageBuffer::from_raw(width, height, expand_rg(&data.pixels, 0, u8::MAX)).map(DynamicImage::ImageRgba8),
+        Format::R8G8B8 => ImageBuffer::from_raw(width, height, bytes()).map(DynamicImage::ImageRgb8),
+        Format::R8G8B8A8 => ImageBuffer::from_raw(width, height, bytes()).map(DynamicImage::ImageRgba8),
+        Format::R16 => ImageBuffer::from_raw(width, height, wide()).map(DynamicImage::ImageLuma16),
+        Format::R16G16 => ImageBuffer::from_raw(width, height, expand_rg(&wide(), 0, u16::MAX)).map(DynamicImage::ImageRgba16),
+        Format::R16G16B16 => ImageBuffer::from_raw(width, height, wide()).map(DynamicImage::ImageRgb16),
+        Format::R16G16B16A16 => ImageBuffer::from_raw(width, height, wide()).map(DynamicImage::ImageRgba16),
+        Format::R32G32B32FLOAT | Format::R32G32B32A32FLOAT => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn image(format : Format, pixels : Vec<u8>) -> gltf::image::Data {
+        gltf::image::Data { pixels, format, width : 2, height : 1 }
+    }
+
+    // one UV mapped triangle under a parent node, no scenes
+    const TRIANGLE_GLTF : &str = r#"{
+        "asset": { "version": "2.0" },
+        "buffers": [{
+            "byteLength": 60,
+            "uri": "data:application/octet-stream;base64,AAAAAAAAAAAAAAAAAACAPwAAAAAAAAAAAAAAAAAAgD8AAAAAAAAAAAAAAAAAAIA/AAAAAAAAAAAAAIA/"
+        }],
+        "bufferViews": [
+            { "buffer": 0, "byteOffset": 0, "byteLength": 36 },
+            { "buffer": 0, "byteOffset": 36, "byteLength": 24 }
+        ],
+        "accessors": [
+            { "bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3", "min": [0, 0, 0], "max": [1, 1, 0] },
+            { "bufferView": 1, "componentType": 5126, "count": 3, "type": "VEC2" }
+        ],
+        "meshes": [{ "name": "triangle", "primitives": [{ "attributes": { "POSITION": 0, "TEXCOORD_0": 1 } }] }],
+        "nodes": [
+            { "name": "parent", "children": [1] },
+            { "mesh": 0, "translation": [0, 2, 0] }
+        ]
+    }"#;
+
+    #[test]
+    fn loads_nodes_and_meshes_without_scenes() {
+        let Some(gpu) = crate::gpu::test_gpu() else { return };
+        let path = std::env::temp_dir().join(format!("wvt_triangle_{}.gltf", std::process::id()));
+        std::fs::write(&path, TRIANGLE_GLTF).unwrap();
+        let model = load(&gpu.device, &gpu.queue, &mut AssetServer::new(), &path);
+        std::fs::remove_file(&path).unwrap();
+        let model = model.unwrap();
+
+        assert_eq!(model.meshes.len(), 1);
+        assert_eq!(model.meshes[0].name, "triangle");
+        assert_eq!(model.meshes[0].material, None);
+        let geometry = &model.meshes[0].geometry;
+        assert_eq!(geometry.indices, [0, 1, 2]);
+        // normals and tangents are generated
+        assert_eq!(geometry.normals[0].normal, [0.0, 0.0, 1.0]);
+        assert_eq!(geometry.tangents.len(), 3);
+        assert!(model.materials.is_empty());
+
+        assert_eq!(model.nodes.len(), 2);
+        assert_eq!(model.nodes[0].name, "parent");
+        assert_eq!(model.nodes[0].children, [1]);
+        assert!(model.nodes[0].meshes.is_empty());
+        assert_eq!(model.nodes[1].name, "node1");
+        assert_eq!(model.nodes[1].meshes, [0]);
+        assert_eq!(model.nodes[1].transform.translation, Vec3::new(0.0, 2.0, 0.0));
+        // only the node that is nobody's child
+        assert_eq!(model.roots, [0]);
+    }
+
+    #[test]
+    fn two_channel_images_are_red_and_green() {
+        let img = to_dynamic_image(&image(Format::R8G8, vec![10, 20, 30, 40])).unwrap().to_rgba8();
+        assert_eq!(img.into_raw(), [10, 20, 0, 255, 30, 40, 0, 255]);
+
+        let wide = [1000u16, 2000, 3000, 4000].iter().flat_map(|v| v.to_ne_bytes()).collect();
+        let img = to_dynamic_image(&image(Format::R16G16, wide)).unwrap().to_rgba16();
+        assert_eq!(img.into_raw(), [1000, 2000, 0, u16::MAX, 3000, 4000, 0, u16::MAX]);
+    }
+
+    #[test]
+    fn other_formats() {
+        let img = to_dynamic_image(&image(Format::R8, vec![7, 9])).unwrap().to_rgba8();
+        assert_eq!(img.into_raw(), [7, 7, 7, 255, 9, 9, 9, 255]);
+        let img = to_dynamic_image(&image(Format::R8G8B8, vec![1, 2, 3, 4, 5, 6])).unwrap().to_rgba8();
+        assert_eq!(img.into_raw(), [1, 2, 3, 255, 4, 5, 6, 255]);
+        // too few pixels for the size
+        assert!(to_dynamic_image(&image(Format::R8G8B8A8, vec![0; 4])).is_none());
+        assert!(to_dynamic_image(&image(Format::R32G32B32FLOAT, vec![0; 24])).is_none());
+    }
+}
@@ -0,0 +1,269 @@
+use glam::{Quat, Vec3};
+
+use crate::mesh::{Aabb, BoundingSphere};
+use crate::math::Ray;
+
+/// First contact of a moving sphere
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct SweepHit {
+    /// fraction of the motion until contact, 0 when already touching at the start
+    pub time : f32,
+    /// unit normal of the box surface at the contact, points towards the sphere
+    pub normal : Vec3,
+    /// contact point on the box surface
+    pub point : Vec3,
+}
+
+/// Box with an arbitrary orientation
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Obb {
+    pub center : Vec3,
+    pub half_extents : Vec3,
+    pub rotation : Quat,
+}
+
+impl Obb {
+    pub fn new(center : Vec3, half_extents : Vec3, rotation : Quat) -> Self {
+        Self { center, half_extents, rotation }
+    }
+
+    pub fn from_aabb(aabb : &Aabb) -> Self {
+        Self::new(aabb.center(), aabb.half_extents(), Quat::IDENTITY)
+    }
+
+    // the box in its own space, centered at the origin
+    fn local_aabb(&self) -> Aabb {
+        Aabb::new(-self.half_extents, self.half_extents)
+    }
+}
+
+/// Continuous collision of a sphere moving by `motion` against a static box.
+/// The sphere center is traced against the box grown by `radius` with rounded edges,
+/// so thin boxes are never tunneled through no matter how fast the sphere is.
+pub fn sweep_sphere_aabb(center : Vec3, radius : f32, motion : Vec3, aabb : &Aabb) -> Option<SweepHit> {
+    let closest = center.clamp(aabb.min, aabb.max);
+    if closest.distance_squared(center) <= radius * radius {
+        return Some(SweepHit { time : 0.0, normal : overlap_normal(center, closest, aabb), point : closest });
+    }
+    if motion == Vec3::ZERO {
+        return None;
+    }
+
+    let ray = Ray::new(center, motion);
+    let expanded = Aabb::new(aabb.min - radius, aabb.max + radius);
+    let mut time = ray.intersect_aabb(&expanded).filter(|t| *t <= 1.0)?;
+
+    // outside of the original box on more than one axis means the hit is on a rounded
+    // edge or corner of the grown box, not on its flat part
+    let point = ray.at(time);
+    let below = point.cmplt(aabb.min);
+    let above = point.cmpgt(aabb.max);
+    let outside = (below | above).bitmask();
+    if outside.count_ones() > 1 {
+        let corner = Vec3::select(above, aabb.max, aabb.min);
+        let other = Vec3::select(above, aabb.min, aabb.max);
+        time = (0..3)
+            .filter(|axis| outside.count_ones() == 3 || outside & (1 << axis) == 0)
+            .filter_map(|axis| {
+                let mut end = corner;
+                end[axis] = other[axis];
+                intersect_capsule(&ray, corner, end, radius)
+            })
+            .min_by(f32::total_cmp)
+            .filter(|t| *t <= 1.0)?;
+    }
+
+    let point = ray.at(time);
+    let contact = point.clamp(aabb.min, aabb.max);
+    Some(SweepHit { time, normal : (point - contact).normalize_or_zero(), point : contact })
+}
+
+/// `sweep_sphere_aabb` for a rotated box, done in the box space
+pub fn sweep_sphere_obb(center : Vec3, radius : f32, motion : Vec3, obb : &Obb) -> Option<SweepHit> {
+    let inverse = obb.rotation.inverse();
+    let local_center = inverse * (center - obb.center);
+    let hit = sweep_sphere_aabb(local_center, radius, inverse * motion, &obb.local_aabb())?;
+    Some(SweepHit {
+        time : hit.time,
+        normal : obb.rotation * hit.normal,
+        point : obb.center + obb.rotation * hit.point,
+    })
+}
+
+// direction to push a sphere out of a box it already touches
+fn overlap_normal(center : Vec3, closest : Vec3, aabb : &Aabb) -> Vec3 {
+    if center != closest {
+        return (center - closest).normalize();
+    }
+    // center inside the box, leave through the nearest face
+    let to_min = center - aabb.min;
+    let to_max = aabb.max - center;
+    let (axis, _) = to_min.min(to_max).to_array().into_iter().enumerate()
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .expect("3 axes");
+    let mut normal = Vec3::ZERO;
+    normal[axis] = if to_min[axis] <= to_max[axis] { -1.0 } else { 1.0 };
+    normal
+}
+
+// first hit of a ray starting outside of the capsule from `a` to `b`
+fn intersect_capsule(ray : &Ray, a : Vec3, b : Vec3, radius : f32) -> Option<f32> {
+    let axis = b - a;
+    let offset = ray.origin - a;
+    let axis_axis = axis.dot(axis);
+    let axis_dir = axis.dot(ray.direction);
+    let axis_offset = axis.dot(offset);
+
+    // infinite cylinder around the axis, clipped to the segment
+    let qa = axis_axis * ray.direction.dot(ray.direction) - axis_dir * axis_dir;
+    let qb = axis_axis * offset.dot(ray.direction) - axis_offset * axis_dir;
+    let qc = axis_axis * offset.dot(offset) - axis_offset * axis_offset - radius * radius * axis_axis;
+    let discriminant = qb * qb - qa * qc;
+    let side = if qa.abs() > f32::EPSILON && discriminant >= 0.0 {
+        let t = (-qb - discriminant.sqrt()) / qa;
+        let along = axis_offset + t * axis_dir;
+        (t >= 0.0 && (0.0..=axis_axis).contains(&along)).then_some(t)
+    } else {
+        None
+    };
+
+    [a, b].into_iter()
+        .filter_map(|center| ray.intersect_sphere(&BoundingSphere::new(center, radius)))
+        .chain(side)
+        .min_by(f32::total_cmp)
+}
+
+/// Bounce `velocity` off a surface with unit `normal`. The normal part is scaled by
+/// `restitution` (1 elastic, 0 no bounce), the tangential part is kept.
+pub fn reflect(velocity : Vec3, normal : Vec3, restitution : f32) -> Vec3 {
+    let normal_speed = velocity.dot(normal);
+    if normal_speed >= 0.0 {
+        // already separating
+        return velocity;
+    }
+    velocity - (1.0 + restitution) * normal_speed * normal
+}
+
+/// Velocity picked up from a moving surface: `spin` times the part of `surface_velocity`
+/// along the surface, e.g. a paddle moving up while hitting the ball.
+pub fn surface_spin(normal : Vec3, surface_velocity : Vec3, spin : f32) -> Vec3 {
+    spin * (surface_velocity - surface_velocity.dot(normal) * normal)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::{FRAC_1_SQRT_2, FRAC_PI_4, FRAC_PI_2, SQRT_2};
+
+    fn unit_box() -> Aabb {
+        Aabb::new(Vec3::splat(-1.0), Vec3::ONE)
+    }
+
+    fn assert_hit(hit : Option<SweepHit>, time : f32, normal : Vec3, point : Vec3) {
+        let hit = hit.expect("hit");
+        assert!((hit.time - time).abs() < 1e-4, "time {} != {time}", hit.time);
+        assert!(hit.normal.abs_diff_eq(normal, 1e-4), "normal {} != {normal}", hit.normal);
+        assert!(hit.point.abs_diff_eq(point, 1e-4), "point {} != {point}", hit.point);
+    }
+
+    #[test]
+    fn fast_sphere_does_not_tunnel_through_a_thin_box() {
+        let wall = Aabb::new(Vec3::new(-0.01, -1.0, -1.0), Vec3::new(0.01, 1.0, 1.0));
+        // the whole wall is crossed within one step
+        let hit = sweep_sphere_aabb(Vec3::new(-10.0, 0.0, 0.0), 0.1, Vec3::new(20.0, 0.0, 0.0), &wall);
+        assert_hit(hit, 9.89 / 20.0, Vec3::NEG_X, Vec3::new(-0.01, 0.0, 0.0));
+    }
+
+    #[test]
+    fn face_hit() {
+        let hit = sweep_sphere_aabb(Vec3::new(-3.0, 0.5, 0.0), 0.5, Vec3::new(4.0, 0.0, 0.0), &unit_box());
+        assert_hit(hit, 1.5 / 4.0, Vec3::NEG_X, Vec3::new(-1.0, 0.5, 0.0));
+
+        let hit = sweep_sphere_aabb(Vec3::new(0.3, 4.0, -0.2), 0.5, Vec3::new(0.0, -5.0, 0.0), &unit_box());
+        assert_hit(hit, 2.5 / 5.0, Vec3::Y, Vec3::new(0.3, 1.0, -0.2));
+    }
+
+    #[test]
+    fn motion_that_ends_short_of_the_box() {
+        assert!(sweep_sphere_aabb(Vec3::new(-3.0, 0.0, 0.0), 0.5, Vec3::new(1.0, 0.0, 0.0), &unit_box()).is_none());
+        assert!(sweep_sphere_aabb(Vec3::new(-3.0, 0.0, 0.0), 0.5, Vec3::new(-4.0, 0.0, 0.0), &unit_box()).is_none());
+        assert!(sweep_sphere_aabb(Vec3::new(-3.0, 0.0, 0.0), 0.5, Vec3::ZERO, &unit_box()).is_none());
+    }
+
+    #[test]
+    fn touching_at_the_start() {
+        let hit = sweep_sphere_aabb(Vec3::new(1.25, 0.0, 0.0), 0.5, Vec3::new(1.0, 0.0, 0.0), &unit_box());
+        assert_hit(hit, 0.0, Vec3::X, Vec3::new(1.0, 0.0, 0.0));
+        // center inside, pushed out through the nearest face
+        let hit = sweep_sphere_aabb(Vec3::new(0.0, -0.9, 0.0), 0.5, Vec3::ZERO, &unit_box());
+        assert_hit(hit, 0.0, Vec3::NEG_Y, Vec3::new(0.0, -0.9, 0.0));
+    }
+
+    #[test]
+    fn rounded_edge_hit() {
+        let offset = 0.5 * FRAC_1_SQRT_2;
+        let hit = sweep_sphere_aabb(Vec3::new(3.0, 3.0, 0.0), 0.5, Vec3::new(-4.0, -4.0, 0.0), &unit_box());
+        assert_hit(hit, (2.0 - offset) / 4.0, Vec3::new(1.0, 1.0, 0.0).normalize(), Vec3::new(1.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn rounded_corner_hit() {
+        let offset = 0.5 / 3.0_f32.sqrt();
+        let hit = sweep_sphere_aabb(Vec3::splat(3.0), 0.5, Vec3::splat(-4.0), &unit_box());
+        assert_hit(hit, (2.0 - offset) / 4.0, Vec3::ONE.normalize(), Vec3::ONE);
+    }
+
+    #[test]
+    fn grown_box_corner_without_edge_contact() {
+        // passes through the corner of the box grown by the radius,
+        // but never gets closer than 0.4 * sqrt(2) to the edge at x = y = 1
+        let center = Vec3::new(0.4, 2.4, 0.0);
+        let motion = Vec3::new(2.0, -2.0, 0.0);
+        let expanded = Aabb::new(Vec3::splat(-1.5), Vec3::splat(1.5));
+        assert!(Ray::new(center, motion).intersect_aabb(&expanded).is_some());
+        assert!(sweep_sphere_aabb(center, 0.5, motion, &unit_box()).is_none());
+
+        // same near a corner
+        let center = Vec3::new(0.4, 2.4, 1.4);
+        assert!(sweep_sphere_aabb(center, 0.5, motion, &unit_box()).is_none());
+    }
+
+    #[test]
+    fn rotated_box() {
+        // a cube turned 45 degrees around z shows an edge at x = sqrt(2)
+        let obb = Obb::new(Vec3::ZERO, Vec3::ONE, Quat::from_rotation_z(FRAC_PI_4));
+        let hit = sweep_sphere_obb(Vec3::new(5.0, 0.0, 0.0), 0.5, Vec3::new(-10.0, 0.0, 0.0), &obb);
+        assert_hit(hit, (5.0 - SQRT_2 - 0.5) / 10.0, Vec3::X, Vec3::new(SQRT_2, 0.0, 0.0));
+
+        // tall thin box turned onto its side, offset from the origin
+        let obb = Obb::new(Vec3::new(1.0, 0.0, 0.0), Vec3::new(0.05, 2.0, 1.0), Quat::from_rotation_z(FRAC_PI_2));
+        let hit = sweep_sphere_obb(Vec3::new(2.0, 5.0, 0.0), 0.5, Vec3::new(0.0, -10.0, 0.0), &obb);
+        assert_hit(hit, (5.0 - 0.55) / 10.0, Vec3::Y, Vec3::new(2.0, 0.05, 0.0));
+        // the unrotated box would be missed
+        let aabb = Obb::new(obb.center, obb.half_extents, Quat::IDENTITY);
+        assert!(sweep_sphere_obb(Vec3::new(4.0, 5.0, 0.0), 0.5, Vec3::new(0.0, -10.0, 0.0), &aabb).is_none());
+    }
+
+    #[test]
+    fn reflect_with_restitution() {
+        let velocity = Vec3::new(3.0, -2.0, 0.0);
+        assert_eq!(reflect(velocity, Vec3::Y, 1.0), Vec3::new(3.0, 2.0, 0.0));
+        assert_eq!(reflect(velocity, Vec3::Y, 0.0), Vec3::new(3.0, 0.0, 0.0));
+        assert_eq!(reflect(velocity, Vec3::Y, 0.5), Vec3::new(3.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn separating_velocity_is_not_reflected() {
+        let velocity = Vec3::new(3.0, 2.0, 0.0);
+        assert_eq!(reflect(velocity, Vec3::Y, 1.0), velocity);
+        // sliding along the surface
+        assert_eq!(reflect(Vec3::X, Vec3::Y, 1.0), Vec3::X);
+    }
+
+    #[test]
+    fn surface_spin_keeps_the_tangential_part() {
+        assert_eq!(surface_spin(Vec3::X, Vec3::new(1.0, 2.0, 0.0), 0.5), Vec3::new(0.0, 1.0, 0.0));
+        assert_eq!(surface_spin(Vec3::X, Vec3::new(4.0, 0.0, 0.0), 0.5), Vec3::ZERO);
+        assert_eq!(surface_spin(Vec3::X, Vec3::new(0.0, 2.0, 0.0), 0.0), Vec3::ZERO);
+    }
+}
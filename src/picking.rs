@@ -0,0 +1,35 @@
+use glam::{Mat4, Vec3};
+
+use crate::math::Ray;
+use crate::mesh;
+use crate::model::ModelGeometry;
+
+/// nearest triangle of `geometry` hit by `ray`, after a bounding box early out
+pub fn intersect_geometry(ray : &Ray, geometry : &ModelGeometry) -> Option<f32> {
+    ray.intersect_aabb(&mesh::aabb(geometry)?)?;
+    let position = |i : usize| Vec3::from(geometry.positions[i].position);
+    mesh::triangles(geometry).into_iter()
+        .filter_map(|[a, b, c]| ray.intersect_triangle([position(a), position(b), position(c)]))
+        .min_by(f32::total_cmp)
+}
+
+/// Nearest object hit by a ray
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct PickHit<T> {
+    pub object : T,
+    /// world space hit point
+    pub point : Vec3,
+    /// from the ray origin, in world units
+    pub distance : f32,
+}
+
+/// test `ray` against the triangles of every `(object, geometry, model matrix)`, nearest hit wins
+pub fn pick<'a, T>(ray : &Ray, objects : impl IntoIterator<Item = (T, &'a ModelGeometry, Mat4)>) -> Option<PickHit<T>> {
+    let ray = Ray::new(ray.origin, ray.direction.normalize_or_zero());
+    objects.into_iter()
+        .filter_map(|(object, geometry, model_matrix)| {
+            let t = intersect_geometry(&ray.transformed(&model_matrix.inverse()), geometry)?;
+            Some(PickHit { object, point : ray.at(t), distance : t })
+        })
+        .min_by(|a, b| a.distance.total_cmp(&b.distance))
+}
@@ -0,0 +1,272 @@
+use crate::errors::WvtError;
+use crate::texture2d::Texture2d;
+
+enum TargetKind {
+    Surface {
+        surface : wgpu::Surface,
+        config : wgpu::SurfaceConfiguration,
+    },
+    Offscreen {
+        texture : wgpu::Texture,
+        format : wgpu::TextureFormat,
+        width : u32,
+        height : u32,
+    },
+}
+
+/// Where a frame gets rendered to: a window surface or an offscreen texture.
+/// Both come with a matching depth buffer, so renderers don't care which one they draw into.
+pub struct RenderTarget {
+    kind : TargetKind,
+    depth_texture : Texture2d,
+    reversed_z : bool,
+}
+
+/// A color view to render one frame into, obtained from `RenderTarget::acquire`
+pub struct Frame {
+    surface_texture : Option<wgpu::SurfaceTexture>,
+    pub view : wgpu::TextureView,
+}
+
+impl Frame {
+    /// show the frame on screen, a no-op for offscreen targets
+    pub fn present(self) {
+        if let Some(surface_texture) = self.surface_texture {
+            surface_texture.present();
+        }
+    }
+}
+
+impl RenderTarget {
+    /// expects an already configured surface
+    pub fn from_surface(device: &wgpu::Device, surface : wgpu::Surface, config : wgpu::SurfaceConfiguration) -> Self {
+        let depth_texture = Texture2d::create_depth_texture(device, &config, "depth_texture");
+        Self {
+            kind : TargetKind::Surface { surface, config },
+            depth_texture,
+            reversed_z : false,
+        }
+    }
+
+    pub fn offscreen(device: &wgpu::Device, width : u32, height : u32, format : wgpu::TextureFormat) -> Self {
+        Self {
+            kind : TargetKind::Offscreen {
+                texture : Self::create_color_texture(device, width, height, format),
+                format,
+                width,
+                height,
+            },
+            depth_texture : Texture2d::create_depth_texture_with_size(device, width, height, "offscreen depth_texture"),
+            reversed_z : false,
+        }
+    }
+
+    fn create_color_texture(device: &wgpu::Device, width : u32, height : u32, format : wgpu::TextureFormat) -> wgpu::Texture {
+        device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("offscreen color texture"),
+            size: wgpu::Extent3d { width, height, depth_or_array_layers: 1 },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                | wgpu::TextureUsages::COPY_SRC
+                | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        })
+    }
+
+    pub fn format(&self) -> wgpu::TextureFormat {
+        match &self.kind {
+            TargetKind::Surface { config, .. } => config.format,
+            TargetKind::Offscreen { format, .. } => *format,
+        }
+    }
+
+    pub fn size(&self) -> (u32, u32) {
+        match &self.kind {
+            TargetKind::Surface { config, .. } => (config.width, config.height),
+            TargetKind::Offscreen { width, height, .. } => (*width, *height),
+        }
+    }
+
+    pub fn depth_view(&self) -> &wgpu::TextureView {
+        &self.depth_texture.view
+    }
+
+    pub fn resize(&mut self, device: &wgpu::Device, new_width : u32, new_height : u32) {
+        if new_width == 0 || new_height == 0 {
+            return;
+        }
+
+        match &mut self.kind {
+            TargetKind::Surface { surface, config } => {
+                config.width = new_width;
+                config.height = new_height;
+                surface.configure(device, config);
+            }
+            TargetKind::Offscreen { texture, format, width, height } => {
+                *texture = Self::create_color_texture(device, new_width, new_height, *format);
+                *width = new_width;
+                *height = new_height;
+            }
+        }
+        self.depth_texture = Texture2d::create_depth_texture_with_size(device, new_width, new_height, "depth_texture");
+    }
+
+    /// reconfigure the surface with its current size, e.g. after `SurfaceError::Lost`
+    pub fn reconfigure(&mut self, device: &wgpu::Device) {
+        let (width, height) = self.size();
+        self.resize(device, width, height);
+    }
+
+    pub fn acquire(&self) -> Result<Frame, wgpu::SurfaceError> {
+        match &self.kind {
+            TargetKind::Surface { surface, .. } => {
+                let output = surface.get_current_texture()?;
+                let view = output.texture.create_view(&wgpu::TextureViewDescriptor::default());
+                Ok(Frame { surface_texture : Some(output), view })
+            }
+            TargetKind::Offscreen { texture, .. } => Ok(Frame {
+                surface_texture : None,
+                view : texture.create_view(&wgpu::TextureViewDescriptor::default()),
+            }),
+        }
+    }
+
+    /// clear the depth buffer to 0 instead of 1, for reversed-Z projections
+    pub fn set_reversed_z(&mut self, reversed_z : bool) {
+        self.reversed_z = reversed_z;
+    }
+
+    /// depth of the far plane, the depth buffer is cleared to it
+    pub fn depth_clear_value(&self) -> f32 {
+        if self.reversed_z { 0.0 } else { 1.0 }
+    }
+
+    /// start a render pass that clears `frame` and the depth buffer
+    pub fn begin_render_pass<'a>(
+        &'a self,
+        encoder : &'a mut wgpu::CommandEncoder,
+        frame : &'a Frame,
+        clear_color : wgpu::Color ) -> wgpu::RenderPass<'a> {
+
+        encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Render Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &frame.view, // to where we rendering to
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(clear_color),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: &self.depth_texture.view,
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(self.depth_clear_value()),
+                    store: wgpu::StoreOp::Store,
+                }),
+                stencil_ops: None,
+            }),
+            occlusion_query_set: None,
+            timestamp_writes: None,
+        })
+    }
+
+    /// start a render pass that draws over the contents of `frame`, without depth buffer.
+    /// Used for text and other 2D overlays after the 3D pass.
+    pub fn begin_overlay_pass<'a>(
+        &'a self,
+        encoder : &'a mut wgpu::CommandEncoder,
+        frame : &'a Frame ) -> wgpu::RenderPass<'a> {
+
+        encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Overlay Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &frame.view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            occlusion_query_set: None,
+            timestamp_writes: None,
+        })
+    }
+
+    /// copy the offscreen color texture back to the cpu, blocks until the gpu is done
+    pub fn read_pixels(&self, device: &wgpu::Device, queue: &wgpu::Queue) -> Result<image::RgbaImage, WvtError> {
+        let TargetKind::Offscreen { texture, format, width, height } = &self.kind else {
+            return Err(WvtError::NotReadable);
+        };
+        let (width, height) = (*width, *height);
+
+        let swap_red_blue = match format {
+            wgpu::TextureFormat::Rgba8Unorm | wgpu::TextureFormat::Rgba8UnormSrgb => false,
+            wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb => true,
+            _ => return Err(WvtError::NotReadable),
+        };
+
+        // rows in the copy buffer have to be 256 byte aligned
+        let unpadded_bytes_per_row = 4 * width;
+        let align = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+        let padded_bytes_per_row = unpadded_bytes_per_row.div_ceil(align) * align;
+
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("offscreen readback buffer"),
+            size: (padded_bytes_per_row * height) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Readback Encoder"),
+        });
+        encoder.copy_texture_to_buffer(
+            wgpu::ImageCopyTexture {
+                aspect: wgpu::TextureAspect::All,
+                texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+            },
+            wgpu::ImageCopyBuffer {
+                buffer: &buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_bytes_per_row),
+                    rows_per_image: Some(height),
+                },
+            },
+            wgpu::Extent3d { width, height, depth_or_array_layers: 1 },
+        );
+        queue.submit(std::iter::once(encoder.finish()));
+
+        let slice = buffer.slice(..);
+        let (sender, receiver) = std::sync::mpsc::channel();
+        slice.map_async(wgpu::MapMode::Read, move |result| {
+            let _ = sender.send(result);
+        });
+        device.poll(wgpu::Maintain::Wait);
+        receiver.recv().map_err(|_| WvtError::ReadbackDisconnected)??;
+
+        let mut pixels = Vec::with_capacity((unpadded_bytes_per_row * height) as usize);
+        {
+            let data = slice.get_mapped_range();
+            for row in data.chunks(padded_bytes_per_row as usize) {
+                pixels.extend_from_slice(&row[..unpadded_bytes_per_row as usize]);
+            }
+        }
+        buffer.unmap();
+
+        if swap_red_blue {
+            pixels.chunks_mut(4).for_each(|p| p.swap(0, 2));
+        }
+
+        let actual = pixels.len();
+        image::RgbaImage::from_raw(width, height, pixels)
+            .ok_or(WvtError::ReadbackSize { expected : (unpadded_bytes_per_row * height) as usize, actual })
+    }
+}
@@ -1,3 +1,6 @@
 pub mod unlit_material;
 pub mod material_buffer;
-pub mod material_shader;
\ No newline at end of file
+pub mod light_buffer;
+pub mod material_shader;
+pub mod pipeline_cache;
+pub mod text_renderer;
\ No newline at end of file
@@ -0,0 +1,128 @@
+use glam::Vec3;
+use crate::buffers::uniform::{UniformBuffer, UniformBufferData};
+
+/// maximum number of point lights in the `Lights` uniform
+pub const MAX_POINT_LIGHTS : usize = 4;
+
+#[repr(C)]
+#[derive(Default, Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct DirectionalLight {
+    // direction the light travels in (world space)
+    pub direction : Vec3,
+    pub intensity : f32,
+    pub color : Vec3,
+    pub _padding : f32,
+}
+
+impl DirectionalLight {
+    pub fn new(direction : Vec3, color : Vec3, intensity : f32) -> Self {
+        Self {
+            direction : direction.normalize_or_zero(),
+            intensity,
+            color,
+            _padding : 0.0,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Default, Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct PointLight {
+    pub position : Vec3,
+    pub intensity : f32,
+    pub color : Vec3,
+    // distance at which the light has faded out completely
+    pub range : f32,
+}
+
+impl PointLight {
+    pub fn new(position : Vec3, color : Vec3, intensity : f32, range : f32) -> Self {
+        Self { position, intensity, color, range }
+    }
+}
+
+#[repr(C)]
+#[derive(Default, Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct Lights {
+    // Due to uniforms requiring 16 byte (4 float) spacing, fields are grouped per vec4
+    pub num_point_lights : u32,
+    pub _padding : [u32; 3],
+    pub ambient_color : Vec3,
+    pub ambient_intensity : f32,
+    pub directional : DirectionalLight,
+    pub point_lights : [PointLight; MAX_POINT_LIGHTS],
+}
+
+impl UniformBufferData for Lights {}
+
+impl Lights {
+    pub fn new(directional : DirectionalLight) -> Self {
+        Self {
+            ambient_color : Vec3::ONE,
+            ambient_intensity : 0.1,
+            directional,
+            ..Default::default()
+        }
+    }
+
+    /// active point lights
+    pub fn point_lights(&self) -> &[PointLight] {
+        &self.point_lights[..self.num_point_lights as usize]
+    }
+
+    /// adds a point light, returns false when all `MAX_POINT_LIGHTS` slots are taken
+    pub fn add_point_light(&mut self, light : PointLight) -> bool {
+        let count = self.num_point_lights as usize;
+        if count >= MAX_POINT_LIGHTS {
+            return false;
+        }
+        self.point_lights[count] = light;
+        self.num_point_lights += 1;
+        true
+    }
+
+    pub fn clear_point_lights(&mut self) {
+        self.num_point_lights = 0;
+    }
+}
+
+/// Scene lights uniform, shared by all lit renderers
+#[derive(Debug)]
+pub struct LightBuffer {
+    buffer : UniformBuffer<Lights>,
+}
+
+impl LightBuffer {
+    pub fn new(device: &wgpu::Device, lights: Lights, label: &str ) -> Self {
+        Self { buffer : UniformBuffer::new(device, lights, Some(label)) }
+    }
+
+    pub fn lights(&self) -> &Lights {
+        &self.buffer.data
+    }
+
+    pub fn lights_mut(&mut self) -> &mut Lights {
+        &mut self.buffer.data
+    }
+
+    pub fn binding_resource(&self) ->wgpu::BindingResource<'_> {
+        self.buffer.buffer.as_entire_binding()
+    }
+
+    pub fn update(&self, queue: &wgpu::Queue) {
+        self.buffer.update(queue);
+    }
+
+    pub fn entry_layout(binding_index : u32) -> wgpu::BindGroupLayoutEntry {
+        wgpu::BindGroupLayoutEntry {
+            binding: binding_index,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        }
+    }
+}
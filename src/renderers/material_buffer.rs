@@ -13,6 +13,9 @@ pub struct Material {
     pub diffuse_intensity : f32,
     pub specular_color :Vec3,
     pub specular_intensity : f32,
+    // specular exponent (Ns)
+    pub shininess : f32,
+    pub _padding : [f32; 3],
 }
 
 
@@ -25,6 +28,8 @@ impl Material {
             ambient_intensity :1.0,
             diffuse_intensity :1.0,
             specular_intensity :1.0,
+            shininess : 32.0,
+            _padding : [0.0; 3],
         }
     }
 }
@@ -41,10 +46,10 @@ impl MaterialBuffer {
     pub fn new(device: &wgpu::Device, material: Material, label: &str ) -> Self {
         let label = Some(label);
         let data = material;
-        Self { buffer : UniformBuffer::new(&device,data,label ),}
+        Self { buffer : UniformBuffer::new(device,data,label ),}
     }
 
-    pub fn binding_resource(&self) ->wgpu::BindingResource {
+    pub fn binding_resource(&self) ->wgpu::BindingResource<'_> {
         self.buffer.buffer.as_entire_binding()
     }
 
@@ -52,6 +57,11 @@ impl MaterialBuffer {
         self.buffer.update(queue);
     }
 
+    pub fn update_material(&mut self, queue: &wgpu::Queue, material: Material) {
+        self.buffer.data = material;
+        self.buffer.update(queue);
+    }
+
     pub fn entry_layout(binding_index : u32) -> wgpu::BindGroupLayoutEntry {
         wgpu::BindGroupLayoutEntry {
             binding: binding_index,
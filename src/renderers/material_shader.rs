@@ -1,79 +1,120 @@
+use std::sync::Arc;
+
 use glam::{Mat4, Vec3};
 
 use crate::{
+    assets::{AssetServer, Handle},
     buffers::{geometry::GeometryBuffer, uniform::UniformBuffer},
     camera::Camera, 
-    geometries::{ColorElement, NormalElement, PositionElement, TexCoordElement},
+    frustum::Frustum,
+    geometries::{ColorElement, NormalElement, PositionElement, TangentElement, TexCoordElement},
+    model,
+    scene::{NodeId, Scene},
     texture2d::Texture2d
 };
 
 use crate::renderers::material_buffer::MaterialBuffer;
+use super::light_buffer::LightBuffer;
 use super::material_buffer::Material;
+use super::pipeline_cache::{LayoutKind, PipelineCache, PipelineKey, ShaderKind};
 
 #[derive(Debug)]
 pub struct MaterialShader{
-    pub render_pipeline: wgpu::RenderPipeline,
+    pub render_pipeline: Arc<wgpu::RenderPipeline>,
     geometry_buffer : GeometryBuffer,
 
     model_matrix_buffer : UniformBuffer<Mat4>,
+    normal_matrix_buffer : UniformBuffer<Mat4>,
     vs_uniforms_bind_group : wgpu::BindGroup,
 
     material_buffer : MaterialBuffer,
     material_bind_group: wgpu::BindGroup,
 
+    texture_bind_group_layout : Arc<wgpu::BindGroupLayout>,
+    diffuse_texture : Handle<Texture2d>,
+    // only set for the normal mapped variant
+    normal_texture : Option<Handle<Texture2d>>,
     diffuse_bind_group: wgpu::BindGroup,
 
-    rot_angle : f32,
+    node : Option<NodeId>,
 }
 
 
 impl MaterialShader {
+    #[allow(clippy::too_many_arguments)]
     pub fn new( device: &wgpu::Device, 
-                queue: &wgpu::Queue,
+                pipelines : &mut PipelineCache,
                 format: wgpu::TextureFormat, 
                 positions : &[PositionElement],
                 normals : &[NormalElement],
                 colors : &[ColorElement],
                 tex_coords : &[TexCoordElement],
                 indices : &[u32],
-                camera : &Camera ) -> Self {
+                diffuse_texture : Handle<Texture2d>,
+                lights : &LightBuffer ) -> Self {
 
-        
-        let shader = device.create_shader_module(wgpu::include_wgsl!("./shaders/MaterialShader.wgsl"));
         let geometry_buffer = GeometryBuffer::new(
-            &device,
+            device,
             positions,
             normals,
             colors,
             tex_coords,
             indices);
 
+        Self::with_geometry(device, pipelines, format, geometry_buffer, diffuse_texture, None, lights)
+    }
+
+    /// variant that perturbs the normals with a tangent space normal map
+    #[allow(clippy::too_many_arguments)]
+    pub fn new_normal_mapped( device: &wgpu::Device, 
+                pipelines : &mut PipelineCache,
+                format: wgpu::TextureFormat, 
+                positions : &[PositionElement],
+                normals : &[NormalElement],
+                colors : &[ColorElement],
+                tex_coords : &[TexCoordElement],
+                tangents : &[TangentElement],
+                indices : &[u32],
+                diffuse_texture : Handle<Texture2d>,
+                normal_texture : Handle<Texture2d>,
+                lights : &LightBuffer ) -> Self {
+
+        let mut geometry_buffer = GeometryBuffer::new(
+            device,
+            positions,
+            normals,
+            colors,
+            tex_coords,
+            indices);
+        geometry_buffer.set_tangents(device, tangents);
+
+        Self::with_geometry(device, pipelines, format, geometry_buffer, diffuse_texture, Some(normal_texture), lights)
+    }
+
+    fn with_geometry( device: &wgpu::Device, 
+                pipelines : &mut PipelineCache,
+                format: wgpu::TextureFormat, 
+                geometry_buffer : GeometryBuffer,
+                diffuse_texture : Handle<Texture2d>,
+                normal_texture : Option<Handle<Texture2d>>,
+                lights : &LightBuffer ) -> Self {
+
         //
         // SETUP VERTEX UNIFORMS
         // 
         let model_matrix_buffer = UniformBuffer::new(
-            &device, 
+            device, 
             Mat4::IDENTITY,
             Some("Vertex model matrix uniform buffer"));
 
-                
-        let vs_uniforms_group_layout = 
-            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-                entries: &[
-                    wgpu::BindGroupLayoutEntry {
-                        binding: 0,
-                        visibility: wgpu::ShaderStages::VERTEX,
-                        ty: wgpu::BindingType::Buffer {
-                            ty: wgpu::BufferBindingType::Uniform,
-                            has_dynamic_offset: false,
-                            min_binding_size: None,
-                        },                    
-                        count: None,                        
-                    },
-                ],
-                label :Some( "material vs uniforms layout group"),
-            }
-        );
+        // inverse transpose of the model matrix, keeps normals perpendicular under non uniform scaling
+        let normal_matrix_buffer = UniformBuffer::new(
+            device, 
+            Mat4::IDENTITY,
+            Some("Vertex normal matrix uniform buffer"));
+
+
+        let vs_uniforms_group_layout = pipelines.bind_group_layout(device, LayoutKind::VertexUniforms);
 
         let vs_uniforms_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor{
             layout: &vs_uniforms_group_layout,
@@ -82,6 +123,10 @@ impl MaterialShader {
                     binding: 0,
                     resource: model_matrix_buffer.buffer.as_entire_binding(),
                 },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: normal_matrix_buffer.buffer.as_entire_binding(),
+                },
             ],
             label :Some( "material vs uniforms buffer group"),
         });
@@ -96,16 +141,12 @@ impl MaterialShader {
         material.diffuse_intensity = 2.0;
 
         let label = "Material shader material buffer";
-        let material_buffer = MaterialBuffer::new(&device,material, label);
+        let material_buffer = MaterialBuffer::new(device,material, label);
 
         let binding_index = 0;
+        let lights_binding_index = 1;
 
-        let material_group_layout = 
-            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-                entries: &[MaterialBuffer::entry_layout(binding_index)],
-                label :Some( "material uniform layout group"),
-            }
-        );
+        let material_group_layout = pipelines.bind_group_layout(device, LayoutKind::MaterialLights);
 
         let material_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor{
             layout: &material_group_layout,
@@ -113,7 +154,11 @@ impl MaterialShader {
                 wgpu::BindGroupEntry {
                     binding: binding_index,
                     resource: material_buffer.binding_resource(),
-                }
+                },
+                wgpu::BindGroupEntry {
+                    binding: lights_binding_index,
+                    resource: lights.binding_resource(),
+                },
             ],
             label :Some( "material uniform buffer group"),
         });
@@ -121,126 +166,207 @@ impl MaterialShader {
         //
         // TEXTURE BINDING GROUP
         // 
-        let texture_bytes = include_bytes!("../assets/test_texture.png");
-        let texture = Texture2d::from_bytes(&device, &queue, texture_bytes, "test_texture").expect("Texture");
-
-        let texture_bind_group_layout =
-            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-                entries: &[
-                    wgpu::BindGroupLayoutEntry {
-                        binding: 0,
-                        visibility: wgpu::ShaderStages::FRAGMENT,
-                        ty: wgpu::BindingType::Texture {
-                            multisampled: false,
-                            view_dimension: wgpu::TextureViewDimension::D2,
-                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
-                        },
-                        count: None,
-                    },
-                    wgpu::BindGroupLayoutEntry {
-                        binding: 1,
-                        visibility: wgpu::ShaderStages::FRAGMENT,
-                        // This should match the filterable field of the
-                        // corresponding Texture entry above.
-                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
-                        count: None,
-                    },
-                ],
-                label: Some("texture_bind_group_layout"),
-            });
-
-
-        let diffuse_bind_group = device.create_bind_group(
-            &wgpu::BindGroupDescriptor {
-                layout: &texture_bind_group_layout,
-                entries: &[
-                    wgpu::BindGroupEntry {
-                        binding: 0,
-                        resource: wgpu::BindingResource::TextureView(&texture.view),
-                    },
-                    wgpu::BindGroupEntry {
-                        binding: 1,
-                        resource: wgpu::BindingResource::Sampler(&texture.sampler),
-                    }
-                ],
-                label: Some("diffuse_bind_group"),
-            }
-        );
-
-        let render_pipeline_layout = device.create_pipeline_layout(
-            &wgpu::PipelineLayoutDescriptor {
-                label: Some("Render Pipeline Layout"),
-                bind_group_layouts: &[
-                    &vs_uniforms_group_layout,      // bind group 0
-                    &camera.bind_group_layout,      // bind group 1
-                    &texture_bind_group_layout,     // bind group 2
-                    &material_group_layout,    // bind group 3
-                ],    
-                push_constant_ranges: &[],
-            }
-        );
-
-
-        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: Some("render pipeline"),
-            layout: Some( &render_pipeline_layout),
-            vertex: wgpu::VertexState {
-                module: &shader,
-                entry_point: "material_vs",
-                buffers: &[
-                    PositionElement::desc::<0>(),
-                    ColorElement::desc::<1>(),
-                    TexCoordElement::desc::<2>(),
-                    NormalElement::desc::<3>() ],
-            },
-            fragment: Some(wgpu::FragmentState {
-                module: &shader,
-                //entry_point: "material_fs",
-                entry_point: "material_flat_fs",
-                targets: &[Some(wgpu::ColorTargetState {
-                    format: format,
-                    blend: Some(wgpu::BlendState::REPLACE),
-                    write_mask: wgpu::ColorWrites::ALL,
-                })],
-            }),
-            
-            primitive: Default::default(), 
-
-            depth_stencil: Some(wgpu::DepthStencilState {
-                format: Texture2d::DEPTH_FORMAT,
-                depth_write_enabled: true,
-                depth_compare: wgpu::CompareFunction::Less,
-                stencil: wgpu::StencilState::default(),
-                bias: wgpu::DepthBiasState::default(),
-            }),
-
-            multisample: Default::default(),
-            multiview: Default::default(),
-        });
+        let texture_layout = if normal_texture.is_some() { LayoutKind::NormalMappedTexture } else { LayoutKind::Texture };
+        let texture_bind_group_layout = pipelines.bind_group_layout(device, texture_layout);
+
+        let diffuse_bind_group = Self::create_texture_bind_group(
+            device,
+            &texture_bind_group_layout,
+            &diffuse_texture,
+            normal_texture.as_deref());
+
+        let mut vertex_layout = vec![
+            PositionElement::desc::<0>(),
+            ColorElement::desc::<1>(),
+            TexCoordElement::desc::<2>(),
+            NormalElement::desc::<3>() ];
+        let (vs_entry, fs_entry) = if normal_texture.is_some() {
+            vertex_layout.push(TangentElement::desc::<4>());
+            ("material_normal_mapped_vs", "material_normal_mapped_fs")
+        } else {
+            //("material_vs", "material_fs")
+            //("material_vs", "material_flat_fs")
+            ("material_vs", "material_lit_fs")
+        };
+
+        let render_pipeline = pipelines.pipeline(device, &PipelineKey::new(
+            ShaderKind::Material,
+            vs_entry,
+            fs_entry,
+            vec![
+                LayoutKind::VertexUniforms,     // bind group 0
+                LayoutKind::Camera,             // bind group 1
+                texture_layout,                 // bind group 2
+                LayoutKind::MaterialLights,     // bind group 3
+            ],
+            vertex_layout,
+            format));
 
         Self {
             render_pipeline,
             geometry_buffer,
 
             model_matrix_buffer,
+            normal_matrix_buffer,
             vs_uniforms_bind_group,
 
             material_buffer,
+            texture_bind_group_layout,
+            diffuse_texture,
+            normal_texture,
             diffuse_bind_group,
             material_bind_group,
 
-            rot_angle : 0.0,
+            node : None,
         }
     }
 
     
-    pub fn update(&mut self, queue: &wgpu::Queue) {
-        self.rot_angle += 0.5;
-        let trans = Mat4::from_translation(Vec3::new(0.0_f32, 0.0_f32, 3.0));
-        let rot = Mat4::from_axis_angle(Vec3::X, self.rot_angle.to_radians() );
-        let scale = Mat4::from_scale(Vec3::new(1.0,1.0,1.0));
-        self.model_matrix_buffer.data =  trans * rot * scale;
+    /// renderer for one mesh of a model, using the mesh material (if any)
+    #[allow(clippy::too_many_arguments)]
+    pub fn from_mesh(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        pipelines : &mut PipelineCache,
+        assets : &mut AssetServer,
+        format: wgpu::TextureFormat,
+        mesh : &model::Mesh,
+        material : Option<&model::Material>,
+        lights : &LightBuffer ) -> Self {
+
+        let geo = &mesh.geometry;
+        let diffuse_texture = material
+            .and_then(|material| material.diffuse_texture.clone())
+            .unwrap_or_else(|| assets.white_texture(device, queue));
+        let normal_texture = material
+            .and_then(|material| material.normal_texture.clone())
+            .filter(|_| !geo.tangents.is_empty());
+
+        let mut shader = match normal_texture {
+            Some(normal_texture) => Self::new_normal_mapped(
+                device,
+                pipelines,
+                format,
+                &geo.positions,
+                &geo.normals,
+                &geo.colors,
+                &geo.tex_coords,
+                &geo.tangents,
+                &geo.indices,
+                diffuse_texture,
+                normal_texture,
+                lights),
+            None => Self::new(
+                device,
+                pipelines,
+                format,
+                &geo.positions,
+                &geo.normals,
+                &geo.colors,
+                &geo.tex_coords,
+                &geo.indices,
+                diffuse_texture,
+                lights),
+        };
+
+        if let Some(material) = material {
+            shader.set_material(queue, material.params);
+        }
+        shader
+    }
+
+    fn create_texture_bind_group(
+        device: &wgpu::Device,
+        layout : &wgpu::BindGroupLayout,
+        texture : &Texture2d,
+        normal_texture : Option<&Texture2d> ) -> wgpu::BindGroup {
+
+        let mut entries = vec![
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(&texture.view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::Sampler(&texture.sampler),
+            }
+        ];
+        if let Some(normal_texture) = normal_texture {
+            entries.push(wgpu::BindGroupEntry {
+                binding: 2,
+                resource: wgpu::BindingResource::TextureView(&normal_texture.view),
+            });
+            entries.push(wgpu::BindGroupEntry {
+                binding: 3,
+                resource: wgpu::BindingResource::Sampler(&normal_texture.sampler),
+            });
+        }
+
+        device.create_bind_group(
+            &wgpu::BindGroupDescriptor {
+                layout,
+                entries: &entries,
+                label: Some("diffuse_bind_group"),
+            }
+        )
+    }
+
+    pub fn set_diffuse_texture(&mut self, device: &wgpu::Device, texture : Handle<Texture2d>) {
+        self.diffuse_bind_group = Self::create_texture_bind_group(
+            device,
+            &self.texture_bind_group_layout,
+            &texture,
+            self.normal_texture.as_deref());
+        self.diffuse_texture = texture;
+    }
+
+    pub fn diffuse_texture(&self) -> &Handle<Texture2d> {
+        &self.diffuse_texture
+    }
+
+    /// normal map, `None` unless created with `new_normal_mapped`
+    pub fn normal_texture(&self) -> Option<&Handle<Texture2d>> {
+        self.normal_texture.as_ref()
+    }
+
+    pub fn set_model_matrix(&mut self, queue: &wgpu::Queue, model_matrix : Mat4) {
+        self.model_matrix_buffer.data = model_matrix;
         self.model_matrix_buffer.update(queue);
+        self.normal_matrix_buffer.data = model_matrix.inverse().transpose();
+        self.normal_matrix_buffer.update(queue);
+    }
+
+    /// take the model matrix from `node` in `update_transform`
+    pub fn set_node(&mut self, node : Option<NodeId>) {
+        self.node = node;
+    }
+
+    pub fn node(&self) -> Option<NodeId> {
+        self.node
+    }
+
+    /// upload the world matrix of the attached node, if it changed
+    pub fn update_transform(&mut self, queue: &wgpu::Queue, scene : &Scene) {
+        if let Some(node) = self.node {
+            let world = scene.world_matrix(node);
+            if world != self.model_matrix_buffer.data {
+                self.set_model_matrix(queue, world);
+            }
+        }
+    }
+
+    pub fn set_material(&mut self, queue: &wgpu::Queue, material : Material) {
+        self.material_buffer.update_material(queue, material);
+    }
+
+    /// false if the geometry is completely outside `frustum` at the current model matrix.
+    /// The cheap sphere test runs first, the box test catches long thin objects.
+    pub fn is_visible(&self, frustum : &Frustum) -> bool {
+        let model_matrix = &self.model_matrix_buffer.data;
+        let sphere = self.geometry_buffer.bounding_sphere.map(|s| s.transformed(model_matrix));
+        let aabb = self.geometry_buffer.aabb.map(|b| b.transformed(model_matrix));
+        sphere.is_none_or(|s| frustum.intersects_sphere(&s))
+            && aabb.is_none_or(|b| frustum.intersects_aabb(&b))
     }
 
     pub fn draw<'a>( &'a self, render_pass: &mut wgpu::RenderPass<'a>, camera:&'a Camera ) {
@@ -257,6 +383,9 @@ impl MaterialShader {
         if let Some(normal_buffer) = &self.geometry_buffer.normal_buffer {
             render_pass.set_vertex_buffer(3, normal_buffer.slice(..));
         }
+        if let Some(tangent_buffer) = &self.geometry_buffer.tangent_buffer {
+            render_pass.set_vertex_buffer(4, tangent_buffer.slice(..));
+        }
 
         if let Some(index_buffer) = &self.geometry_buffer.index_buffer {
             render_pass.set_index_buffer(index_buffer.slice(..), wgpu::IndexFormat::Uint32);
@@ -0,0 +1,320 @@
+use std::{collections::HashMap, sync::Arc};
+
+use crate::texture2d::Texture2d;
+use super::light_buffer::LightBuffer;
+use super::material_buffer::MaterialBuffer;
+
+/// WGSL modules known to the cache, each compiled once
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum ShaderKind {
+    Material,
+    UnlitMaterial,
+    Text,
+}
+
+impl ShaderKind {
+    fn descriptor(self) -> wgpu::ShaderModuleDescriptor<'static> {
+        match self {
+            ShaderKind::Material => wgpu::include_wgsl!("./shaders/MaterialShader.wgsl"),
+            ShaderKind::UnlitMaterial => wgpu::include_wgsl!("./shaders/UnlitMaterialShader.wgsl"),
+            ShaderKind::Text => wgpu::include_wgsl!("./shaders/TextShader.wgsl"),
+        }
+    }
+}
+
+/// Bind group layouts shared between renderers
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum LayoutKind {
+    /// `CameraUniform`, also used by `Camera` for its bind group
+    Camera,
+    /// model matrix + one more vertex uniform (normal matrix, texture tiling)
+    VertexUniforms,
+    /// a single vertex uniform
+    VertexUniform,
+    /// 2D texture + filtering sampler
+    Texture,
+    /// diffuse texture + sampler, normal map + sampler
+    NormalMappedTexture,
+    /// material + scene lights
+    MaterialLights,
+    /// a single fragment uniform
+    FragmentUniform,
+}
+
+impl LayoutKind {
+    fn entries(self) -> Vec<wgpu::BindGroupLayoutEntry> {
+        match self {
+            // the fragment stage needs the eye position for lighting
+            LayoutKind::Camera => vec![uniform_entry(0, wgpu::ShaderStages::VERTEX_FRAGMENT)],
+            LayoutKind::VertexUniforms => vec![
+                uniform_entry(0, wgpu::ShaderStages::VERTEX),
+                uniform_entry(1, wgpu::ShaderStages::VERTEX),
+            ],
+            LayoutKind::VertexUniform => vec![uniform_entry(0, wgpu::ShaderStages::VERTEX)],
+            LayoutKind::Texture => texture_entries(0).to_vec(),
+            LayoutKind::NormalMappedTexture => [texture_entries(0), texture_entries(2)].concat(),
+            // the scene lights share the material group, wgpu only guarantees 4 bind groups
+            LayoutKind::MaterialLights => vec![
+                MaterialBuffer::entry_layout(0),
+                LightBuffer::entry_layout(1),
+            ],
+            LayoutKind::FragmentUniform => vec![uniform_entry(0, wgpu::ShaderStages::FRAGMENT)],
+        }
+    }
+}
+
+// filterable 2D texture at `binding`, its sampler at `binding + 1`
+fn texture_entries(binding : u32) -> [wgpu::BindGroupLayoutEntry; 2] {
+    [
+        wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                multisampled: false,
+                view_dimension: wgpu::TextureViewDimension::D2,
+                sample_type: wgpu::TextureSampleType::Float { filterable: true },
+            },
+            count: None,
+        },
+        wgpu::BindGroupLayoutEntry {
+            binding: binding + 1,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            // This should match the filterable field of the
+            // corresponding Texture entry above.
+            ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+            count: None,
+        },
+    ]
+}
+
+fn uniform_entry(binding : u32, visibility : wgpu::ShaderStages) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility,
+        ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Uniform,
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
+    }
+}
+
+/// Everything that makes two render pipelines differ
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct PipelineKey {
+    pub shader : ShaderKind,
+    pub vs_entry : &'static str,
+    pub fs_entry : &'static str,
+    /// layouts of bind group 0, 1, ...
+    pub bind_group_layouts : Vec<LayoutKind>,
+    pub vertex_layout : Vec<wgpu::VertexBufferLayout<'static>>,
+    pub format : wgpu::TextureFormat,
+    pub depth : Option<wgpu::DepthStencilState>,
+    pub blend : Option<wgpu::BlendState>,
+}
+
+impl PipelineKey {
+    /// opaque pipeline writing to `format` with the default depth buffer
+    pub fn new(
+        shader : ShaderKind,
+        vs_entry : &'static str,
+        fs_entry : &'static str,
+        bind_group_layouts : Vec<LayoutKind>,
+        vertex_layout : Vec<wgpu::VertexBufferLayout<'static>>,
+        format : wgpu::TextureFormat ) -> Self {
+        Self {
+            shader,
+            vs_entry,
+            fs_entry,
+            bind_group_layouts,
+            vertex_layout,
+            format,
+            depth : Some(wgpu::DepthStencilState {
+                format: Texture2d::DEPTH_FORMAT,
+                depth_write_enabled: true,
+                depth_compare: wgpu::CompareFunction::Less,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            blend : Some(wgpu::BlendState::REPLACE),
+        }
+    }
+}
+
+/// Hands out shared shader modules, bind group layouts and render pipelines,
+/// so renderers with the same setup don't each create their own.
+#[derive(Debug, Default)]
+pub struct PipelineCache {
+    shaders : HashMap<ShaderKind, Arc<wgpu::ShaderModule>>,
+    layouts : HashMap<LayoutKind, Arc<wgpu::BindGroupLayout>>,
+    pipelines : HashMap<PipelineKey, Arc<wgpu::RenderPipeline>>,
+    reversed_z : bool,
+}
+
+// the same ordering for a depth buffer that goes from 1 (near) to 0 (far)
+fn reversed_compare(compare : wgpu::CompareFunction) -> wgpu::CompareFunction {
+    use wgpu::CompareFunction::*;
+    match compare {
+        Less => Greater,
+        LessEqual => GreaterEqual,
+        Greater => Less,
+        GreaterEqual => LessEqual,
+        other => other,
+    }
+}
+
+impl PipelineCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn shader(&mut self, device : &wgpu::Device, kind : ShaderKind) -> Arc<wgpu::ShaderModule> {
+        self.shaders.entry(kind)
+            .or_insert_with(|| Arc::new(device.create_shader_module(kind.descriptor())))
+            .clone()
+    }
+
+    pub fn bind_group_layout(&mut self, device : &wgpu::Device, kind : LayoutKind) -> Arc<wgpu::BindGroupLayout> {
+        self.layouts.entry(kind)
+            .or_insert_with(|| Arc::new(device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &kind.entries(),
+                label: Some(&format!("{kind:?} bind group layout")),
+            })))
+            .clone()
+    }
+
+    /// flip the depth compare of all pipelines created from now on, for reversed-Z projections
+    pub fn set_reversed_z(&mut self, reversed_z : bool) {
+        self.reversed_z = reversed_z;
+    }
+
+    pub fn is_reversed_z(&self) -> bool {
+        self.reversed_z
+    }
+
+    pub fn pipeline(&mut self, device : &wgpu::Device, key : &PipelineKey) -> Arc<wgpu::RenderPipeline> {
+        let mut key = key.clone();
+        if let Some(depth) = key.depth.as_mut().filter(|_| self.reversed_z) {
+            depth.depth_compare = reversed_compare(depth.depth_compare);
+        }
+        let key = &key;
+        if let Some(pipeline) = self.pipelines.get(key) {
+            return pipeline.clone();
+        }
+
+        let shader = self.shader(device, key.shader);
+        let layouts : Vec<_> = key.bind_group_layouts.iter()
+            .map(|kind| self.bind_group_layout(device, *kind))
+            .collect();
+        let layout_refs : Vec<&wgpu::BindGroupLayout> = layouts.iter().map(|l| l.as_ref()).collect();
+
+        let render_pipeline_layout = device.create_pipeline_layout(
+            &wgpu::PipelineLayoutDescriptor {
+                label: Some("Render Pipeline Layout"),
+                bind_group_layouts: &layout_refs,
+                push_constant_ranges: &[],
+            }
+        );
+
+        let label = format!("{:?} {} render pipeline", key.shader, key.fs_entry);
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some(&label),
+            layout: Some(&render_pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: key.vs_entry,
+                buffers: &key.vertex_layout,
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: key.fs_entry,
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: key.format,
+                    blend: key.blend,
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: Default::default(),
+            depth_stencil: key.depth.clone(),
+            multisample: Default::default(),
+            multiview: Default::default(),
+        });
+
+        let pipeline = Arc::new(pipeline);
+        self.pipelines.insert(key.clone(), pipeline.clone());
+        pipeline
+    }
+
+    /// number of distinct pipelines created so far
+    pub fn num_pipelines(&self) -> usize {
+        self.pipelines.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometries::{ColorElement, NormalElement, PositionElement, TangentElement, TexCoordElement};
+
+    fn material_key() -> PipelineKey {
+        PipelineKey::new(
+            ShaderKind::Material,
+            "material_vs",
+            "material_lit_fs",
+            vec![LayoutKind::VertexUniforms, LayoutKind::Camera, LayoutKind::Texture, LayoutKind::MaterialLights],
+            vec![
+                PositionElement::desc::<0>(),
+                ColorElement::desc::<1>(),
+                TexCoordElement::desc::<2>(),
+                NormalElement::desc::<3>() ],
+            wgpu::TextureFormat::Rgba8UnormSrgb)
+    }
+
+    #[test]
+    fn equal_keys_share_a_pipeline() {
+        let Some(gpu) = crate::gpu::test_gpu() else { return };
+        let mut cache = PipelineCache::new();
+        let a = cache.pipeline(&gpu.device, &material_key());
+        let b = cache.pipeline(&gpu.device, &material_key());
+        assert!(Arc::ptr_eq(&a, &b));
+        assert_eq!(cache.num_pipelines(), 1);
+        // shaders and layouts are shared as well
+        assert!(Arc::ptr_eq(
+            &cache.bind_group_layout(&gpu.device, LayoutKind::Camera),
+            &cache.bind_group_layout(&gpu.device, LayoutKind::Camera)));
+    }
+
+    #[test]
+    fn differing_keys_get_their_own_pipeline() {
+        let Some(gpu) = crate::gpu::test_gpu() else { return };
+        let mut cache = PipelineCache::new();
+        let base = cache.pipeline(&gpu.device, &material_key());
+
+        let format = PipelineKey { format : wgpu::TextureFormat::Bgra8UnormSrgb, ..material_key() };
+        let depth = PipelineKey { depth : None, ..material_key() };
+        let blend = PipelineKey { blend : Some(wgpu::BlendState::ALPHA_BLENDING), ..material_key() };
+        // an extra buffer the shader doesn't read still changes the layout
+        let mut vertex_layout = material_key();
+        vertex_layout.vertex_layout.push(TangentElement::desc::<4>());
+
+        for (i, key) in [format, depth, blend, vertex_layout].iter().enumerate() {
+            let pipeline = cache.pipeline(&gpu.device, key);
+            assert!(!Arc::ptr_eq(&base, &pipeline), "{key:?}");
+            assert_eq!(cache.num_pipelines(), i + 2);
+        }
+    }
+
+    #[test]
+    fn reversed_z_flips_the_depth_compare() {
+        let Some(gpu) = crate::gpu::test_gpu() else { return };
+        let mut cache = PipelineCache::new();
+        let forward = cache.pipeline(&gpu.device, &material_key());
+        cache.set_reversed_z(true);
+        let reversed = cache.pipeline(&gpu.device, &material_key());
+        assert!(!Arc::ptr_eq(&forward, &reversed));
+        assert!(Arc::ptr_eq(&reversed, &cache.pipeline(&gpu.device, &material_key())));
+        assert_eq!(reversed_compare(wgpu::CompareFunction::Less), wgpu::CompareFunction::Greater);
+        assert_eq!(reversed_compare(wgpu::CompareFunction::Always), wgpu::CompareFunction::Always);
+    }
+}
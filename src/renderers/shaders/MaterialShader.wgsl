@@ -10,6 +10,25 @@ struct VsOutput {
     @location(1) color : vec4f,
     @location(2) texcoord : vec2f,
     @location(3) normal : vec3f,
+    @location(4) world_position : vec3f,
+}
+
+struct NormalMappedVsInput {
+    @location(0) position : vec3f,
+    @location(1) color : vec4f,
+    @location(2) texcoord : vec2f,
+    @location(3) normal : vec3f,
+    // xyz along +U, w bitangent handedness
+    @location(4) tangent : vec4f,
+}
+
+struct NormalMappedVsOutput {
+    @builtin(position) position : vec4f,
+    @location(1) color : vec4f,
+    @location(2) texcoord : vec2f,
+    @location(3) normal : vec3f,
+    @location(4) world_position : vec3f,
+    @location(5) tangent : vec4f,
 }
 
 struct Material {
@@ -19,13 +38,53 @@ struct Material {
     @location(3) diffuse_intensity : f32,
     @location(4) specular_color : vec3f,
     @location(5) specular_intensity : f32,
+    @location(6) shininess : f32,
+}
+
+struct DirectionalLight {
+    direction : vec3f,
+    intensity : f32,
+    color : vec3f,
+}
+
+struct PointLight {
+    position : vec3f,
+    intensity : f32,
+    color : vec3f,
+    range : f32,
+}
+
+const MAX_POINT_LIGHTS : u32 = 4u;
+
+struct Lights {
+    num_point_lights : u32,
+    ambient_color : vec3f,
+    ambient_intensity : f32,
+    directional : DirectionalLight,
+    point_lights : array<PointLight, MAX_POINT_LIGHTS>,
 }
 
 @group(0) @binding(0)
 var<uniform> model_matrix : mat4x4f;
 
+@group(0) @binding(1)
+var<uniform> normal_matrix : mat4x4f;
+
+struct Camera {
+    view : mat4x4f,
+    projection : mat4x4f,
+    view_projection : mat4x4f,
+    inverse_view : mat4x4f,
+    inverse_projection : mat4x4f,
+    inverse_view_projection : mat4x4f,
+    // world space
+    eye : vec3f,
+    // in pixels
+    viewport : vec2f,
+}
+
 @group(1) @binding(0)
-var<uniform> view_projection : mat4x4f;
+var<uniform> camera : Camera;
 
 @group(2) @binding(0)
 var t_diffuse : texture_2d<f32>;
@@ -33,22 +92,62 @@ var t_diffuse : texture_2d<f32>;
 @group(2) @binding(1)
 var s_diffuse : sampler;
 
+// only bound for the normal mapped pipeline
+@group(2) @binding(2)
+var t_normal : texture_2d<f32>;
+
+@group(2) @binding(3)
+var s_normal : sampler;
+
 @group(3) @binding(0)
 var<uniform> material : Material;
 
+@group(3) @binding(1)
+var<uniform> lights : Lights;
+
 
 @vertex
 fn material_vs( in : VsInput ) -> VsOutput {
     var out : VsOutput;
     // COLUMN MAJOR LH PROJECTION * VIEW * MODEL
-    out.position =   view_projection * model_matrix * vec4f(in.position, 1.0);
+    let world_position = model_matrix * vec4f(in.position, 1.0);
+    out.position =   camera.view_projection * world_position;
     //out.position =   model_matrix * vec4f(in.position, 1.0);
     out.color = in.color;
     out.texcoord = in.texcoord;
-    out.normal = in.normal;
+    out.normal = (normal_matrix * vec4f(in.normal, 0.0)).xyz;
+    out.world_position = world_position.xyz;
     return out;
 }
 
+@vertex
+fn material_normal_mapped_vs( in : NormalMappedVsInput ) -> NormalMappedVsOutput {
+    var out : NormalMappedVsOutput;
+    let world_position = model_matrix * vec4f(in.position, 1.0);
+    out.position =   camera.view_projection * world_position;
+    out.color = in.color;
+    out.texcoord = in.texcoord;
+    out.normal = (normal_matrix * vec4f(in.normal, 0.0)).xyz;
+    // tangents lie in the surface, so they transform like positions
+    out.tangent = vec4f((model_matrix * vec4f(in.tangent.xyz, 0.0)).xyz, in.tangent.w);
+    out.world_position = world_position.xyz;
+    return out;
+}
+
+// diffuse + specular contribution of one light, `light_dir` points towards the light
+fn blinn_phong( normal : vec3f, view_dir : vec3f, light_dir : vec3f, radiance : vec3f, albedo : vec3f ) -> vec3f {
+    let n_dot_l = max(dot(normal, light_dir), 0.0);
+    let diffuse = albedo * material.diffuse_color * material.diffuse_intensity * n_dot_l;
+
+    let half_dir = normalize(light_dir + view_dir);
+    var specular = vec3f(0.0);
+    if (n_dot_l > 0.0) {
+        specular = material.specular_color * material.specular_intensity
+            * pow(max(dot(normal, half_dir), 0.0), max(material.shininess, 1.0));
+    }
+    return (diffuse + specular) * radiance;
+}
+
 @fragment
 fn material_fs( in: VsOutput ) -> @location(0) vec4f {
     //return vec4f(in.texcoord.x,in.texcoord.y,0.0,1.0);
@@ -62,7 +161,55 @@ fn material_fs( in: VsOutput ) -> @location(0) vec4f {
 fn material_flat_fs( in: VsOutput ) -> @location(0) vec4f {
     //return vec4f(in.texcoord.x,in.texcoord.y,0.0,1.0);
     //return textureSample(t_diffuse, s_diffuse, in.texcoord) * vec4f(abs( in.normal ), 1.0);
-    return vec4f( material.diffuse_color * material.diffuse_intensity, 1.0);
+    let diffuse = textureSample(t_diffuse, s_diffuse, in.texcoord);
+    return vec4f( diffuse.rgb * material.diffuse_color * material.diffuse_intensity, diffuse.a);
     //return vec4f(abs( in.normal ), 1.0);
 //    return textureSample(t_diffuse, s_diffuse, in.texcoord);
 }
+
+// ambient + all scene lights for a surface point
+fn shade( albedo : vec3f, normal : vec3f, world_position : vec3f ) -> vec3f {
+    let view_dir = normalize(camera.eye - world_position);
+
+    var color = albedo * material.ambient_color * material.ambient_intensity
+        * lights.ambient_color * lights.ambient_intensity;
+
+    let directional = lights.directional;
+    color += blinn_phong(normal, view_dir, -normalize(directional.direction),
+        directional.color * directional.intensity, albedo);
+
+    for (var i = 0u; i < min(lights.num_point_lights, MAX_POINT_LIGHTS); i++) {
+        let light = lights.point_lights[i];
+        let to_light = light.position - world_position;
+        let distance = length(to_light);
+        // smooth falloff reaching zero at the light range
+        let falloff = clamp(1.0 - pow(distance / max(light.range, 0.0001), 4.0), 0.0, 1.0);
+        let attenuation = falloff * falloff / (distance * distance + 1.0);
+        color += blinn_phong(normal, view_dir, to_light / max(distance, 0.0001),
+            light.color * light.intensity * attenuation, albedo);
+    }
+
+    return color;
+}
+
+@fragment
+fn material_lit_fs( in: VsOutput ) -> @location(0) vec4f {
+    let texel = textureSample(t_diffuse, s_diffuse, in.texcoord);
+    return vec4f(shade(texel.rgb, normalize(in.normal), in.world_position), texel.a);
+}
+
+@fragment
+fn material_normal_mapped_fs( in: NormalMappedVsOutput ) -> @location(0) vec4f {
+    let texel = textureSample(t_diffuse, s_diffuse, in.texcoord);
+
+    // re-orthogonalize the interpolated tangent frame
+    let n = normalize(in.normal);
+    let t = normalize(in.tangent.xyz - n * dot(n, in.tangent.xyz));
+    let b = cross(n, t) * in.tangent.w;
+
+    // normal maps are authored with V up, our texture coordinates have V down
+    let sampled = textureSample(t_normal, s_normal, in.texcoord).xyz * 2.0 - 1.0;
+    let normal = normalize(t * sampled.x - b * sampled.y + n * sampled.z);
+
+    return vec4f(shade(texel.rgb, normal, in.world_position), texel.a);
+}
@@ -0,0 +1,36 @@
+struct VsInput {
+    // window pixels, y down
+    @location(0) position : vec2f,
+    @location(1) texcoord : vec2f,
+    @location(2) color : vec4f,
+}
+
+struct VsOutput {
+    @builtin(position) position : vec4f,
+    @location(0) texcoord : vec2f,
+    @location(1) color : vec4f,
+}
+
+// pixels to clip space
+@group(0) @binding(0)
+var<uniform> projection : mat4x4f;
+
+@group(1) @binding(0)
+var t_atlas : texture_2d<f32>;
+
+@group(1) @binding(1)
+var s_atlas : sampler;
+
+@vertex
+fn text_vs( in : VsInput ) -> VsOutput {
+    var out : VsOutput;
+    out.position = projection * vec4f(in.position, 0.0, 1.0);
+    out.texcoord = in.texcoord;
+    out.color = in.color;
+    return out;
+}
+
+@fragment
+fn text_fs( in : VsOutput ) -> @location(0) vec4f {
+    return textureSample(t_atlas, s_atlas, in.texcoord) * in.color;
+}
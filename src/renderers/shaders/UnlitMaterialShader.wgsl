@@ -4,10 +4,19 @@ struct VsInput {
     @location(2) texcoord : vec2f,
 }
 
+struct InstanceInput {
+    @location(3) model_matrix_0 : vec4f,
+    @location(4) model_matrix_1 : vec4f,
+    @location(5) model_matrix_2 : vec4f,
+    @location(6) model_matrix_3 : vec4f,
+    @location(7) color : vec4f,
+}
+
 struct VsOutput {
     @builtin(position) position : vec4f,
     @location(1) color : vec4f,
     @location(2) texcoord : vec2f,
+    @location(3) tint : vec4f,
 }
 
 
@@ -17,8 +26,21 @@ var<uniform> model_matrix : mat4x4f;
 @group(0) @binding(1)
 var<uniform> texture_tiling : vec2f;
 
+struct Camera {
+    view : mat4x4f,
+    projection : mat4x4f,
+    view_projection : mat4x4f,
+    inverse_view : mat4x4f,
+    inverse_projection : mat4x4f,
+    inverse_view_projection : mat4x4f,
+    // world space
+    eye : vec3f,
+    // in pixels
+    viewport : vec2f,
+}
+
 @group(1) @binding(0)
-var<uniform> view_projection : mat4x4f;
+var<uniform> camera : Camera;
 
 
 @group(2) @binding(0)
@@ -31,13 +53,19 @@ var s_diffuse : sampler;
 var<uniform> diffuse_color : vec4f;
 
 @vertex
-fn unlit_material_vs( in : VsInput ) -> VsOutput {
+fn unlit_material_vs( in : VsInput, instance : InstanceInput ) -> VsOutput {
     var out : VsOutput;
-    // COLUMN MAJOR LH PROJECTION * VIEW * MODEL
-    out.position =   view_projection * model_matrix * vec4f(in.position, 1.0);
+    let instance_matrix = mat4x4f(
+        instance.model_matrix_0,
+        instance.model_matrix_1,
+        instance.model_matrix_2,
+        instance.model_matrix_3);
+    // COLUMN MAJOR LH PROJECTION * VIEW * MODEL * INSTANCE
+    out.position =   camera.view_projection * model_matrix * instance_matrix * vec4f(in.position, 1.0);
     //out.position =   model_matrix * vec4f(in.position, 1.0);
     out.color = in.color;
     out.texcoord = in.texcoord * texture_tiling;
+    out.tint = instance.color;
     return out;
 }
 
@@ -45,6 +73,6 @@ fn unlit_material_vs( in : VsInput ) -> VsOutput {
 @fragment
 fn unlit_material_fs( in: VsOutput ) -> @location(0) vec4f {
     //return vec4f(in.texcoord.x,in.texcoord.y,0.0,1.0);
-    return textureSample(t_diffuse, s_diffuse, in.texcoord) * diffuse_color;
+    return textureSample(t_diffuse, s_diffuse, in.texcoord) * diffuse_color * in.tint;
 //    return textureSample(t_diffuse, s_diffuse, in.texcoord);
 }
@@ -0,0 +1,222 @@
+use std::sync::Arc;
+
+use glam::{Mat4, Vec2, Vec4};
+use crate::{
+    assets::Handle,
+    buffers::uniform::UniformBuffer,
+    text::BitmapFont,
+    texture2d::{SamplerPreset, Texture2d},
+};
+use super::pipeline_cache::{LayoutKind, PipelineCache, PipelineKey, ShaderKind};
+
+/// Corner of a glyph quad, in window pixels
+#[repr(C)]
+#[derive(Default, Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct TextVertex {
+    pub position : [f32; 2],
+    pub tex_coord : [f32; 2],
+    pub color : [f32; 4],
+}
+
+impl TextVertex {
+    const ATTRIBUTES : [wgpu::VertexAttribute; 3] = wgpu::vertex_attr_array![0 => Float32x2, 1 => Float32x2, 2 => Float32x4];
+
+    pub fn desc() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<Self>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &Self::ATTRIBUTES,
+        }
+    }
+}
+
+/// Screen space text on top of the 3D scene. Text is queued during the frame,
+/// `prepare` uploads all glyph quads at once and `draw` renders them in a single draw call.
+/// Meant for a render pass without depth buffer, see `RenderTarget::begin_overlay_pass`.
+#[derive(Debug)]
+pub struct TextRenderer {
+    pub render_pipeline : Arc<wgpu::RenderPipeline>,
+    font : BitmapFont,
+    atlas : Handle<Texture2d>,
+    atlas_bind_group : wgpu::BindGroup,
+    projection_buffer : UniformBuffer<Mat4>,
+    projection_bind_group : wgpu::BindGroup,
+
+    vertices : Vec<TextVertex>,
+    vertex_buffer : wgpu::Buffer,
+    index_buffer : wgpu::Buffer,
+    // glyphs that fit into the gpu buffers
+    capacity : usize,
+    num_indices : u32,
+}
+
+impl TextRenderer {
+    pub fn new(
+        device : &wgpu::Device,
+        queue : &wgpu::Queue,
+        pipelines : &mut PipelineCache,
+        format : wgpu::TextureFormat ) -> Self {
+
+        let font = BitmapFont::new();
+        // nearest keeps the font pixels sharp at any integer scale
+        let atlas = Texture2d::from_image_with_sampler(
+            device,
+            queue,
+            &font.atlas().clone().into(),
+            Some("font atlas"),
+            SamplerPreset::Nearest)
+            .expect("font atlas is a valid image");
+        let atlas = Handle::new(atlas);
+
+        let texture_layout = pipelines.bind_group_layout(device, LayoutKind::Texture);
+        let atlas_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &texture_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&atlas.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&atlas.sampler),
+                },
+            ],
+            label: Some("font atlas bind group"),
+        });
+
+        let projection_buffer = UniformBuffer::new(device, Mat4::IDENTITY, Some("text projection uniform buffer"));
+        let projection_layout = pipelines.bind_group_layout(device, LayoutKind::VertexUniform);
+        let projection_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &projection_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: projection_buffer.buffer.as_entire_binding(),
+                },
+            ],
+            label: Some("text projection bind group"),
+        });
+
+        let mut key = PipelineKey::new(
+            ShaderKind::Text,
+            "text_vs",
+            "text_fs",
+            vec![
+                LayoutKind::VertexUniform,  // bind group 0
+                LayoutKind::Texture,        // bind group 1
+            ],
+            vec![TextVertex::desc()],
+            format);
+        // drawn over the finished scene
+        key.depth = None;
+        key.blend = Some(wgpu::BlendState::ALPHA_BLENDING);
+        let render_pipeline = pipelines.pipeline(device, &key);
+
+        let capacity = 256;
+        let (vertex_buffer, index_buffer) = Self::create_buffers(device, capacity);
+
+        Self {
+            render_pipeline,
+            font,
+            atlas,
+            atlas_bind_group,
+            projection_buffer,
+            projection_bind_group,
+            vertices : Vec::new(),
+            vertex_buffer,
+            index_buffer,
+            capacity,
+            num_indices : 0,
+        }
+    }
+
+    // vertex buffer and a fixed index buffer for `capacity` glyph quads
+    fn create_buffers(device : &wgpu::Device, capacity : usize) -> (wgpu::Buffer, wgpu::Buffer) {
+        let vertex_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Text Vertex Buffer"),
+            size: (4 * capacity * std::mem::size_of::<TextVertex>()) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let indices : Vec<u32> = (0..capacity as u32)
+            .flat_map(|quad| [0, 1, 2, 2, 1, 3].map(|i| 4 * quad + i))
+            .collect();
+        let index_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Text Index Buffer"),
+            size: std::mem::size_of_val(indices.as_slice()) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::INDEX,
+            mapped_at_creation: true,
+        });
+        index_buffer.slice(..).get_mapped_range_mut().copy_from_slice(bytemuck::cast_slice(&indices));
+        index_buffer.unmap();
+
+        (vertex_buffer, index_buffer)
+    }
+
+    pub fn font(&self) -> &BitmapFont {
+        &self.font
+    }
+
+    pub fn atlas(&self) -> &Handle<Texture2d> {
+        &self.atlas
+    }
+
+    /// size of `text` in pixels when queued with `scale` pixels per font pixel
+    pub fn measure(&self, text : &str, scale : f32) -> Vec2 {
+        self.font.measure(text, scale)
+    }
+
+    /// add `text` with its top left corner at `position` in window pixels
+    pub fn queue(&mut self, text : &str, position : Vec2, scale : f32, color : Vec4) {
+        let color = color.to_array();
+        for quad in self.font.layout(text, position, scale) {
+            let corners = [
+                (Vec2::new(quad.min.x, quad.min.y), Vec2::new(quad.uv_min.x, quad.uv_min.y)),
+                (Vec2::new(quad.max.x, quad.min.y), Vec2::new(quad.uv_max.x, quad.uv_min.y)),
+                (Vec2::new(quad.min.x, quad.max.y), Vec2::new(quad.uv_min.x, quad.uv_max.y)),
+                (Vec2::new(quad.max.x, quad.max.y), Vec2::new(quad.uv_max.x, quad.uv_max.y)),
+            ];
+            self.vertices.extend(corners.map(|(position, tex_coord)| TextVertex {
+                position : position.to_array(),
+                tex_coord : tex_coord.to_array(),
+                color,
+            }));
+        }
+    }
+
+    /// number of glyphs queued since the last `prepare`
+    pub fn num_queued(&self) -> usize {
+        self.vertices.len() / 4
+    }
+
+    /// upload the queued text for a `width` x `height` pixel target and start a new batch
+    pub fn prepare(&mut self, device : &wgpu::Device, queue : &wgpu::Queue, width : u32, height : u32) {
+        let projection = Mat4::orthographic_lh(0.0, width as f32, height as f32, 0.0, 0.0, 1.0);
+        if projection != self.projection_buffer.data {
+            self.projection_buffer.data = projection;
+            self.projection_buffer.update(queue);
+        }
+
+        let num_glyphs = self.num_queued();
+        if num_glyphs > self.capacity {
+            self.capacity = num_glyphs.next_power_of_two();
+            (self.vertex_buffer, self.index_buffer) = Self::create_buffers(device, self.capacity);
+        }
+        queue.write_buffer(&self.vertex_buffer, 0, bytemuck::cast_slice(&self.vertices));
+        self.num_indices = 6 * num_glyphs as u32;
+        self.vertices.clear();
+    }
+
+    pub fn draw<'a>(&'a self, render_pass : &mut wgpu::RenderPass<'a>) {
+        if self.num_indices == 0 {
+            return;
+        }
+        render_pass.set_pipeline(&self.render_pipeline);
+        render_pass.set_bind_group(0, &self.projection_bind_group, &[]);
+        render_pass.set_bind_group(1, &self.atlas_bind_group, &[]);
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        render_pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+        render_pass.draw_indexed(0..self.num_indices, 0, 0..1);
+    }
+}
@@ -1,92 +1,75 @@
-use glam::{Mat4, Vec2, Vec3, Vec4};
+use std::sync::Arc;
+
+use glam::{Mat4, Vec2, Vec4};
 use crate::{
-    buffers::{geometry::GeometryBuffer, uniform::UniformBuffer},
+    assets::Handle,
+    buffers::{geometry::GeometryBuffer, instance::InstanceBuffer, uniform::UniformBuffer},
     camera::Camera, 
-    geometries::{ColorElement, PositionElement, TexCoordElement},
-    texture2d::{self, Texture2d}
+    geometries::{ColorElement, InstanceElement, PositionElement, TexCoordElement},
+    scene::{NodeId, Scene},
+    texture2d::Texture2d
 };
+use super::pipeline_cache::{LayoutKind, PipelineCache, PipelineKey, ShaderKind};
 
+/// Unlit textured renderer, draws all its instances in a single draw call.
+/// Each instance is transformed by the renderer model matrix and then its own.
 #[derive(Debug)]
 pub struct UnlitMaterial{
-    pub render_pipeline: wgpu::RenderPipeline,
+    pub render_pipeline: Arc<wgpu::RenderPipeline>,
     geometry_buffer : GeometryBuffer,
+    instance_buffer : InstanceBuffer,
 
     texture_tiling_buffer : UniformBuffer<Vec2>,
     model_matrix_buffer : UniformBuffer<Mat4>,
     vs_uniforms_bind_group : wgpu::BindGroup,
 
     diffuse_color_buffer : UniformBuffer<Vec4>,
+    texture_bind_group_layout : Arc<wgpu::BindGroupLayout>,
+    diffuse_texture : Handle<Texture2d>,
     diffuse_bind_group: wgpu::BindGroup,
     diffuse_color_bind_group: wgpu::BindGroup,
 
-    rot_angle : f32,
-    instance : usize,
+    node : Option<NodeId>,
 }
 
 
 impl UnlitMaterial {
+    #[allow(clippy::too_many_arguments)]
     pub fn new( device: &wgpu::Device, 
-                queue: &wgpu::Queue,
+                pipelines : &mut PipelineCache,
                 format: wgpu::TextureFormat, 
                 positions : &[PositionElement],
                 colors : &[ColorElement],
                 tex_coords : &[TexCoordElement],
                 indices : &[u32],
-                camera : &Camera,
-                instance : usize ) -> Self {
+                diffuse_texture : Handle<Texture2d>,
+                instances : &[InstanceElement] ) -> Self {
 
-        
-        let shader = device.create_shader_module(wgpu::include_wgsl!("./shaders/UnlitMaterialShader.wgsl"));
         let geometry_buffer = GeometryBuffer::new(
-            &device,
+            device,
             positions,
             &[],
             colors,
             tex_coords,
             indices);
+        let instance_buffer = InstanceBuffer::new(device, instances);
 
         //
         // SETUP VERTEX UNIFORMS
         // 
         let texture_tiling_buffer = UniformBuffer::new(
-            &device, 
+            device, 
             Vec2::new(1.0,1.0), 
             Some("Vertex texture tiling uniform buffer"));
 
 
         let model_matrix_buffer = UniformBuffer::new(
-            &device, 
+            device, 
             Mat4::IDENTITY,
             Some("Vertex model matrix uniform buffer"));
 
-                
-        let vs_uniforms_group_layout = 
-            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-                entries: &[
-                    wgpu::BindGroupLayoutEntry {
-                        binding: 0,
-                        visibility: wgpu::ShaderStages::VERTEX,
-                        ty: wgpu::BindingType::Buffer {
-                            ty: wgpu::BufferBindingType::Uniform,
-                            has_dynamic_offset: false,
-                            min_binding_size: None,
-                        },                    
-                        count: None,                        
-                    },
-                    wgpu::BindGroupLayoutEntry {
-                        binding: 1,
-                        visibility: wgpu::ShaderStages::VERTEX,
-                        ty: wgpu::BindingType::Buffer {
-                            ty: wgpu::BufferBindingType::Uniform,
-                            has_dynamic_offset: false,
-                            min_binding_size: None,
-                        },                    
-                        count: None,                        
-                    },
-                ],
-                label :Some( "Unlit material vs uniforms layout group"),
-            }
-        );
+
+        let vs_uniforms_group_layout = pipelines.bind_group_layout(device, LayoutKind::VertexUniforms);
 
         let vs_uniforms_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor{
             layout: &vs_uniforms_group_layout,
@@ -107,28 +90,12 @@ impl UnlitMaterial {
         // DIFFUSE COLOR UNIFORM
         // 
         let diffuse_color_buffer = UniformBuffer::new(
-            &device, 
+            device, 
             Vec4::new(1.0,1.0, 1.0,1.0), 
             Some("diffuse color buffer"));
 
 
-        let diffuse_color_group_layout = 
-            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-                entries: &[
-                    wgpu::BindGroupLayoutEntry {
-                        binding: 0,
-                        visibility: wgpu::ShaderStages::FRAGMENT,
-                        ty: wgpu::BindingType::Buffer {
-                            ty: wgpu::BufferBindingType::Uniform,
-                            has_dynamic_offset: false,
-                            min_binding_size: None,
-                        },                    
-                        count: None,                        
-                    }
-                ],
-                label :Some( "Unlit material diffuse color uniform layout group"),
-            }
-        );
+        let diffuse_color_group_layout = pipelines.bind_group_layout(device, LayoutKind::FragmentUniform);
 
         let diffuse_color_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor{
             layout: &diffuse_color_group_layout,
@@ -144,38 +111,50 @@ impl UnlitMaterial {
         //
         // TEXTURE BINDING GROUP
         // 
-        let texture_bytes = include_bytes!("../assets/test_texture.png");
-        let texture = Texture2d::from_bytes(&device, &queue, texture_bytes, "test_texture").expect("Texture");
+        let texture_bind_group_layout = pipelines.bind_group_layout(device, LayoutKind::Texture);
+
+        let diffuse_bind_group = Self::create_texture_bind_group(device, &texture_bind_group_layout, &diffuse_texture);
+
+        let render_pipeline = pipelines.pipeline(device, &PipelineKey::new(
+            ShaderKind::UnlitMaterial,
+            "unlit_material_vs",
+            "unlit_material_fs",
+            vec![
+                LayoutKind::VertexUniforms,     // bind group 0
+                LayoutKind::Camera,             // bind group 1
+                LayoutKind::Texture,            // bind group 2
+                LayoutKind::FragmentUniform,    // bind group 3
+            ],
+            vec![
+                PositionElement::desc::<0>(),
+                ColorElement::desc::<1>(),
+                TexCoordElement::desc::<2>(),
+                InstanceElement::desc::<3>() ],
+            format));
 
-        let texture_bind_group_layout =
-            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-                entries: &[
-                    wgpu::BindGroupLayoutEntry {
-                        binding: 0,
-                        visibility: wgpu::ShaderStages::FRAGMENT,
-                        ty: wgpu::BindingType::Texture {
-                            multisampled: false,
-                            view_dimension: wgpu::TextureViewDimension::D2,
-                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
-                        },
-                        count: None,
-                    },
-                    wgpu::BindGroupLayoutEntry {
-                        binding: 1,
-                        visibility: wgpu::ShaderStages::FRAGMENT,
-                        // This should match the filterable field of the
-                        // corresponding Texture entry above.
-                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
-                        count: None,
-                    },
-                ],
-                label: Some("texture_bind_group_layout"),
-            });
+        Self {
+            render_pipeline,
+            geometry_buffer,
+            instance_buffer,
+
+            texture_tiling_buffer,
+            model_matrix_buffer,
+            vs_uniforms_bind_group,
+
+            diffuse_color_buffer,
+            texture_bind_group_layout,
+            diffuse_texture,
+            diffuse_bind_group,
+            diffuse_color_bind_group,
 
+            node : None,
+        }
+    }
 
-        let diffuse_bind_group = device.create_bind_group(
+    fn create_texture_bind_group(device: &wgpu::Device, layout : &wgpu::BindGroupLayout, texture : &Texture2d) -> wgpu::BindGroup {
+        device.create_bind_group(
             &wgpu::BindGroupDescriptor {
-                layout: &texture_bind_group_layout,
+                layout,
                 entries: &[
                     wgpu::BindGroupEntry {
                         binding: 0,
@@ -188,90 +167,62 @@ impl UnlitMaterial {
                 ],
                 label: Some("diffuse_bind_group"),
             }
-        );
-
-        let render_pipeline_layout = device.create_pipeline_layout(
-            &wgpu::PipelineLayoutDescriptor {
-                label: Some("Render Pipeline Layout"),
-                bind_group_layouts: &[
-                    &vs_uniforms_group_layout,      // bind group 0
-                    &camera.bind_group_layout,      // bind group 1
-                    &texture_bind_group_layout,     // bind group 2
-                    &diffuse_color_group_layout,    // bind group 3
-                ],    
-                push_constant_ranges: &[],
-            }
-        );
-
-
-        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: Some("Unlit render pipeline"),
-            layout: Some( &render_pipeline_layout),
-            vertex: wgpu::VertexState {
-                module: &shader,
-                entry_point: "unlit_material_vs",
-                buffers: &[
-                    PositionElement::desc::<0>(),
-                    ColorElement::desc::<1>(),
-                    TexCoordElement::desc::<2>() ],
-            },
-            fragment: Some(wgpu::FragmentState {
-                module: &shader,
-                entry_point: "unlit_material_fs",
-                targets: &[Some(wgpu::ColorTargetState {
-                    format: format,
-                    blend: Some(wgpu::BlendState::REPLACE),
-                    write_mask: wgpu::ColorWrites::ALL,
-                })],
-            }),
-            
-            primitive: Default::default(), 
-
-            depth_stencil: Some(wgpu::DepthStencilState {
-                format: Texture2d::DEPTH_FORMAT,
-                depth_write_enabled: true,
-                depth_compare: wgpu::CompareFunction::Less,
-                stencil: wgpu::StencilState::default(),
-                bias: wgpu::DepthBiasState::default(),
-            }),
-
-            multisample: Default::default(),
-            multiview: Default::default(),
-        });
+        )
+    }
 
-        Self {
-            render_pipeline,
-            geometry_buffer,
+    pub fn set_diffuse_texture(&mut self, device: &wgpu::Device, texture : Handle<Texture2d>) {
+        self.diffuse_bind_group = Self::create_texture_bind_group(device, &self.texture_bind_group_layout, &texture);
+        self.diffuse_texture = texture;
+    }
 
-            texture_tiling_buffer,
-            model_matrix_buffer,
-            vs_uniforms_bind_group,
+    pub fn diffuse_texture(&self) -> &Handle<Texture2d> {
+        &self.diffuse_texture
+    }
 
-            diffuse_color_buffer,
-            diffuse_bind_group,
-            diffuse_color_bind_group,
+    /// replace the per instance transforms and tints
+    pub fn set_instances(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, instances : &[InstanceElement]) {
+        self.instance_buffer.update(device, queue, instances);
+    }
 
-            rot_angle : 0.0,
-            instance,
-        }
+    pub fn num_instances(&self) -> u32 {
+        self.instance_buffer.num_instances()
     }
 
     
-    pub fn update(&mut self, queue: &wgpu::Queue) {
-        //self.textiling_buffer.data.x *= 1.01;
-        //self.textiling_buffer.update(queue);
-
-        //self.rot_angle += 0.5;
-
-        //self.model_matrix_buffer.data.translate(0.5, 1.0, 0.0);
-        let trans = Mat4::from_translation(Vec3::new(0.5_f32 * self.instance as f32, 0.5_f32 * self.instance as f32, 3.0));
-        //let trans = Mat4::from_translation(Vec3::new(0.0, 0.0, 3.0));
-        let rot = Mat4::from_axis_angle(Vec3::X, self.rot_angle.to_radians() );
-        let scale = Mat4::from_scale(Vec3::new(1.0,1.0,1.0));
-        self.model_matrix_buffer.data =  trans * rot * scale;
+    pub fn set_model_matrix(&mut self, queue: &wgpu::Queue, model_matrix : Mat4) {
+        self.model_matrix_buffer.data = model_matrix;
         self.model_matrix_buffer.update(queue);
     }
 
+    /// take the model matrix from `node` in `update_transform`
+    pub fn set_node(&mut self, node : Option<NodeId>) {
+        self.node = node;
+    }
+
+    pub fn node(&self) -> Option<NodeId> {
+        self.node
+    }
+
+    /// upload the world matrix of the attached node, if it changed
+    pub fn update_transform(&mut self, queue: &wgpu::Queue, scene : &Scene) {
+        if let Some(node) = self.node {
+            let world = scene.world_matrix(node);
+            if world != self.model_matrix_buffer.data {
+                self.set_model_matrix(queue, world);
+            }
+        }
+    }
+
+    pub fn set_texture_tiling(&mut self, queue: &wgpu::Queue, tiling : Vec2) {
+        self.texture_tiling_buffer.data = tiling;
+        self.texture_tiling_buffer.update(queue);
+    }
+
+    pub fn set_diffuse_color(&mut self, queue: &wgpu::Queue, color : Vec4) {
+        self.diffuse_color_buffer.data = color;
+        self.diffuse_color_buffer.update(queue);
+    }
+
     pub fn draw<'a>( &'a self, render_pass: &mut wgpu::RenderPass<'a>, camera:&'a Camera ) {
         render_pass.set_pipeline(&self.render_pipeline); // setup renderpipeline
         render_pass.set_bind_group(0, &self.vs_uniforms_bind_group, &[]);
@@ -282,13 +233,15 @@ impl UnlitMaterial {
         render_pass.set_vertex_buffer(0, self.geometry_buffer.position_buffer.slice(..));
         render_pass.set_vertex_buffer(1, self.geometry_buffer.color_buffer.slice(..));
         render_pass.set_vertex_buffer(2, self.geometry_buffer.texcoord_buffer.slice(..));
+        render_pass.set_vertex_buffer(3, self.instance_buffer.buffer.slice(..));
 
+        let instances = 0..self.instance_buffer.num_instances();
         if let Some(index_buffer) = &self.geometry_buffer.index_buffer {
             render_pass.set_index_buffer(index_buffer.slice(..), wgpu::IndexFormat::Uint32);
-            render_pass.draw_indexed(0..self.geometry_buffer.num_indices, 0, 0..1);
+            render_pass.draw_indexed(0..self.geometry_buffer.num_indices, 0, instances);
 
         } else {
-            render_pass.draw(0..self.geometry_buffer.num_vertices, 0..1); // draw 3 vertices with pipeline    
+            render_pass.draw(0..self.geometry_buffer.num_vertices, instances); // draw 3 vertices with pipeline    
         }
     }
 
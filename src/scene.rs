@@ -0,0 +1,282 @@
+use glam::{Mat4, Quat, Vec3};
+
+/// Local translation / rotation / scale of a scene node
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Transform {
+    pub translation : Vec3,
+    pub rotation : Quat,
+    pub scale : Vec3,
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+impl Transform {
+    pub const IDENTITY : Self = Self {
+        translation : Vec3::ZERO,
+        rotation : Quat::IDENTITY,
+        scale : Vec3::ONE,
+    };
+
+    pub fn from_translation(translation : Vec3) -> Self {
+        Self { translation, ..Self::IDENTITY }
+    }
+
+    pub fn from_rotation(rotation : Quat) -> Self {
+        Self { rotation, ..Self::IDENTITY }
+    }
+
+    pub fn from_scale(scale : Vec3) -> Self {
+        Self { scale, ..Self::IDENTITY }
+    }
+
+    /// decompose an affine matrix, shear is lost
+    pub fn from_matrix(matrix : Mat4) -> Self {
+        let (scale, rotation, translation) = matrix.to_scale_rotation_translation();
+        Self { translation, rotation, scale }
+    }
+
+    pub fn with_translation(self, translation : Vec3) -> Self {
+        Self { translation, ..self }
+    }
+
+    pub fn with_rotation(self, rotation : Quat) -> Self {
+        Self { rotation, ..self }
+    }
+
+    pub fn with_scale(self, scale : Vec3) -> Self {
+        Self { scale, ..self }
+    }
+
+    pub fn matrix(&self) -> Mat4 {
+        Mat4::from_scale_rotation_translation(self.scale, self.rotation, self.translation)
+    }
+}
+
+/// Handle to a node in a `Scene`
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct NodeId(usize);
+
+#[derive(Debug)]
+pub struct Node {
+    pub name : String,
+    local : Transform,
+    parent : Option<NodeId>,
+    children : Vec<NodeId>,
+    world : Mat4,
+    dirty : bool,
+}
+
+impl Node {
+    pub fn local(&self) -> &Transform {
+        &self.local
+    }
+
+    pub fn parent(&self) -> Option<NodeId> {
+        self.parent
+    }
+
+    pub fn children(&self) -> &[NodeId] {
+        &self.children
+    }
+}
+
+/// Hierarchy of transforms. World matrices are cached per node and only
+/// recomputed for nodes whose own or ancestor's local transform changed.
+#[derive(Debug, Default)]
+pub struct Scene {
+    nodes : Vec<Node>,
+}
+
+impl Scene {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_node(&mut self, name : &str, local : Transform, parent : Option<NodeId>) -> NodeId {
+        let id = NodeId(self.nodes.len());
+        self.nodes.push(Node {
+            name : name.into(),
+            local,
+            parent,
+            children : Vec::new(),
+            world : Mat4::IDENTITY,
+            dirty : true,
+        });
+        if let Some(parent) = parent {
+            self.nodes[parent.0].children.push(id);
+        }
+        id
+    }
+
+    pub fn node(&self, id : NodeId) -> &Node {
+        &self.nodes[id.0]
+    }
+
+    pub fn find(&self, name : &str) -> Option<NodeId> {
+        self.nodes.iter().position(|n| n.name == name).map(NodeId)
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// re-parent `id`, keeping its local transform. Panics when this would create a cycle.
+    pub fn set_parent(&mut self, id : NodeId, parent : Option<NodeId>) {
+        let mut ancestor = parent;
+        while let Some(a) = ancestor {
+            assert!(a != id, "node {:?} can't be its own ancestor", self.nodes[id.0].name);
+            ancestor = self.nodes[a.0].parent;
+        }
+
+        if let Some(old) = self.nodes[id.0].parent {
+            self.nodes[old.0].children.retain(|c| *c != id);
+        }
+        if let Some(new) = parent {
+            self.nodes[new.0].children.push(id);
+        }
+        self.nodes[id.0].parent = parent;
+        self.mark_dirty(id);
+    }
+
+    pub fn set_local(&mut self, id : NodeId, local : Transform) {
+        if self.nodes[id.0].local != local {
+            self.nodes[id.0].local = local;
+            self.mark_dirty(id);
+        }
+    }
+
+    /// modify the local transform in place
+    pub fn update_local(&mut self, id : NodeId, f : impl FnOnce(&mut Transform)) {
+        let mut local = self.nodes[id.0].local;
+        f(&mut local);
+        self.set_local(id, local);
+    }
+
+    fn mark_dirty(&mut self, id : NodeId) {
+        let mut stack = vec![id];
+        while let Some(id) = stack.pop() {
+            let node = &mut self.nodes[id.0];
+            // descendants of a dirty node are dirty already
+            if node.dirty {
+                continue;
+            }
+            node.dirty = true;
+            stack.extend_from_slice(&node.children);
+        }
+    }
+
+    pub fn is_dirty(&self, id : NodeId) -> bool {
+        self.nodes[id.0].dirty
+    }
+
+    /// recompute the world matrices of all dirty nodes
+    pub fn update_world_matrices(&mut self) {
+        for index in 0..self.nodes.len() {
+            self.update_world_matrix(NodeId(index));
+        }
+    }
+
+    fn update_world_matrix(&mut self, id : NodeId) -> Mat4 {
+        let node = &self.nodes[id.0];
+        if !node.dirty {
+            return node.world;
+        }
+
+        let local = node.local.matrix();
+        let world = match node.parent {
+            Some(parent) => self.update_world_matrix(parent) * local,
+            None => local,
+        };
+
+        let node = &mut self.nodes[id.0];
+        node.world = world;
+        node.dirty = false;
+        world
+    }
+
+    /// cached world matrix, call `update_world_matrices` after changing transforms
+    pub fn world_matrix(&self, id : NodeId) -> Mat4 {
+        let node = &self.nodes[id.0];
+        debug_assert!(!node.dirty, "world matrix of {:?} is out of date", node.name);
+        node.world
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_translation(scene : &Scene, id : NodeId, expected : Vec3) {
+        let translation = scene.world_matrix(id).w_axis.truncate();
+        assert!(translation.abs_diff_eq(expected, 1e-5), "{translation} != {expected}");
+    }
+
+    #[test]
+    fn moving_the_parent_moves_the_children() {
+        let mut scene = Scene::new();
+        let parent = scene.add_node("parent", Transform::from_translation(Vec3::X), None);
+        let child = scene.add_node("child", Transform::from_translation(Vec3::Y), Some(parent));
+        let grandchild = scene.add_node("grandchild", Transform::from_translation(Vec3::Z), Some(child));
+        scene.update_world_matrices();
+        assert_translation(&scene, grandchild, Vec3::new(1.0, 1.0, 1.0));
+        assert!(!scene.is_dirty(parent) && !scene.is_dirty(child) && !scene.is_dirty(grandchild));
+
+        scene.set_local(parent, Transform::from_translation(Vec3::new(5.0, 0.0, 0.0)));
+        assert!(scene.is_dirty(child) && scene.is_dirty(grandchild));
+        scene.update_world_matrices();
+        assert_translation(&scene, child, Vec3::new(5.0, 1.0, 0.0));
+        assert_translation(&scene, grandchild, Vec3::new(5.0, 1.0, 1.0));
+    }
+
+    #[test]
+    fn parent_rotation_and_scale_apply_to_children() {
+        let mut scene = Scene::new();
+        let parent = scene.add_node("parent", Transform::from_scale(Vec3::splat(2.0))
+            .with_rotation(Quat::from_rotation_z(std::f32::consts::FRAC_PI_2)), None);
+        let child = scene.add_node("child", Transform::from_translation(Vec3::X), Some(parent));
+        scene.update_world_matrices();
+        assert_translation(&scene, child, Vec3::new(0.0, 2.0, 0.0));
+    }
+
+    #[test]
+    fn unchanged_transform_keeps_the_node_clean() {
+        let mut scene = Scene::new();
+        let node = scene.add_node("node", Transform::from_translation(Vec3::X), None);
+        scene.update_world_matrices();
+        scene.set_local(node, Transform::from_translation(Vec3::X));
+        assert!(!scene.is_dirty(node));
+    }
+
+    #[test]
+    fn reparenting_updates_the_world_matrix() {
+        let mut scene = Scene::new();
+        let a = scene.add_node("a", Transform::from_translation(Vec3::X), None);
+        let b = scene.add_node("b", Transform::from_translation(Vec3::Y), None);
+        let child = scene.add_node("child", Transform::IDENTITY, Some(a));
+        scene.update_world_matrices();
+        assert_translation(&scene, child, Vec3::X);
+
+        scene.set_parent(child, Some(b));
+        scene.update_world_matrices();
+        assert_translation(&scene, child, Vec3::Y);
+        assert!(scene.node(a).children().is_empty());
+        assert_eq!(scene.node(b).children(), &[child]);
+    }
+
+    #[test]
+    #[should_panic(expected = "own ancestor")]
+    fn cycles_are_rejected() {
+        let mut scene = Scene::new();
+        let parent = scene.add_node("parent", Transform::IDENTITY, None);
+        let child = scene.add_node("child", Transform::IDENTITY, Some(parent));
+        scene.set_parent(parent, Some(child));
+    }
+}
@@ -0,0 +1,183 @@
+use winit::event::WindowEvent;
+
+use crate::game::PongWorld;
+use crate::game::ai::AiController;
+use crate::input::InputState;
+
+pub mod pong;
+
+/// The game state screens work on
+pub struct ScreenContext<'a> {
+    pub world : &'a mut PongWorld,
+    /// computer player, None in two player mode
+    pub ai : &'a mut Option<AiController>,
+    pub input : &'a InputState,
+}
+
+impl ScreenContext<'_> {
+    /// advance the match by `dt` with the keyboard and AI inputs
+    pub fn step_world(&mut self, dt : f32) {
+        let mut inputs = self.input.pong_inputs();
+        if let Some(ai) = self.ai.as_mut() {
+            *inputs.get_mut(ai.side) = ai.update(self.world, dt);
+        }
+        self.world.step(dt, &inputs);
+    }
+}
+
+/// Where on the window a line of overlay text goes
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Anchor {
+    Top,
+    Center,
+    Bottom,
+}
+
+/// One line of overlay text, centered horizontally
+#[derive(Clone, Debug, PartialEq)]
+pub struct OverlayText {
+    pub anchor : Anchor,
+    pub text : String,
+    /// relative to the normal text size
+    pub scale : f32,
+}
+
+/// Text the screens want drawn over the scene, collected again every frame.
+/// Lines with the same anchor are stacked in the order they were added.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Overlay {
+    pub texts : Vec<OverlayText>,
+}
+
+impl Overlay {
+    pub fn text(&mut self, anchor : Anchor, text : impl Into<String>) {
+        self.scaled_text(anchor, text, 1.0);
+    }
+
+    pub fn scaled_text(&mut self, anchor : Anchor, text : impl Into<String>, scale : f32) {
+        self.texts.push(OverlayText { anchor, text : text.into(), scale });
+    }
+
+    pub fn clear(&mut self) {
+        self.texts.clear();
+    }
+}
+
+/// Change of the screen stack requested by the top screen
+pub enum Transition {
+    None,
+    /// put a screen on top, the others stay below it
+    Push(Box<dyn Screen>),
+    /// remove the top screen
+    Pop,
+    /// swap the top screen for another one
+    Replace(Box<dyn Screen>),
+    /// remove all screens and start over with this one
+    Reset(Box<dyn Screen>),
+    Quit,
+}
+
+/// One state of the game, e.g. the title screen or the running match.
+/// Only the top screen of a `ScreenStack` gets `update` and `input`, all screens render
+/// bottom to top so e.g. a pause screen draws over the match.
+pub trait Screen {
+    fn name(&self) -> &'static str;
+
+    /// called when the screen is added to the stack
+    fn enter(&mut self, _ctx : &mut ScreenContext) {}
+
+    /// called when the screen is removed from the stack
+    fn exit(&mut self, _ctx : &mut ScreenContext) {}
+
+    /// one fixed simulation step
+    fn update(&mut self, ctx : &mut ScreenContext, dt : f32) -> Transition;
+
+    fn render(&self, _world : &PongWorld, _overlay : &mut Overlay) {}
+
+    /// raw window events, for things the action bindings don't cover
+    fn input(&mut self, _ctx : &mut ScreenContext, _event : &WindowEvent) -> Transition {
+        Transition::None
+    }
+}
+
+/// Stack of active screens, the top one is in control
+#[derive(Default)]
+pub struct ScreenStack {
+    screens : Vec<Box<dyn Screen>>,
+    quit : bool,
+}
+
+impl ScreenStack {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, ctx : &mut ScreenContext, mut screen : Box<dyn Screen>) {
+        log::debug!("enter {} screen", screen.name());
+        screen.enter(ctx);
+        self.screens.push(screen);
+    }
+
+    pub fn pop(&mut self, ctx : &mut ScreenContext) -> Option<Box<dyn Screen>> {
+        let mut screen = self.screens.pop()?;
+        log::debug!("exit {} screen", screen.name());
+        screen.exit(ctx);
+        Some(screen)
+    }
+
+    pub fn replace(&mut self, ctx : &mut ScreenContext, screen : Box<dyn Screen>) {
+        self.pop(ctx);
+        self.push(ctx, screen);
+    }
+
+    pub fn top(&self) -> Option<&dyn Screen> {
+        self.screens.last().map(|screen| &**screen)
+    }
+
+    pub fn len(&self) -> usize {
+        self.screens.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.screens.is_empty()
+    }
+
+    /// true once a screen asked to quit or the last screen was popped
+    pub fn should_quit(&self) -> bool {
+        self.quit || self.screens.is_empty()
+    }
+
+    pub fn apply(&mut self, ctx : &mut ScreenContext, transition : Transition) {
+        match transition {
+            Transition::None => {}
+            Transition::Push(screen) => self.push(ctx, screen),
+            Transition::Pop => {
+                self.pop(ctx);
+            }
+            Transition::Replace(screen) => self.replace(ctx, screen),
+            Transition::Reset(screen) => {
+                while self.pop(ctx).is_some() {}
+                self.push(ctx, screen);
+            }
+            Transition::Quit => self.quit = true,
+        }
+    }
+
+    pub fn update(&mut self, ctx : &mut ScreenContext, dt : f32) {
+        if let Some(screen) = self.screens.last_mut() {
+            let transition = screen.update(ctx, dt);
+            self.apply(ctx, transition);
+        }
+    }
+
+    pub fn input(&mut self, ctx : &mut ScreenContext, event : &WindowEvent) {
+        if let Some(screen) = self.screens.last_mut() {
+            let transition = screen.input(ctx, event);
+            self.apply(ctx, transition);
+        }
+    }
+
+    pub fn render(&self, world : &PongWorld, overlay : &mut Overlay) {
+        self.screens.iter().for_each(|screen| screen.render(world, overlay));
+    }
+}
@@ -0,0 +1,441 @@
+use crate::game::{PongEvent, PongWorld, Side};
+use crate::game::ai::{AiController, Difficulty};
+use crate::input::{Action, InputState};
+use super::{Anchor, Overlay, Screen, ScreenContext, Transition};
+
+// how long the point scored message stays up before the next serve
+const POINT_SCORED_TIME : f32 = 1.0;
+
+// text sizes relative to the normal overlay text
+const TITLE_SCALE : f32 = 4.0;
+const SCORE_SCALE : f32 = 2.0;
+
+// the AI always plays the right paddle
+const AI_SIDE : Side = Side::Right;
+
+fn side_name(side : Side) -> &'static str {
+    match side {
+        Side::Left => "left",
+        Side::Right => "right",
+    }
+}
+
+fn render_score(world : &PongWorld, overlay : &mut Overlay) {
+    overlay.scaled_text(Anchor::Top, format!("{} : {}", world.score.left, world.score.right), SCORE_SCALE);
+}
+
+fn pause_requested(input : &InputState) -> bool {
+    input.is_pressed(Action::Pause) || input.is_pressed(Action::Back)
+}
+
+// back to the title screen with the mode of the current match selected
+fn back_to_title(ctx : &ScreenContext) -> Transition {
+    let mode = ctx.ai.as_ref().map(|ai| ai.difficulty().unwrap_or(Difficulty::Normal));
+    Transition::Reset(Box::new(TitleScreen::new(mode)))
+}
+
+/// Choose single player difficulty or two players, then start a match
+pub struct TitleScreen {
+    // None is two players
+    modes : Vec<Option<Difficulty>>,
+    selected : usize,
+}
+
+impl TitleScreen {
+    /// `mode` is selected at first, None for two players
+    pub fn new(mode : Option<Difficulty>) -> Self {
+        let modes : Vec<_> = Difficulty::ALL.into_iter().map(Some).chain([None]).collect();
+        let selected = modes.iter().position(|m| *m == mode).unwrap_or(0);
+        Self { modes, selected }
+    }
+
+    fn mode_name(&self) -> String {
+        match self.modes[self.selected] {
+            Some(difficulty) => format!("1 player, {}", difficulty.name()),
+            None => "2 players".to_string(),
+        }
+    }
+
+    fn start(&self, ctx : &mut ScreenContext) -> Transition {
+        *ctx.ai = self.modes[self.selected].map(|difficulty| AiController::new(AI_SIDE, difficulty));
+        ctx.world.reset();
+        Transition::Replace(Box::new(ServeScreen))
+    }
+}
+
+impl Screen for TitleScreen {
+    fn name(&self) -> &'static str {
+        "title"
+    }
+
+    fn enter(&mut self, ctx : &mut ScreenContext) {
+        ctx.world.reset();
+    }
+
+    fn update(&mut self, ctx : &mut ScreenContext, _dt : f32) -> Transition {
+        let input = ctx.input;
+        let pressed = |action : fn(Side) -> Action| {
+            [Side::Left, Side::Right].into_iter().any(|side| input.is_pressed(action(side)))
+        };
+        let count = self.modes.len();
+        if pressed(Action::PaddleUp) {
+            self.selected = (self.selected + count - 1) % count;
+        }
+        if pressed(Action::PaddleDown) {
+            self.selected = (self.selected + 1) % count;
+        }
+
+        if input.is_pressed(Action::Confirm) {
+            self.start(ctx)
+        } else if input.is_pressed(Action::Back) {
+            Transition::Quit
+        } else {
+            Transition::None
+        }
+    }
+
+    fn render(&self, _world : &PongWorld, overlay : &mut Overlay) {
+        overlay.scaled_text(Anchor::Center, "PONG", TITLE_SCALE);
+        overlay.text(Anchor::Center, format!("< {} >", self.mode_name()));
+        overlay.text(Anchor::Bottom, "up/down to choose, enter to start, esc to quit");
+    }
+}
+
+/// Ball waits at the center for the serve, the paddles can already move
+pub struct ServeScreen;
+
+impl Screen for ServeScreen {
+    fn name(&self) -> &'static str {
+        "serve"
+    }
+
+    fn update(&mut self, ctx : &mut ScreenContext, dt : f32) -> Transition {
+        if pause_requested(ctx.input) {
+            return Transition::Push(Box::new(PausedScreen));
+        }
+        ctx.step_world(dt);
+        if ctx.world.events().iter().any(|event| matches!(event, PongEvent::Served(_))) {
+            Transition::Replace(Box::new(PlayingScreen))
+        } else {
+            Transition::None
+        }
+    }
+
+    fn render(&self, world : &PongWorld, overlay : &mut Overlay) {
+        render_score(world, overlay);
+        overlay.text(Anchor::Bottom, "get ready");
+    }
+}
+
+/// Ball in play until someone scores
+pub struct PlayingScreen;
+
+impl Screen for PlayingScreen {
+    fn name(&self) -> &'static str {
+        "playing"
+    }
+
+    fn update(&mut self, ctx : &mut ScreenContext, dt : f32) -> Transition {
+        if pause_requested(ctx.input) {
+            return Transition::Push(Box::new(PausedScreen));
+        }
+        ctx.step_world(dt);
+
+        // game over comes right after the winning point in the same step
+        let mut transition = Transition::None;
+        for event in ctx.world.events() {
+            match *event {
+                PongEvent::GameOver(winner) => return Transition::Replace(Box::new(GameOverScreen::new(winner))),
+                PongEvent::PointScored(scorer) => transition = Transition::Replace(Box::new(PointScoredScreen::new(scorer))),
+                _ => {}
+            }
+        }
+        transition
+    }
+
+    fn render(&self, world : &PongWorld, overlay : &mut Overlay) {
+        render_score(world, overlay);
+    }
+}
+
+/// Short break after a point, the world is frozen
+pub struct PointScoredScreen {
+    scorer : Side,
+    timer : f32,
+}
+
+impl PointScoredScreen {
+    pub fn new(scorer : Side) -> Self {
+        Self { scorer, timer : POINT_SCORED_TIME }
+    }
+}
+
+impl Screen for PointScoredScreen {
+    fn name(&self) -> &'static str {
+        "point scored"
+    }
+
+    fn update(&mut self, ctx : &mut ScreenContext, dt : f32) -> Transition {
+        if pause_requested(ctx.input) {
+            return Transition::Push(Box::new(PausedScreen));
+        }
+        self.timer -= dt;
+        if self.timer <= 0.0 {
+            Transition::Replace(Box::new(ServeScreen))
+        } else {
+            Transition::None
+        }
+    }
+
+    fn render(&self, world : &PongWorld, overlay : &mut Overlay) {
+        render_score(world, overlay);
+        overlay.text(Anchor::Center, format!("point for {}", side_name(self.scorer)));
+    }
+}
+
+/// Freezes the screens below until resumed
+pub struct PausedScreen;
+
+impl Screen for PausedScreen {
+    fn name(&self) -> &'static str {
+        "paused"
+    }
+
+    fn update(&mut self, ctx : &mut ScreenContext, _dt : f32) -> Transition {
+        let input = ctx.input;
+        if input.is_pressed(Action::Pause) || input.is_pressed(Action::Confirm) {
+            Transition::Pop
+        } else if input.is_pressed(Action::Back) {
+            back_to_title(ctx)
+        } else {
+            Transition::None
+        }
+    }
+
+    fn render(&self, _world : &PongWorld, overlay : &mut Overlay) {
+        overlay.scaled_text(Anchor::Center, "paused", SCORE_SCALE);
+        overlay.text(Anchor::Bottom, "p or enter to resume, esc for the title screen");
+    }
+}
+
+/// Match result, play again or go back to the title
+pub struct GameOverScreen {
+    winner : Side,
+    message : String,
+}
+
+impl GameOverScreen {
+    pub fn new(winner : Side) -> Self {
+        Self { winner, message : format!("{} wins", side_name(winner)) }
+    }
+}
+
+impl Screen for GameOverScreen {
+    fn name(&self) -> &'static str {
+        "game over"
+    }
+
+    fn enter(&mut self, ctx : &mut ScreenContext) {
+        if let Some(ai) = ctx.ai.as_ref() {
+            self.message = if ai.side == self.winner { "you lose" } else { "you win" }.to_string();
+        }
+    }
+
+    fn update(&mut self, ctx : &mut ScreenContext, _dt : f32) -> Transition {
+        let input = ctx.input;
+        if input.is_pressed(Action::Confirm) {
+            ctx.world.reset();
+            Transition::Replace(Box::new(ServeScreen))
+        } else if input.is_pressed(Action::Back) {
+            back_to_title(ctx)
+        } else {
+            Transition::None
+        }
+    }
+
+    fn render(&self, world : &PongWorld, overlay : &mut Overlay) {
+        render_score(world, overlay);
+        overlay.scaled_text(Anchor::Center, self.message.as_str(), SCORE_SCALE);
+        overlay.text(Anchor::Bottom, "enter to play again, esc for the title screen");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use winit::event::{ElementState, MouseButton, VirtualKeyCode};
+
+    use super::*;
+    use crate::game::{PongConfig, ServeState};
+    use crate::input::{Binding, InputMap};
+    use crate::screen::ScreenStack;
+
+    const DT : f32 = 1.0 / 120.0;
+
+    // everything `State` owns for the screens, without the window
+    struct Game {
+        world : PongWorld,
+        ai : Option<AiController>,
+        input : InputState,
+        screens : ScreenStack,
+    }
+
+    impl Game {
+        fn new(screen : Box<dyn Screen>) -> Self {
+            let mut game = Self {
+                world : PongWorld::new(PongConfig::default()),
+                ai : None,
+                input : InputState::new(InputMap::default()),
+                screens : ScreenStack::new(),
+            };
+            let mut ctx = ScreenContext { world : &mut game.world, ai : &mut game.ai, input : &game.input };
+            game.screens.push(&mut ctx, screen);
+            game
+        }
+
+        fn update(&mut self) {
+            let mut ctx = ScreenContext { world : &mut self.world, ai : &mut self.ai, input : &self.input };
+            self.screens.update(&mut ctx, DT);
+            self.input.end_tick();
+        }
+
+        // one tick with `key` pressed
+        fn press(&mut self, key : VirtualKeyCode) {
+            self.input.set_binding(Binding::Key(key), ElementState::Pressed);
+            self.update();
+            self.input.set_binding(Binding::Key(key), ElementState::Released);
+        }
+
+        fn top(&self) -> &'static str {
+            self.screens.top().map(|screen| screen.name()).unwrap_or("none")
+        }
+
+        // tick until `name` is on top, panics after `max_steps`
+        fn update_until(&mut self, name : &str, max_steps : usize) {
+            for _ in 0..max_steps {
+                self.update();
+                if self.top() == name {
+                    return;
+                }
+            }
+            panic!("no {name} screen within {max_steps} steps, on {}", self.top());
+        }
+
+        // a match in play with the ball about to pass the left paddle
+        fn playing(right_score : u32) -> Self {
+            let mut game = Self::new(Box::new(PlayingScreen));
+            game.world.serve = ServeState::InPlay;
+            game.world.score.right = right_score;
+            game.world.ball.position = glam::Vec2::new(-3.0, 2.0);
+            game.world.ball.velocity = glam::Vec2::new(-6.0, 0.0);
+            game
+        }
+    }
+
+    #[test]
+    fn serve_then_play() {
+        let mut game = Game::new(Box::new(TitleScreen::new(None)));
+        game.press(VirtualKeyCode::Return);
+        assert_eq!(game.top(), "serve");
+        assert!(game.ai.is_none());
+        game.update_until("playing", 200);
+        assert_eq!(game.world.serve, ServeState::InPlay);
+        assert_eq!(game.screens.len(), 1);
+    }
+
+    #[test]
+    fn points_until_game_over() {
+        let mut game = Game::playing(0);
+        game.update_until("point scored", 60);
+        assert_eq!(game.world.score.right, 1);
+        // the next serve after the break
+        game.update_until("serve", (POINT_SCORED_TIME / DT) as usize + 2);
+
+        let winning_score = game.world.config.winning_score;
+        let mut game = Game::playing(winning_score - 1);
+        game.update_until("game over", 60);
+        assert_eq!(game.world.winner(), Some(Side::Right));
+    }
+
+    #[test]
+    fn pause_and_resume() {
+        let mut game = Game::playing(0);
+        game.update();
+        game.press(VirtualKeyCode::P);
+        assert_eq!(game.top(), "paused");
+        assert_eq!(game.screens.len(), 2);
+
+        // the match below is frozen
+        let ball = game.world.ball.position;
+        for _ in 0..10 {
+            game.update();
+        }
+        assert_eq!(game.world.ball.position, ball);
+
+        game.press(VirtualKeyCode::P);
+        assert_eq!(game.top(), "playing");
+        game.press(VirtualKeyCode::Escape);
+        assert_eq!(game.top(), "paused");
+        game.press(VirtualKeyCode::Return);
+        assert_eq!(game.top(), "playing");
+        assert_eq!(game.screens.len(), 1);
+    }
+
+    #[test]
+    fn back_to_title() {
+        let mut game = Game::playing(0);
+        game.ai = Some(AiController::new(AI_SIDE, Difficulty::Hard));
+        game.press(VirtualKeyCode::Escape);
+        game.press(VirtualKeyCode::Escape);
+        assert_eq!(game.top(), "title");
+        assert_eq!(game.screens.len(), 1);
+
+        // the mode of the last match is selected, confirm starts it again
+        game.press(VirtualKeyCode::Return);
+        assert_eq!(game.ai.as_ref().and_then(|ai| ai.difficulty()), Some(Difficulty::Hard));
+
+        // escape on the title screen quits
+        let mut game = Game::new(Box::new(TitleScreen::new(None)));
+        game.press(VirtualKeyCode::Escape);
+        assert!(game.screens.should_quit());
+    }
+
+    #[test]
+    fn restart_or_leave_after_game_over() {
+        let winning_score = PongConfig::default().winning_score;
+        let mut game = Game::playing(winning_score - 1);
+        game.update_until("game over", 60);
+        game.press(VirtualKeyCode::Return);
+        assert_eq!(game.top(), "serve");
+        assert_eq!((game.world.score.left, game.world.score.right), (0, 0));
+        assert_eq!(game.world.winner(), None);
+
+        let mut game = Game::playing(winning_score - 1);
+        game.update_until("game over", 60);
+        game.press(VirtualKeyCode::Escape);
+        assert_eq!(game.top(), "title");
+        assert_eq!(game.screens.len(), 1);
+    }
+
+    #[test]
+    fn only_confirm_starts_a_match() {
+        let mut world = PongWorld::new(PongConfig::default());
+        let mut ai = None;
+        let mut input = InputState::new(InputMap::default());
+        let mut screens = ScreenStack::new();
+        let mut ctx = ScreenContext { world : &mut world, ai : &mut ai, input : &input };
+        screens.push(&mut ctx, Box::new(TitleScreen::new(Some(Difficulty::Normal))));
+
+        // clicks are for picking, not for the menu
+        input.set_binding(Binding::Mouse(MouseButton::Left), ElementState::Pressed);
+        let mut ctx = ScreenContext { world : &mut world, ai : &mut ai, input : &input };
+        screens.update(&mut ctx, 0.01);
+        assert_eq!(screens.top().map(|s| s.name()), Some("title"));
+        input.end_tick();
+
+        input.set_binding(Binding::Key(VirtualKeyCode::Return), ElementState::Pressed);
+        let mut ctx = ScreenContext { world : &mut world, ai : &mut ai, input : &input };
+        screens.update(&mut ctx, 0.01);
+        assert_eq!(screens.top().map(|s| s.name()), Some("serve"));
+        assert!(ai.is_some());
+    }
+}
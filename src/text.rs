@@ -0,0 +1,169 @@
+use glam::Vec2;
+
+mod font5x7;
+
+// glyph cells in the atlas, with a pixel of padding right and below each glyph
+const GLYPH_SIZE : (u32, u32) = (5, 7);
+const CELL_SIZE : (u32, u32) = (6, 8);
+const ATLAS_COLUMNS : u32 = 16;
+const FIRST_CHAR : char = ' ';
+const LAST_CHAR : char = '~';
+
+/// One quad of laid out text, in the units of the layout origin
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct GlyphQuad {
+    pub min : Vec2,
+    pub max : Vec2,
+    pub uv_min : Vec2,
+    pub uv_max : Vec2,
+}
+
+/// Monospaced 5x7 pixel font of the printable ASCII characters, packed into one atlas image.
+/// Sizes are in font pixels, `scale` is the size of a font pixel on screen.
+#[derive(Clone, Debug)]
+pub struct BitmapFont {
+    atlas : image::RgbaImage,
+}
+
+impl Default for BitmapFont {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BitmapFont {
+    pub fn new() -> Self {
+        let count = LAST_CHAR as u32 - FIRST_CHAR as u32 + 1;
+        let rows = count.div_ceil(ATLAS_COLUMNS);
+        let mut atlas = image::RgbaImage::new(ATLAS_COLUMNS * CELL_SIZE.0, rows * CELL_SIZE.1);
+        // white glyphs on transparent, tinted by the vertex color
+        for (index, glyph) in font5x7::GLYPHS.iter().enumerate() {
+            let (cell_x, cell_y) = Self::cell(index as u32);
+            for (y, row) in glyph.iter().enumerate() {
+                for x in 0..GLYPH_SIZE.0 {
+                    if row & (1 << (GLYPH_SIZE.0 - 1 - x)) != 0 {
+                        atlas.put_pixel(cell_x + x, cell_y + y as u32, image::Rgba([255, 255, 255, 255]));
+                    }
+                }
+            }
+        }
+        Self { atlas }
+    }
+
+    // top left pixel of a glyph in the atlas
+    fn cell(index : u32) -> (u32, u32) {
+        ((index % ATLAS_COLUMNS) * CELL_SIZE.0, (index / ATLAS_COLUMNS) * CELL_SIZE.1)
+    }
+
+    pub fn atlas(&self) -> &image::RgbaImage {
+        &self.atlas
+    }
+
+    pub fn glyph_size(&self) -> Vec2 {
+        Vec2::new(GLYPH_SIZE.0 as f32, GLYPH_SIZE.1 as f32)
+    }
+
+    /// distance from one character to the next
+    pub fn advance(&self) -> f32 {
+        CELL_SIZE.0 as f32
+    }
+
+    /// distance from one line to the next
+    pub fn line_height(&self) -> f32 {
+        (GLYPH_SIZE.1 + 2) as f32
+    }
+
+    /// atlas texture coordinates of `c`, characters without a glyph show as '?'
+    pub fn glyph_uv(&self, c : char) -> (Vec2, Vec2) {
+        let c = if (FIRST_CHAR..=LAST_CHAR).contains(&c) { c } else { '?' };
+        let (x, y) = Self::cell(c as u32 - FIRST_CHAR as u32);
+        let atlas_size = Vec2::new(self.atlas.width() as f32, self.atlas.height() as f32);
+        let min = Vec2::new(x as f32, y as f32);
+        (min / atlas_size, (min + self.glyph_size()) / atlas_size)
+    }
+
+    /// size of the text block, lines are separated by '\n'
+    pub fn measure(&self, text : &str, scale : f32) -> Vec2 {
+        let columns = text.lines().map(|line| line.chars().count()).max().unwrap_or(0);
+        let lines = text.lines().count();
+        if columns == 0 {
+            return Vec2::new(0.0, lines as f32 * self.line_height() * scale);
+        }
+        let width = (columns - 1) as f32 * self.advance() + self.glyph_size().x;
+        let height = (lines - 1) as f32 * self.line_height() + self.glyph_size().y;
+        Vec2::new(width, height) * scale
+    }
+
+    /// quads of the visible glyphs with the top left of the text at `origin`, y pointing down
+    pub fn layout<'a>(&'a self, text : &'a str, origin : Vec2, scale : f32) -> impl Iterator<Item = GlyphQuad> + 'a {
+        text.lines().enumerate().flat_map(move |(line, chars)| {
+            chars.chars().enumerate()
+                .filter(|(_, c)| *c != ' ')
+                .map(move |(column, c)| {
+                    let min = origin + Vec2::new(column as f32 * self.advance(), line as f32 * self.line_height()) * scale;
+                    let (uv_min, uv_max) = self.glyph_uv(c);
+                    GlyphQuad { min, max : min + self.glyph_size() * scale, uv_min, uv_max }
+                })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn measure_single_and_multiple_lines() {
+        let font = BitmapFont::new();
+        assert_eq!(font.measure("", 1.0), Vec2::ZERO);
+        assert_eq!(font.measure("A", 1.0), Vec2::new(5.0, 7.0));
+        // no spacing after the last character
+        assert_eq!(font.measure("AB", 1.0), Vec2::new(11.0, 7.0));
+        assert_eq!(font.measure("AB", 2.0), Vec2::new(22.0, 14.0));
+        // the longest line sets the width
+        assert_eq!(font.measure("AB\nCDE", 1.0), Vec2::new(17.0, 16.0));
+        assert_eq!(font.measure("ABC\nD\n", 3.0), Vec2::new(51.0, 48.0));
+    }
+
+    #[test]
+    fn layout_places_glyphs_on_the_grid() {
+        let font = BitmapFont::new();
+        let origin = Vec2::new(10.0, 20.0);
+        let quads : Vec<_> = font.layout("A C\nD", origin, 2.0).collect();
+        // spaces don't get a quad
+        assert_eq!(quads.len(), 3);
+        assert_eq!(quads[0].min, origin);
+        assert_eq!(quads[0].max, origin + Vec2::new(10.0, 14.0));
+        assert_eq!(quads[1].min, origin + Vec2::new(24.0, 0.0));
+        assert_eq!(quads[2].min, origin + Vec2::new(0.0, 18.0));
+
+        // the quads fill exactly the measured block
+        let text = "PONG\n1 : 0";
+        let max = font.layout(text, Vec2::ZERO, 3.0).fold(Vec2::ZERO, |max, quad| max.max(quad.max));
+        assert_eq!(max, font.measure(text, 3.0));
+    }
+
+    #[test]
+    fn glyphs_come_from_their_atlas_cell() {
+        let font = BitmapFont::new();
+        let atlas = Vec2::new(font.atlas().width() as f32, font.atlas().height() as f32);
+        assert_eq!(atlas, Vec2::new(96.0, 48.0));
+
+        // 'A' is the 33rd character after the space
+        let (uv_min, uv_max) = font.glyph_uv('A');
+        assert_eq!(uv_min * atlas, Vec2::new(6.0, 16.0));
+        assert!(((uv_max - uv_min) * atlas).abs_diff_eq(font.glyph_size(), 1e-4));
+        // without a glyph
+        assert_eq!(font.glyph_uv('\u{e9}'), font.glyph_uv('?'));
+        assert_eq!(font.layout("\u{e9}", Vec2::ZERO, 1.0).next().map(|q| q.uv_min), Some(font.glyph_uv('?').0));
+
+        // the glyph pixels are opaque white, the padding is empty
+        let pixels = |x0 : u32, y0 : u32, w : u32, h : u32| (x0..x0 + w)
+            .flat_map(move |x| (y0..y0 + h).map(move |y| (x, y)))
+            .filter(|(x, y)| font.atlas().get_pixel(*x, *y).0 == [255; 4])
+            .count();
+        assert!(pixels(6, 16, 5, 7) > 0);
+        assert_eq!(pixels(11, 16, 1, 8), 0);
+        assert_eq!(pixels(6, 23, 6, 1), 0);
+    }
+}
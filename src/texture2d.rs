@@ -1,6 +1,99 @@
+use std::path::Path;
 use image::GenericImageView;
 use crate::errors::WvtError;
 
+/// Filtering used when sampling a color texture
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum SamplerPreset {
+    /// blocky pixels, no filtering between texels or mip levels
+    Nearest,
+    /// linear between texels, nearest mip level
+    Bilinear,
+    /// linear between texels and mip levels
+    #[default]
+    Trilinear,
+    /// trilinear with anisotropic filtering, clamp is 1..=16
+    Anisotropic(u16),
+}
+
+impl SamplerPreset {
+    pub fn descriptor(self) -> wgpu::SamplerDescriptor<'static> {
+        let (filter, mipmap_filter, anisotropy_clamp) = match self {
+            SamplerPreset::Nearest => (wgpu::FilterMode::Nearest, wgpu::FilterMode::Nearest, 1),
+            SamplerPreset::Bilinear => (wgpu::FilterMode::Linear, wgpu::FilterMode::Nearest, 1),
+            SamplerPreset::Trilinear => (wgpu::FilterMode::Linear, wgpu::FilterMode::Linear, 1),
+            SamplerPreset::Anisotropic(clamp) => (wgpu::FilterMode::Linear, wgpu::FilterMode::Linear, clamp.clamp(1, 16)),
+        };
+        wgpu::SamplerDescriptor {
+            label: Some("Texture sampler"),
+            address_mode_u: wgpu::AddressMode::Repeat,
+            address_mode_v: wgpu::AddressMode::Repeat,
+            address_mode_w: wgpu::AddressMode::Repeat,
+            mag_filter: filter,
+            min_filter: filter,
+            mipmap_filter,
+            anisotropy_clamp,
+            ..Default::default()
+        }
+    }
+}
+
+/// number of mip levels down to 1x1 for a texture of the given size
+pub fn mip_level_count(width : u32, height : u32) -> u32 {
+    u32::BITS - width.max(height).max(1).leading_zeros()
+}
+
+fn srgb_to_linear(c : u8) -> f32 {
+    let c = c as f32 / 255.0;
+    if c <= 0.04045 { c / 12.92 } else { ((c + 0.055) / 1.055).powf(2.4) }
+}
+
+fn linear_to_srgb(c : f32) -> u8 {
+    let c = c.clamp(0.0, 1.0);
+    let c = if c <= 0.0031308 { c * 12.92 } else { 1.055 * c.powf(1.0 / 2.4) - 0.055 };
+    (c * 255.0).round() as u8
+}
+
+/// `count` levels, each a filtered downsample of the previous one.
+/// sRGB colors are filtered in linear space so the mips don't get darker, alpha is always linear.
+fn mip_chain(rgba : image::RgbaImage, count : u32, srgb : bool) -> Vec<image::RgbaImage> {
+    let (width, height) = rgba.dimensions();
+    let size = |mip_level : u32| ((width >> mip_level).max(1), (height >> mip_level).max(1));
+    let filter = image::imageops::FilterType::Triangle;
+
+    if !srgb {
+        let mut levels = vec![rgba];
+        for mip_level in 1..count {
+            let (w, h) = size(mip_level);
+            levels.push(image::imageops::resize(&levels[levels.len() - 1], w, h, filter));
+        }
+        return levels;
+    }
+
+    let mut linear = image::Rgba32FImage::from_fn(width, height, |x, y| {
+        let [r, g, b, a] = rgba.get_pixel(x, y).0;
+        image::Rgba([srgb_to_linear(r), srgb_to_linear(g), srgb_to_linear(b), a as f32 / 255.0])
+    });
+    let mut levels = vec![rgba];
+    for mip_level in 1..count {
+        let (w, h) = size(mip_level);
+        linear = image::imageops::resize(&linear, w, h, filter);
+        levels.push(image::RgbaImage::from_fn(w, h, |x, y| {
+            let [r, g, b, a] = linear.get_pixel(x, y).0;
+            image::Rgba([linear_to_srgb(r), linear_to_srgb(g), linear_to_srgb(b), (a.clamp(0.0, 1.0) * 255.0).round() as u8])
+        }));
+    }
+    levels
+}
+
+// file contents, errors keep the path
+fn read_texture_file(path : &Path) -> Result<Vec<u8>, WvtError> {
+    std::fs::read(path).map_err(|source| match source.kind() {
+        std::io::ErrorKind::NotFound => WvtError::MissingTexture { path : path.into() },
+        _ => WvtError::TextureRead { path : path.into(), source },
+    })
+}
+
 #[derive(Debug)]
 pub struct Texture2d {
     pub texture : wgpu::Texture,
@@ -18,8 +111,43 @@ impl Texture2d {
         label: &str
     ) -> Result<Self, WvtError> {
         let img = image::load_from_memory(bytes)?;
-        let res = Self::from_image(device, queue, &img, Some(label));
-        res
+        Self::from_image(device, queue, &img, Some(label))
+    }
+
+    pub fn from_path(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        path: &Path,
+    ) -> Result<Self, WvtError> {
+        Self::load_path(device, queue, path, wgpu::TextureFormat::Rgba8UnormSrgb)
+    }
+
+    /// load without sRGB decoding, for textures holding data like normal maps
+    pub fn from_path_linear(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        path: &Path,
+    ) -> Result<Self, WvtError> {
+        Self::load_path(device, queue, path, wgpu::TextureFormat::Rgba8Unorm)
+    }
+
+    fn load_path(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        path: &Path,
+        format: wgpu::TextureFormat,
+    ) -> Result<Self, WvtError> {
+        let bytes = read_texture_file(path)?;
+        let img = image::load_from_memory(&bytes)
+            .map_err(|source| WvtError::TextureLoad { path : path.into(), source })?;
+        Self::from_image_with_format(device, queue, &img, Some(&path.to_string_lossy()), SamplerPreset::default(), format)
+    }
+
+    /// 1x1 white texture, neutral element when multiplying with a color
+    pub fn white(device: &wgpu::Device, queue: &wgpu::Queue) -> Self {
+        let img = image::RgbaImage::from_pixel(1, 1, image::Rgba([255, 255, 255, 255]));
+        Self::from_image(device, queue, &img.into(), Some("white texture"))
+            .expect("1x1 texture")
     }
 
     pub fn from_image(
@@ -28,8 +156,33 @@ impl Texture2d {
         img: &image::DynamicImage,
         label: Option<&str>
     ) -> Result<Self, WvtError> {
+        Self::from_image_with_sampler(device, queue, img, label, SamplerPreset::default())
+    }
+
+    pub fn from_image_with_sampler(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        img: &image::DynamicImage,
+        label: Option<&str>,
+        sampler: SamplerPreset,
+    ) -> Result<Self, WvtError> {
+        Self::from_image_with_format(device, queue, img, label, sampler, wgpu::TextureFormat::Rgba8UnormSrgb)
+    }
+
+    /// upload `img` with a full mip chain, downsampled on the CPU.
+    /// `format` has to be one of the 8 bit RGBA formats.
+    pub fn from_image_with_format(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        img: &image::DynamicImage,
+        label: Option<&str>,
+        sampler: SamplerPreset,
+        format: wgpu::TextureFormat,
+    ) -> Result<Self, WvtError> {
+        debug_assert!(matches!(format, wgpu::TextureFormat::Rgba8UnormSrgb | wgpu::TextureFormat::Rgba8Unorm));
         let rgba = img.to_rgba8();
         let dimensions = img.dimensions();
+        let mip_level_count = mip_level_count(dimensions.0, dimensions.1);
 
         let size = wgpu::Extent3d {
             width: dimensions.0,
@@ -40,52 +193,62 @@ impl Texture2d {
             &wgpu::TextureDescriptor {
                 label,
                 size,
-                mip_level_count: 1,
+                mip_level_count,
                 sample_count: 1,
                 dimension: wgpu::TextureDimension::D2,
-                format: wgpu::TextureFormat::Rgba8UnormSrgb,
+                format,
                 usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST | wgpu::TextureUsages::RENDER_ATTACHMENT,
                 view_formats: &[],
             }
         );
 
-        queue.write_texture(
-            wgpu::ImageCopyTexture {
-                aspect: wgpu::TextureAspect::All,
-                texture: &texture,
-                mip_level: 0,
-                origin: wgpu::Origin3d::ZERO,
-            },
-            &rgba,
-            wgpu::ImageDataLayout {
-                offset: 0,
-                bytes_per_row: Some(4 * dimensions.0),
-                rows_per_image: Some(dimensions.1),
-            },
-            size,
-        );
+        let levels = mip_chain(rgba, mip_level_count, format.is_srgb());
+        for (mip_level, level) in (0..).zip(&levels) {
+            queue.write_texture(
+                wgpu::ImageCopyTexture {
+                    aspect: wgpu::TextureAspect::All,
+                    texture: &texture,
+                    mip_level,
+                    origin: wgpu::Origin3d::ZERO,
+                },
+                level,
+                wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(4 * level.width()),
+                    rows_per_image: Some(level.height()),
+                },
+                wgpu::Extent3d {
+                    width: level.width(),
+                    height: level.height(),
+                    depth_or_array_layers: 1,
+                },
+            );
+        }
 
         let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
-        let sampler = device.create_sampler(
-            &wgpu::SamplerDescriptor {
-                address_mode_u: wgpu::AddressMode::Repeat,
-                address_mode_v: wgpu::AddressMode::Repeat,
-                address_mode_w: wgpu::AddressMode::Repeat,
-                mag_filter: wgpu::FilterMode::Linear,
-                min_filter: wgpu::FilterMode::Nearest,
-                mipmap_filter: wgpu::FilterMode::Nearest,
-                ..Default::default()
-            }
-        );
+        let sampler = device.create_sampler(&sampler.descriptor());
         
         Ok(Self { texture, view, sampler })
     }
 
+    /// replace the sampler, bind groups using the old one have to be recreated
+    pub fn set_sampler(&mut self, device: &wgpu::Device, sampler : SamplerPreset) {
+        self.sampler = device.create_sampler(&sampler.descriptor());
+    }
+
+    pub fn mip_level_count(&self) -> u32 {
+        self.texture.mip_level_count()
+    }
+
 
     pub fn create_depth_texture(device: &wgpu::Device, config: &wgpu::SurfaceConfiguration, label: &str) -> Self {
+        Self::create_depth_texture_with_size(device, config.width, config.height, label)
+    }
+
+    pub fn create_depth_texture_with_size(device: &wgpu::Device, width: u32, height: u32, label: &str) -> Self {
         let size = wgpu::Extent3d {
-            width: config.width,
-            height: config.height,
+            width,
+            height,
             depth_or_array_layers: 1,
         };
 
@@ -118,4 +281,66 @@ impl Texture2d {
     //     Self {
     //     }
     // }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_errors_keep_the_path() {
+        let missing = Path::new("src/assets/no_such_texture.png");
+        match read_texture_file(missing) {
+            Err(WvtError::MissingTexture { path }) => assert_eq!(path, missing),
+            other => panic!("expected MissingTexture, got {other:?}"),
+        }
+
+        // a directory can't be read as a file
+        let directory = Path::new("src/assets");
+        let error = read_texture_file(directory).unwrap_err();
+        assert!(matches!(&error, WvtError::TextureRead { path, .. } if path == directory), "{error:?}");
+        assert!(error.to_string().contains("src/assets"), "{error}");
+    }
+
+    #[test]
+    fn mip_levels_go_down_to_1x1() {
+        assert_eq!(mip_level_count(1, 1), 1);
+        assert_eq!(mip_level_count(0, 0), 1);
+        assert_eq!(mip_level_count(2, 2), 2);
+        assert_eq!(mip_level_count(256, 256), 9);
+        // non power of two sizes round down each level
+        assert_eq!(mip_level_count(300, 200), 9);
+        assert_eq!(mip_level_count(255, 255), 8);
+        assert_eq!(mip_level_count(1, 100), 7);
+        assert_eq!(mip_level_count(100, 1), 7);
+    }
+
+    #[test]
+    fn mip_chain_sizes() {
+        let img = image::RgbaImage::new(5, 1);
+        let sizes : Vec<_> = mip_chain(img, mip_level_count(5, 1), true).iter().map(|l| l.dimensions()).collect();
+        assert_eq!(sizes, [(5, 1), (2, 1), (1, 1)]);
+    }
+
+    #[test]
+    fn srgb_mips_are_filtered_in_linear_space() {
+        // black and white checker, averages to half the light
+        let checker = image::RgbaImage::from_fn(2, 2, |x, y| {
+            let c = if (x + y) % 2 == 0 { 0 } else { 255 };
+            image::Rgba([c, c, c, 255])
+        });
+        let srgb = mip_chain(checker.clone(), 2, true);
+        assert_eq!(srgb[0], checker);
+        let [r, g, b, a] = srgb[1].get_pixel(0, 0).0;
+        assert_eq!((r, g, b, a), (188, 188, 188, 255));
+
+        let linear = mip_chain(checker, 2, false);
+        assert!(linear[1].get_pixel(0, 0).0[0].abs_diff(128) <= 1);
+    }
+
+    #[test]
+    fn srgb_conversion_round_trips() {
+        for c in 0..=255u8 {
+            assert_eq!(linear_to_srgb(srgb_to_linear(c)), c);
+        }
+    }
+}
@@ -0,0 +1,182 @@
+use std::time::{Duration, Instant};
+
+/// Accumulator based fixed timestep, decouples the simulation rate from the
+/// frame rate. Every frame call `tick` to get the number of simulation steps to
+/// run, then render with `alpha` to interpolate between the last two steps.
+#[derive(Debug, Clone)]
+pub struct FixedTimestep {
+    tick_rate : f64,
+    max_steps : u32,
+    accumulator : f64,
+    last : Option<Instant>,
+}
+
+impl Default for FixedTimestep {
+    fn default() -> Self {
+        Self::new(120.0, 8)
+    }
+}
+
+impl FixedTimestep {
+    /// `tick_rate` in steps per second, `max_steps` limits the catch-up steps per frame
+    pub fn new(tick_rate : f64, max_steps : u32) -> Self {
+        assert!(tick_rate > 0.0, "tick rate must be positive");
+        Self {
+            tick_rate,
+            max_steps : max_steps.max(1),
+            accumulator : 0.0,
+            last : None,
+        }
+    }
+
+    pub fn tick_rate(&self) -> f64 {
+        self.tick_rate
+    }
+
+    pub fn max_steps(&self) -> u32 {
+        self.max_steps
+    }
+
+    /// duration of one simulation step in seconds
+    pub fn dt(&self) -> f32 {
+        (1.0 / self.tick_rate) as f32
+    }
+
+    /// fraction (0..1) of a step left in the accumulator, used to blend
+    /// between the previous and current simulation state
+    pub fn alpha(&self) -> f32 {
+        (self.accumulator * self.tick_rate) as f32
+    }
+
+    /// measure the time since the previous call and return the number of steps to run
+    pub fn tick(&mut self, now : Instant) -> u32 {
+        let elapsed = match self.last {
+            Some(last) => now.saturating_duration_since(last),
+            None => Duration::ZERO,
+        };
+        self.last = Some(now);
+        self.advance(elapsed)
+    }
+
+    /// add `elapsed` to the accumulator and return the number of steps to run
+    pub fn advance(&mut self, elapsed : Duration) -> u32 {
+        let step = 1.0 / self.tick_rate;
+        self.accumulator += elapsed.as_secs_f64();
+
+        let mut steps = 0;
+        while self.accumulator >= step && steps < self.max_steps {
+            self.accumulator -= step;
+            steps += 1;
+        }
+
+        // too far behind (e.g. after a breakpoint or window drag), drop the
+        // backlog instead of trying to catch up over the next frames
+        if self.accumulator >= step {
+            self.accumulator %= step;
+        }
+        steps
+    }
+
+    /// forget the accumulated time, e.g. after a pause
+    pub fn reset(&mut self) {
+        self.accumulator = 0.0;
+        self.last = None;
+    }
+}
+
+/// Frames per second, averaged over `interval` so the number is readable
+#[derive(Debug, Clone)]
+pub struct FpsCounter {
+    interval : Duration,
+    frames : u32,
+    start : Option<Instant>,
+    fps : f32,
+}
+
+impl Default for FpsCounter {
+    fn default() -> Self {
+        Self::new(Duration::from_millis(500))
+    }
+}
+
+impl FpsCounter {
+    pub fn new(interval : Duration) -> Self {
+        Self { interval, frames : 0, start : None, fps : 0.0 }
+    }
+
+    /// count a frame presented at `now`
+    pub fn frame(&mut self, now : Instant) {
+        let start = *self.start.get_or_insert(now);
+        self.frames += 1;
+        let elapsed = now.saturating_duration_since(start);
+        if elapsed >= self.interval {
+            self.fps = self.frames as f32 / elapsed.as_secs_f32();
+            self.frames = 0;
+            self.start = Some(now);
+        }
+    }
+
+    /// average of the last full interval, 0 until then
+    pub fn fps(&self) -> f32 {
+        self.fps
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn steps(seconds : f64) -> Duration {
+        Duration::from_secs_f64(seconds)
+    }
+
+    #[test]
+    fn first_tick_runs_no_steps() {
+        let mut timestep = FixedTimestep::new(100.0, 8);
+        assert_eq!(timestep.tick(Instant::now()), 0);
+        assert_eq!(timestep.alpha(), 0.0);
+    }
+
+    #[test]
+    fn tick_measures_time_between_calls() {
+        let mut timestep = FixedTimestep::new(100.0, 8);
+        let start = Instant::now();
+        timestep.tick(start);
+        assert_eq!(timestep.tick(start + steps(0.035)), 3);
+        assert!((timestep.alpha() - 0.5).abs() < 1e-3);
+    }
+
+    #[test]
+    fn leftover_time_carries_over_to_the_next_frame() {
+        let mut timestep = FixedTimestep::new(100.0, 8);
+        assert_eq!(timestep.advance(steps(0.006)), 0);
+        assert!((timestep.alpha() - 0.6).abs() < 1e-3);
+        assert_eq!(timestep.advance(steps(0.006)), 1);
+        assert!((timestep.alpha() - 0.2).abs() < 1e-3);
+    }
+
+    #[test]
+    fn catch_up_is_clamped_and_the_backlog_dropped() {
+        let mut timestep = FixedTimestep::new(100.0, 4);
+        assert_eq!(timestep.advance(steps(1.0)), 4);
+        // at most one step is left over, 1.0 when the remainder rounds up
+        assert!((0.0..=1.0).contains(&timestep.alpha()));
+        // no burst of steps on the following frames
+        assert_eq!(timestep.advance(Duration::ZERO), 0);
+    }
+
+    #[test]
+    fn reset_forgets_accumulated_time() {
+        let mut timestep = FixedTimestep::new(100.0, 8);
+        timestep.advance(steps(0.005));
+        timestep.reset();
+        assert_eq!(timestep.alpha(), 0.0);
+        assert_eq!(timestep.tick(Instant::now()), 0);
+    }
+
+    #[test]
+    fn dt_is_the_inverse_tick_rate() {
+        assert_eq!(FixedTimestep::new(120.0, 8).dt(), 1.0 / 120.0);
+        assert_eq!(FixedTimestep::new(0.5, 0).max_steps(), 1);
+    }
+}
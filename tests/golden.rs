@@ -0,0 +1,113 @@
+// Golden image tests: render on the software fallback adapter and compare with the
+// reference images in tests/golden. Skipped when no adapter is available,
+// run with UPDATE_GOLDEN=1 to rewrite the references after an intended change.
+
+use glam::{Quat, Vec3};
+use rust_wgpu_pong3d::{
+    assets::AssetServer,
+    camera::{Camera, Projection},
+    geometries::CubeGeometry,
+    gpu::Gpu,
+    render_target::RenderTarget,
+    renderers::light_buffer::{DirectionalLight, LightBuffer, Lights},
+    renderers::material_shader::MaterialShader,
+    renderers::pipeline_cache::PipelineCache,
+    scene::{Scene, Transform},
+};
+
+const WIDTH : u32 = 160;
+const HEIGHT : u32 = 120;
+// per channel difference that still counts as the same pixel
+const CHANNEL_TOLERANCE : u8 = 8;
+// fraction of pixels allowed to differ more, for rasterization differences on edges
+const MAX_DIFFERENT_PIXELS : f64 = 0.005;
+
+fn render_cube(gpu : &Gpu) -> image::RgbaImage {
+    let Gpu { device, queue, .. } = gpu;
+    let target = RenderTarget::offscreen(device, WIDTH, HEIGHT, wgpu::TextureFormat::Rgba8UnormSrgb);
+    let mut pipelines = PipelineCache::new();
+    let camera = Camera::new(
+        device,
+        &mut pipelines,
+        Projection::perspective(90.0_f32.to_radians(), 0.01, 5.0),
+        WIDTH,
+        HEIGHT,
+        Vec3::new(0.0, 0.0, -3.0),
+        Vec3::ZERO,
+        Vec3::Y,
+        "Golden camera");
+    let lights = Lights::new(DirectionalLight::new(Vec3::new(-0.5, -0.5, 1.0), Vec3::ONE, 1.0));
+    let lights = LightBuffer::new(device, lights, "Golden lights");
+
+    let mut assets = AssetServer::new();
+    let mut scene = Scene::new();
+    let root = scene.add_node("cube", Transform::from_rotation(
+        Quat::from_rotation_y(30.0_f32.to_radians()) * Quat::from_rotation_x(20.0_f32.to_radians())), None);
+
+    let geo = CubeGeometry::new();
+    let mut renderer = MaterialShader::new(
+        device,
+        &mut pipelines,
+        target.format(),
+        geo.positions,
+        geo.normals,
+        geo.colors,
+        geo.tex_coords,
+        geo.indices,
+        assets.white_texture(device, queue),
+        &lights);
+    renderer.set_node(Some(root));
+    scene.update_world_matrices();
+    renderer.update_transform(queue, &scene);
+
+    let frame = target.acquire().expect("offscreen frame");
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+        label: Some("Golden Encoder"),
+    });
+    {
+        let mut render_pass = target.begin_render_pass(&mut encoder, &frame, wgpu::Color::BLACK);
+        renderer.draw(&mut render_pass, &camera);
+    }
+    queue.submit(std::iter::once(encoder.finish()));
+    target.read_pixels(device, queue).expect("readback")
+}
+
+// fraction of pixels where any channel differs by more than the tolerance
+fn different_pixels(image : &image::RgbaImage, reference : &image::RgbaImage) -> f64 {
+    let different = image.pixels().zip(reference.pixels())
+        .filter(|(a, b)| a.0.iter().zip(b.0).any(|(a, b)| a.abs_diff(b) > CHANNEL_TOLERANCE))
+        .count();
+    different as f64 / (image.width() * image.height()) as f64
+}
+
+fn assert_matches_golden(image : &image::RgbaImage, name : &str) {
+    let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden").join(name);
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        image.save(&path).expect("write reference image");
+        return;
+    }
+
+    let reference = image::open(&path)
+        .unwrap_or_else(|e| panic!("missing reference {}: {e}, run with UPDATE_GOLDEN=1", path.display()))
+        .to_rgba8();
+    assert_eq!(image.dimensions(), reference.dimensions(), "size of {name}");
+
+    let different = different_pixels(image, &reference);
+    if different > MAX_DIFFERENT_PIXELS {
+        let actual = path.with_extension("actual.png");
+        image.save(&actual).expect("write actual image");
+        panic!("{:.2}% of the pixels differ from {}, see {}", 100.0 * different, path.display(), actual.display());
+    }
+}
+
+#[test]
+fn cube_matches_golden_image() {
+    let gpu = match pollster::block_on(Gpu::headless(true)) {
+        Ok(gpu) => gpu,
+        Err(e) => {
+            eprintln!("skipping golden image test, no fallback adapter: {e}");
+            return;
+        }
+    };
+    assert_matches_golden(&render_cube(&gpu), "cube.png");
+}